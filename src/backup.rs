@@ -0,0 +1,646 @@
+//! Backup and migration of keys using DUMP/RESTORE
+//!
+//! [`RedisClient::export_keys`] walks the keys matching a pattern with SCAN
+//! and writes their DUMP payloads to any [`Write`]r, and
+//! [`RedisClient::import_keys`] restores them from a [`Read`]er, so a subset
+//! of keys can be moved between environments or kept as a fixture file.
+//!
+//! ```no_run
+//! use rhai_redis::RedisClient;
+//! use std::fs::File;
+//!
+//! let mut source = RedisClient::open("redis://staging:6379")?;
+//! source.export_keys("user:*", File::create("users.rdump")?)?;
+//!
+//! let mut target = RedisClient::open("redis://localhost:6379")?;
+//! target.import_keys(File::open("users.rdump")?, true)?;
+//! # Ok::<(), rhai_redis::Error>(())
+//! ```
+//!
+//! # File format
+//!
+//! The file starts with the magic bytes `RHAIDUMP` and a version byte (`1`),
+//! followed by one record per key until the end of the file. All integers
+//! are big-endian:
+//!
+//! - `u32` key length, then the key bytes
+//! - `i64` expiry as a Unix timestamp in milliseconds, or `-1` for no TTL
+//! - `u32` payload length, then the DUMP payload
+//!
+//! Expiry times are absolute, so keys keep their original deadline and keys
+//! that expired while the backup sat on disk are skipped on import. DUMP
+//! payloads are only portable between Redis versions with a compatible RDB
+//! format.
+//!
+//! # JSON fixtures
+//!
+//! [`RedisClient::export_json`] writes a reviewable JSON document instead,
+//! and [`RedisClient::import_json`] recreates the keys from it. Both are
+//! available to scripts as `redis.export_json(pattern)` and
+//! `redis.import_json(json)`:
+//!
+//! ```json
+//! {"version": 1, "keys": [
+//!   {"key": "user:1", "type": "hash", "ttl": 60000, "value": {"name": "Ada"}},
+//!   {"key": "scores", "type": "zset", "value": [{"member": "ada", "score": 12.0}]},
+//!   {"key": "orders", "type": "stream", "value": [{"id": "1-0", "fields": {"n": "1"}}]}
+//! ]}
+//! ```
+//!
+//! Strings, lists, hashes, sets, sorted sets and stream entries are
+//! supported; `ttl` is the remaining time to live in milliseconds and is
+//! omitted for keys without one. Other types and stream consumer groups are
+//! not exported.
+//!
+//! Keys whose contents are not valid UTF-8 are written with
+//! `"encoding": "base64"`, and every string in their `value` (members, fields
+//! and values, but not stream IDs) is then base64-encoded so binary data
+//! survives the round trip.
+
+use crate::client::RedisClient;
+use crate::{Error, Result};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map};
+use std::io::{ErrorKind, Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAGIC: &[u8; 8] = b"RHAIDUMP";
+const VERSION: u8 = 1;
+const JSON_VERSION: i64 = 1;
+
+/// How many keys to request per SCAN round trip
+const SCAN_COUNT: i64 = 500;
+
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> Result<()> {
+    let len =
+        u32::try_from(bytes.len()).map_err(|_| Error::Backup("value larger than 4 GiB".into()))?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+/// Read a length-prefixed byte string, or `None` at a clean end of input
+fn read_bytes(reader: &mut impl Read) -> Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    let first = loop {
+        match reader.read(&mut len[..1]) {
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            result => break result?,
+        }
+    };
+    if first == 0 {
+        return Ok(None);
+    }
+    reader.read_exact(&mut len[1..])?;
+    // The length is untrusted, so grow the buffer as data arrives rather than up front
+    let len = u64::from(u32::from_be_bytes(len));
+    let mut bytes = Vec::new();
+    reader.by_ref().take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(Error::Backup("truncated record".into()));
+    }
+    Ok(Some(bytes))
+}
+
+/// Sorted-set scores as JSON numbers, with infinities as strings
+fn score_to_dynamic(score: f64) -> Dynamic {
+    if score.is_finite() {
+        score.into()
+    } else {
+        score.to_string().into()
+    }
+}
+
+fn dynamic_to_score(score: &Dynamic) -> Option<f64> {
+    score
+        .as_float()
+        .ok()
+        .or_else(|| score.as_int().ok().map(|i| i as f64))
+        .or_else(|| score.to_string().parse().ok())
+}
+
+fn invalid(key: &str, reason: &str) -> Error {
+    Error::Backup(format!("{}: {}", key, reason))
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+impl RedisClient {
+    /// Write the keys matching `pattern` to `writer`, returning how many were exported.
+    ///
+    /// Keys deleted between SCAN and DUMP are skipped.
+    pub fn export_keys(&mut self, pattern: &str, mut writer: impl Write) -> Result<usize> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+
+        let mut exported = 0;
+        let mut cursor = 0u64;
+        loop {
+            let mut conn = self.conn.lock().unwrap();
+            let (next, keys): (u64, Vec<Vec<u8>>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(pattern)
+                .arg("COUNT")
+                .arg(SCAN_COUNT)
+                .query(&mut *conn)?;
+
+            let mut pipe = redis::pipe();
+            for key in &keys {
+                pipe.cmd("DUMP").arg(key).cmd("PEXPIRETIME").arg(key);
+            }
+            let replies: Vec<(Option<Vec<u8>>, i64)> = if keys.is_empty() {
+                Vec::new()
+            } else {
+                let flat: Vec<redis::Value> = pipe.query(&mut *conn)?;
+                flat.chunks(2)
+                    .map(|pair| {
+                        Ok((
+                            redis::from_redis_value(&pair[0])?,
+                            redis::from_redis_value(&pair[1])?,
+                        ))
+                    })
+                    .collect::<redis::RedisResult<_>>()?
+            };
+            drop(conn);
+
+            for (key, (payload, expires_at)) in keys.iter().zip(replies) {
+                // -2 means the key disappeared after SCAN returned it
+                let Some(payload) = payload.filter(|_| expires_at != -2) else {
+                    continue;
+                };
+                write_bytes(&mut writer, key)?;
+                writer.write_all(&expires_at.to_be_bytes())?;
+                write_bytes(&mut writer, &payload)?;
+                exported += 1;
+            }
+
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+
+        writer.flush()?;
+        Ok(exported)
+    }
+
+    /// Restore keys written by [`export_keys`](Self::export_keys), returning how many were imported.
+    ///
+    /// With `replace`, existing keys are overwritten; otherwise an existing
+    /// key fails the import. Keys whose expiry has already passed are skipped.
+    pub fn import_keys(&mut self, mut reader: impl Read, replace: bool) -> Result<usize> {
+        let mut header = [0u8; 9];
+        reader.read_exact(&mut header)?;
+        if &header[..8] != MAGIC {
+            return Err(Error::Backup("missing RHAIDUMP header".into()));
+        }
+        if header[8] != VERSION {
+            return Err(Error::Backup(format!("unsupported version {}", header[8])));
+        }
+
+        let mut imported = 0;
+        while let Some(key) = read_bytes(&mut reader)? {
+            let mut expires_at = [0u8; 8];
+            reader.read_exact(&mut expires_at)?;
+            let expires_at = i64::from_be_bytes(expires_at);
+            let payload =
+                read_bytes(&mut reader)?.ok_or_else(|| Error::Backup("truncated record".into()))?;
+
+            if expires_at >= 0 && expires_at <= now_ms() {
+                continue;
+            }
+
+            let mut cmd = redis::cmd("RESTORE");
+            cmd.arg(&key).arg(expires_at.max(0)).arg(payload);
+            if expires_at >= 0 {
+                cmd.arg("ABSTTL");
+            }
+            if replace {
+                cmd.arg("REPLACE");
+            }
+            let mut conn = self.conn.lock().unwrap();
+            cmd.query::<()>(&mut *conn)?;
+            imported += 1;
+        }
+        Ok(imported)
+    }
+
+    /// Collect all keys matching `pattern` with SCAN
+    fn scan_keys(&mut self, pattern: &str) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        let mut cursor = 0u64;
+        loop {
+            let mut conn = self.conn.lock().unwrap();
+            let (next, batch): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(pattern)
+                .arg("COUNT")
+                .arg(SCAN_COUNT)
+                .query(&mut *conn)?;
+            keys.extend(batch);
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+        keys.sort();
+        keys.dedup();
+        Ok(keys)
+    }
+
+    /// Export the keys matching `pattern` as a JSON document
+    pub fn export_json(&mut self, pattern: &str) -> Result<String> {
+        let mut entries = Array::new();
+        for key in self.scan_keys(pattern)? {
+            let key_type = self.key_type(&key);
+            let Some(value) = self.read_raw(&key, &key_type)? else {
+                continue;
+            };
+            // The key expired or was deleted between SCAN and the read
+            if value.is_empty() {
+                continue;
+            }
+
+            let mut entry = Map::new();
+            entry.insert("key".into(), key.as_str().into());
+            entry.insert("type".into(), key_type.into());
+            let ttl = self.pttl(&key);
+            if ttl >= 0 {
+                entry.insert("ttl".into(), ttl.into());
+            }
+            let binary = !value.is_utf8();
+            if binary {
+                entry.insert("encoding".into(), "base64".into());
+            }
+            entry.insert("value".into(), value.to_dynamic(binary));
+            entries.push(entry.into());
+        }
+
+        let mut document = Map::new();
+        document.insert("version".into(), JSON_VERSION.into());
+        document.insert("keys".into(), entries.into());
+        Ok(crate::json::to_json(&document.into()))
+    }
+
+    /// Read the contents of `key` as raw bytes, or `None` for unsupported types
+    fn read_raw(&mut self, key: &str, key_type: &str) -> Result<Option<RawValue>> {
+        let mut conn = self.conn.lock().unwrap();
+        let value = match key_type {
+            "string" => {
+                let value: Option<Vec<u8>> = redis::cmd("GET").arg(key).query(&mut *conn)?;
+                RawValue::String(value)
+            }
+            "list" => RawValue::List(
+                redis::cmd("LRANGE")
+                    .arg(key)
+                    .arg(0)
+                    .arg(-1)
+                    .query(&mut *conn)?,
+            ),
+            "hash" => RawValue::Hash(redis::cmd("HGETALL").arg(key).query(&mut *conn)?),
+            "set" => {
+                let mut members: Vec<Vec<u8>> =
+                    redis::cmd("SMEMBERS").arg(key).query(&mut *conn)?;
+                members.sort();
+                RawValue::Set(members)
+            }
+            "zset" => RawValue::Zset(
+                redis::cmd("ZRANGE")
+                    .arg(key)
+                    .arg(0)
+                    .arg(-1)
+                    .arg("WITHSCORES")
+                    .query(&mut *conn)?,
+            ),
+            "stream" => RawValue::Stream(
+                redis::cmd("XRANGE")
+                    .arg(key)
+                    .arg("-")
+                    .arg("+")
+                    .query(&mut *conn)?,
+            ),
+            _ => return Ok(None),
+        };
+        Ok(Some(value))
+    }
+
+    /// Recreate keys from a document produced by [`export_json`](Self::export_json),
+    /// returning how many were imported.
+    ///
+    /// With `replace`, existing keys are overwritten; otherwise an existing
+    /// key fails the import and nothing is written. The check and the writes
+    /// run in one WATCH-guarded transaction, so a key created concurrently is
+    /// never overwritten.
+    pub fn import_json(&mut self, json: &str, replace: bool) -> Result<usize> {
+        let document = Engine::new_raw().parse_json(json, true)?;
+        if document.get("version").and_then(|v| v.as_int().ok()) != Some(JSON_VERSION) {
+            return Err(Error::Backup("unsupported JSON version".into()));
+        }
+        let entries = document
+            .get("keys")
+            .and_then(|k| k.clone().try_cast::<Array>())
+            .ok_or_else(|| Error::Backup("missing 'keys' array".into()))?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        let mut names = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let entry = entry
+                .try_cast::<Map>()
+                .ok_or_else(|| Error::Backup("key entries must be objects".into()))?;
+            let key = entry
+                .get("key")
+                .map(|k| k.to_string())
+                .ok_or_else(|| Error::Backup("entry is missing 'key'".into()))?;
+            let value = entry
+                .get("value")
+                .cloned()
+                .ok_or_else(|| invalid(&key, "missing 'value'"))?;
+            let binary = match entry.get("encoding").map(|e| e.to_string()).as_deref() {
+                None => false,
+                Some("base64") => true,
+                Some(other) => {
+                    return Err(invalid(&key, &format!("unsupported encoding '{}'", other)))
+                }
+            };
+            let bytes = |value: &Dynamic| -> Result<Vec<u8>> {
+                let text = value.to_string();
+                if binary {
+                    base64_decode(&text).ok_or_else(|| invalid(&key, "invalid base64"))
+                } else {
+                    Ok(text.into_bytes())
+                }
+            };
+            let array = || {
+                value
+                    .clone()
+                    .try_cast::<Array>()
+                    .ok_or_else(|| invalid(&key, "expected an array"))
+            };
+            let map = |value: &Dynamic| {
+                value
+                    .clone()
+                    .try_cast::<Map>()
+                    .ok_or_else(|| invalid(&key, "expected an object"))
+            };
+            let pairs = |value: &Dynamic| -> Result<Vec<Pair>> {
+                map(value)?
+                    .into_iter()
+                    .map(|(field, item)| Ok((bytes(&field.as_str().into())?, bytes(&item)?)))
+                    .collect()
+            };
+
+            pipe.del(&key).ignore();
+            match entry.get("type").map(|t| t.to_string()).as_deref() {
+                Some("string") => {
+                    pipe.set(&key, bytes(&value)?).ignore();
+                }
+                Some("list") => {
+                    for item in array()? {
+                        pipe.rpush(&key, bytes(&item)?).ignore();
+                    }
+                }
+                Some("hash") => {
+                    for (field, item) in pairs(&value)? {
+                        pipe.hset(&key, field, item).ignore();
+                    }
+                }
+                Some("set") => {
+                    for member in array()? {
+                        pipe.sadd(&key, bytes(&member)?).ignore();
+                    }
+                }
+                Some("zset") => {
+                    for item in array()? {
+                        let item = map(&item)?;
+                        let member = item.get("member").map(&bytes).transpose()?;
+                        let score = item.get("score").and_then(dynamic_to_score);
+                        let (Some(member), Some(score)) = (member, score) else {
+                            return Err(invalid(&key, "zset entries need a member and score"));
+                        };
+                        pipe.zadd(&key, member, score).ignore();
+                    }
+                }
+                Some("stream") => {
+                    for item in array()? {
+                        let item = map(&item)?;
+                        let id = item
+                            .get("id")
+                            .map(|id| id.to_string())
+                            .ok_or_else(|| invalid(&key, "stream entries need an id"))?;
+                        let fields = pairs(item.get("fields").unwrap_or(&Dynamic::UNIT))?;
+                        pipe.xadd(&key, id, &fields).ignore();
+                    }
+                }
+                Some(other) => return Err(invalid(&key, &format!("unsupported type '{}'", other))),
+                None => return Err(invalid(&key, "missing 'type'")),
+            }
+            if let Some(ttl) = entry.get("ttl").and_then(|t| t.as_int().ok()) {
+                pipe.pexpire(&key, ttl.max(1)).ignore();
+            }
+            names.push(key);
+        }
+
+        if names.is_empty() {
+            return Ok(0);
+        }
+        let mut conn = self.conn.lock().unwrap();
+        if replace {
+            pipe.query::<()>(&mut *conn)?;
+            return Ok(names.len());
+        }
+        loop {
+            redis::cmd("WATCH").arg(&names).query::<()>(&mut *conn)?;
+            let existing: i64 = redis::cmd("EXISTS").arg(&names).query(&mut *conn)?;
+            if existing > 0 {
+                redis::cmd("UNWATCH").query::<()>(&mut *conn)?;
+                return Err(Error::Backup(format!(
+                    "{} of the imported keys already exist",
+                    existing
+                )));
+            }
+            // `None` means a watched key changed before EXEC; check again
+            if pipe.query::<Option<()>>(&mut *conn)?.is_some() {
+                return Ok(names.len());
+            }
+        }
+    }
+}
+
+/// A hash or stream entry field and its value
+type Pair = (Vec<u8>, Vec<u8>);
+
+/// Key contents as read from Redis, before any text decoding
+enum RawValue {
+    String(Option<Vec<u8>>),
+    List(Vec<Vec<u8>>),
+    Hash(Vec<Pair>),
+    Set(Vec<Vec<u8>>),
+    Zset(Vec<(Vec<u8>, f64)>),
+    Stream(Vec<(String, Vec<Pair>)>),
+}
+
+impl RawValue {
+    fn is_empty(&self) -> bool {
+        match self {
+            RawValue::String(value) => value.is_none(),
+            RawValue::List(items) | RawValue::Set(items) => items.is_empty(),
+            RawValue::Hash(pairs) => pairs.is_empty(),
+            RawValue::Zset(members) => members.is_empty(),
+            RawValue::Stream(entries) => entries.is_empty(),
+        }
+    }
+
+    /// Every byte string the value holds
+    fn bytes(&self) -> Vec<&[u8]> {
+        match self {
+            RawValue::String(value) => value.iter().map(Vec::as_slice).collect(),
+            RawValue::List(items) | RawValue::Set(items) => {
+                items.iter().map(Vec::as_slice).collect()
+            }
+            RawValue::Hash(fields) => pair_bytes(fields).collect(),
+            RawValue::Zset(members) => members.iter().map(|(m, _)| m.as_slice()).collect(),
+            RawValue::Stream(entries) => entries.iter().flat_map(|(_, f)| pair_bytes(f)).collect(),
+        }
+    }
+
+    fn is_utf8(&self) -> bool {
+        self.bytes().iter().all(|b| std::str::from_utf8(b).is_ok())
+    }
+
+    /// Convert to the JSON shape, base64-encoding every byte string when
+    /// `binary` is set
+    fn to_dynamic(&self, binary: bool) -> Dynamic {
+        let text = |bytes: &[u8]| -> String {
+            if binary {
+                base64_encode(bytes)
+            } else {
+                String::from_utf8_lossy(bytes).into_owned()
+            }
+        };
+        let map = |pairs: &[Pair]| -> Map {
+            pairs
+                .iter()
+                .map(|(field, value)| (text(field).into(), text(value).into()))
+                .collect()
+        };
+        match self {
+            RawValue::String(value) => value.as_deref().map_or(Dynamic::UNIT, |v| text(v).into()),
+            RawValue::List(items) | RawValue::Set(items) => items
+                .iter()
+                .map(|item| Dynamic::from(text(item)))
+                .collect::<Array>()
+                .into(),
+            RawValue::Hash(fields) => map(fields).into(),
+            RawValue::Zset(members) => members
+                .iter()
+                .map(|(member, score)| {
+                    let mut entry = Map::new();
+                    entry.insert("member".into(), text(member).into());
+                    entry.insert("score".into(), score_to_dynamic(*score));
+                    Dynamic::from(entry)
+                })
+                .collect::<Array>()
+                .into(),
+            RawValue::Stream(entries) => entries
+                .iter()
+                .map(|(id, fields)| {
+                    let mut entry = Map::new();
+                    entry.insert("id".into(), id.as_str().into());
+                    entry.insert("fields".into(), map(fields).into());
+                    Dynamic::from(entry)
+                })
+                .collect::<Array>()
+                .into(),
+        }
+    }
+}
+
+fn pair_bytes(pairs: &[Pair]) -> impl Iterator<Item = &[u8]> {
+    pairs.iter().flat_map(|(a, b)| [a.as_slice(), b.as_slice()])
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    for chunk in text.chunks(4) {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 {
+            return None;
+        }
+        let mut n = 0u32;
+        for (i, c) in chunk[..4 - padding].iter().enumerate() {
+            let digit = BASE64.iter().position(|b| b == c)? as u32;
+            n |= digit << (18 - 6 * i);
+        }
+        out.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Some(out)
+}
+
+/// Register JSON export and import methods with the Rhai engine
+pub fn register_backup_methods(engine: &mut Engine) {
+    engine
+        .register_fn(
+            "export_json",
+            |client: &mut RedisClient,
+             pattern: &str|
+             -> std::result::Result<String, Box<EvalAltResult>> {
+                client
+                    .export_json(pattern)
+                    .map_err(|e| e.to_string().into())
+            },
+        )
+        .register_fn(
+            "import_json",
+            |client: &mut RedisClient,
+             json: &str|
+             -> std::result::Result<i64, Box<EvalAltResult>> {
+                client
+                    .import_json(json, false)
+                    .map(|n| n as i64)
+                    .map_err(|e| e.to_string().into())
+            },
+        )
+        .register_fn(
+            "import_json",
+            |client: &mut RedisClient,
+             json: &str,
+             replace: bool|
+             -> std::result::Result<i64, Box<EvalAltResult>> {
+                client
+                    .import_json(json, replace)
+                    .map(|n| n as i64)
+                    .map_err(|e| e.to_string().into())
+            },
+        );
+}
@@ -0,0 +1,269 @@
+//! Cache-aside helper for Redis Rhai integration
+//!
+//! `redis.cached(key, ttl_s, || compute())` returns the value cached under
+//! `key`, or runs the closure and caches its result. Values are stored as a
+//! JSON document together with their expiry time and how long they took to
+//! compute, so they may be strings, numbers, booleans, `()`, or maps and
+//! arrays of these. Other values, such as timestamps, throw instead of being
+//! cached.
+//!
+//! - **Jitter**: each TTL is extended by a random fraction (10% by default)
+//!   so keys written together do not expire together.
+//! - **Early expiration**: a value may be recomputed shortly before it
+//!   expires, with a probability that rises as expiry approaches and with the
+//!   cost of recomputing it (the "XFetch" algorithm). `beta: 0.0` disables this.
+//! - **Stale-while-revalidate**: with `stale_s`, an expired value is kept for
+//!   that many more seconds and returned while another caller recomputes it.
+//! - **Stampede protection**: only the caller holding a short lock on
+//!   `{key}:lock` recomputes. On a miss, other callers wait for its result for
+//!   up to `lock_ms`, then compute the value themselves.
+//!
+//! # Example
+//! ```rhai
+//! let report = redis.cached("report:daily", 300, #{stale_s: 60}, || {
+//!     #{total: redis.scard("orders"), regions: redis.smembers("regions")}
+//! });
+//! print(report.total);
+//! ```
+
+use crate::client::RedisClient;
+use crate::lock::wait_for_retry;
+use rand::Rng;
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, Map, NativeCallContext};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Longest lifetime passed to Redis; larger PX values overflow its clock
+/// arithmetic and are rejected
+const MAX_KEEP_MS: i64 = i64::MAX / 2;
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Tuning for [`RedisClient::get_or_compute`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CacheOptions {
+    /// Largest random extension of the TTL, as a fraction of it
+    pub jitter: f64,
+    /// How eagerly values are recomputed before they expire; 0 disables it
+    pub beta: f64,
+    /// Seconds an expired value may still be served while it is recomputed
+    pub stale_s: i64,
+    /// How long the recompute lock is held, and how long a miss waits for it
+    pub lock_ms: i64,
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        Self {
+            jitter: 0.1,
+            beta: 1.0,
+            stale_s: 0,
+            lock_ms: 5_000,
+        }
+    }
+}
+
+impl CacheOptions {
+    fn from_map(options: &Map) -> Self {
+        let defaults = Self::default();
+        let float = |name: &str| {
+            options.get(name).and_then(|v| {
+                v.as_float()
+                    .ok()
+                    .or_else(|| v.as_int().ok().map(|n| n as f64))
+            })
+        };
+        let int = |name: &str| options.get(name).and_then(|v| v.as_int().ok());
+        Self {
+            jitter: float("jitter").unwrap_or(defaults.jitter),
+            beta: float("beta").unwrap_or(defaults.beta),
+            stale_s: int("stale_s").unwrap_or(defaults.stale_s),
+            lock_ms: int("lock_ms").unwrap_or(defaults.lock_ms),
+        }
+    }
+}
+
+/// A cached value with its expiry and computation time, in milliseconds
+struct Entry {
+    value: Dynamic,
+    expires_at: i64,
+    delta: i64,
+}
+
+impl Entry {
+    fn decode(doc: &str) -> Option<Self> {
+        let mut doc = Engine::new_raw().parse_json(doc, true).ok()?;
+        Some(Self {
+            value: doc.remove("value")?,
+            expires_at: doc.get("expires_at")?.as_int().ok()?,
+            delta: doc.get("delta")?.as_int().ok()?,
+        })
+    }
+
+    fn encode(&self) -> String {
+        let mut doc = Map::new();
+        doc.insert("value".into(), self.value.clone());
+        doc.insert("expires_at".into(), self.expires_at.into());
+        doc.insert("delta".into(), self.delta.into());
+        rhai::format_map_as_json(&doc)
+    }
+
+    /// Whether this caller should recompute the value now
+    fn should_refresh(&self, now: i64, beta: f64) -> bool {
+        if now >= self.expires_at {
+            return true;
+        }
+        if beta <= 0.0 {
+            return false;
+        }
+        let roll = 1.0 - rand::thread_rng().r#gen::<f64>();
+        now as f64 - self.delta as f64 * beta * roll.ln() >= self.expires_at as f64
+    }
+}
+
+impl RedisClient {
+    fn cache_entry(&self, key: &str) -> Option<Entry> {
+        let mut conn = self.conn.lock().unwrap();
+        match redis::cmd("GET")
+            .arg(key)
+            .query::<Option<String>>(&mut *conn)
+        {
+            Ok(None) => None,
+            Ok(Some(doc)) => {
+                let entry = Entry::decode(&doc);
+                if entry.is_none() {
+                    eprintln!(
+                        "Cache error: could not decode the value cached under '{}'",
+                        key
+                    );
+                }
+                entry
+            }
+            Err(e) => {
+                eprintln!("Redis error: {}", e);
+                None
+            }
+        }
+    }
+
+    fn store_cache_entry(&self, key: &str, doc: &str, keep_ms: i64) {
+        let mut conn = self.conn.lock().unwrap();
+        if let Err(e) = redis::cmd("SET")
+            .arg(key)
+            .arg(doc)
+            .arg("PX")
+            .arg(keep_ms.clamp(1, MAX_KEEP_MS))
+            .query::<()>(&mut *conn)
+        {
+            eprintln!("Redis error: {}", e);
+        }
+    }
+
+    /// Return the value cached under `key`, or compute, cache and return it.
+    ///
+    /// The value is kept for `ttl_s` seconds plus jitter. If `compute` fails,
+    /// nothing is cached and the error is returned; the same happens if the
+    /// value has no JSON form and so could not be read back.
+    pub fn get_or_compute<E: From<String>>(
+        &mut self,
+        key: &str,
+        ttl_s: i64,
+        options: &CacheOptions,
+        compute: impl FnOnce() -> Result<Dynamic, E>,
+    ) -> Result<Dynamic, E> {
+        let lock_key = format!("{}:lock", key);
+        let deadline = Instant::now() + Duration::from_millis(options.lock_ms.max(0) as u64);
+
+        let lock = loop {
+            let cached = match self.cache_entry(key) {
+                Some(entry) if !entry.should_refresh(now_ms(), options.beta) => {
+                    return Ok(entry.value)
+                }
+                cached => cached,
+            };
+
+            if let Some(lock) = self.lock(&lock_key, options.lock_ms) {
+                break Some(lock);
+            }
+            // Someone else is recomputing: serve what we have, or wait for theirs
+            if let Some(entry) = cached {
+                return Ok(entry.value);
+            }
+            if self.is_stopped() || !wait_for_retry(Some(deadline)) {
+                break None;
+            }
+        };
+
+        let started = Instant::now();
+        let mut result = compute();
+        if let Ok(value) = &result {
+            let ttl_ms = ttl_s.max(1).saturating_mul(1000).min(MAX_KEEP_MS);
+            // `as` saturates, so a huge jitter cannot wrap around
+            let jitter_ms = (ttl_ms as f64 * options.jitter.max(0.0)) as i64;
+            let ttl_ms = ttl_ms
+                .saturating_add(rand::thread_rng().gen_range(0..=jitter_ms))
+                .min(MAX_KEEP_MS);
+            let entry = Entry {
+                value: value.clone(),
+                expires_at: now_ms().saturating_add(ttl_ms),
+                delta: started.elapsed().as_millis() as i64,
+            };
+            let doc = entry.encode();
+            if Entry::decode(&doc).is_some() {
+                let stale_ms = options.stale_s.max(0).saturating_mul(1000);
+                self.store_cache_entry(key, &doc, ttl_ms.saturating_add(stale_ms));
+            } else {
+                result = Err(E::from(format!(
+                    "Value for '{}' cannot be cached: it has no JSON form",
+                    key
+                )));
+            }
+        }
+        if let Some(mut lock) = lock {
+            lock.unlock();
+        }
+        result
+    }
+
+    /// Script form of [`get_or_compute`](Self::get_or_compute), with options
+    /// `jitter`, `beta`, `stale_s` and `lock_ms`
+    pub fn cached(
+        &mut self,
+        ctx: &NativeCallContext,
+        key: &str,
+        ttl_s: i64,
+        options: Map,
+        callback: FnPtr,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let options = CacheOptions::from_map(&options);
+        self.get_or_compute(key, ttl_s, &options, || {
+            callback.call_within_context(ctx, ())
+        })
+    }
+}
+
+/// Register the cache-aside helper with the Rhai engine
+pub fn register_cache_methods(engine: &mut Engine) {
+    engine
+        .register_fn(
+            "cached",
+            |ctx: NativeCallContext,
+             client: &mut RedisClient,
+             key: &str,
+             ttl_s: i64,
+             callback: FnPtr| { client.cached(&ctx, key, ttl_s, Map::new(), callback) },
+        )
+        .register_fn(
+            "cached",
+            |ctx: NativeCallContext,
+             client: &mut RedisClient,
+             key: &str,
+             ttl_s: i64,
+             options: Map,
+             callback: FnPtr| { client.cached(&ctx, key, ttl_s, options, callback) },
+        );
+}
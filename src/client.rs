@@ -1,18 +1,191 @@
 //! Redis client for Rhai scripting
 
 use redis::Connection;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often blocking commands wake up to check for shutdown, cancellation and timeouts
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long to block before the next check, or `None` once `deadline` or the
+/// run's timeout has passed, or the run was cancelled
+pub(crate) fn next_wait(deadline: Option<Instant>, run: &CancelToken) -> Option<Duration> {
+    if run.is_cancelled() {
+        return None;
+    }
+    match deadline.into_iter().chain(run.deadline()).min() {
+        Some(deadline) => {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            (!remaining.is_zero()).then(|| remaining.min(POLL_INTERVAL))
+        }
+        None => Some(POLL_INTERVAL),
+    }
+}
 
 /// Thread-safe Redis client for Rhai scripting
 #[derive(Clone)]
 pub struct RedisClient {
     pub(crate) conn: Arc<Mutex<Connection>>,
+    pub(crate) client: Option<redis::Client>,
+    pub(crate) shutdown: ShutdownHandle,
+    /// Cancellation for the script using this client, set by its engine
+    pub(crate) run: CancelToken,
 }
 
 impl RedisClient {
     pub fn new(conn: Connection) -> Self {
         Self {
             conn: Arc::new(Mutex::new(conn)),
+            client: None,
+            shutdown: ShutdownHandle::new(),
+            run: CancelToken::new(),
+        }
+    }
+
+    /// Create a client from a `redis::Client`.
+    ///
+    /// Unlike [`RedisClient::new`], this keeps the `redis::Client` around so
+    /// commands that need a dedicated connection (such as `subscribe`) can
+    /// open one.
+    pub fn from_client(client: redis::Client) -> redis::RedisResult<Self> {
+        let conn = client.get_connection()?;
+        Ok(Self {
+            client: Some(client),
+            ..Self::new(conn)
+        })
+    }
+
+    /// Connect to the Redis server at `url`
+    pub fn open(url: &str) -> redis::RedisResult<Self> {
+        Self::from_client(redis::Client::open(url)?)
+    }
+
+    /// Open a new connection to the server, separate from the shared one
+    pub(crate) fn dedicated_connection(&self) -> redis::RedisResult<Connection> {
+        match &self.client {
+            Some(client) => client.get_connection(),
+            None => Err(redis::RedisError::from((
+                redis::ErrorKind::ClientError,
+                "A dedicated connection requires a client created with RedisClient::open or RedisClient::from_client",
+            ))),
+        }
+    }
+
+    /// Get the handle used to stop long-running commands on this client
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// Whether blocking commands and retry loops should give up: shutdown
+    /// was requested, or the current run was cancelled or timed out
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.shutdown.is_shutdown() || self.run.is_cancelled()
+    }
+}
+
+/// Signals long-running commands such as `subscribe` to stop.
+///
+/// Clones share the same state, so a host can keep a handle and call
+/// [`shutdown`](ShutdownHandle::shutdown) from another thread while a
+/// script is blocked. Every clone of a [`RedisClient`] shares its handle.
+///
+/// Shutdown is cooperative: blocking commands return early and scripts can
+/// check `redis.is_shutdown()`. To terminate a running script, use the
+/// [`CancelToken`] of the engine running it.
+#[derive(Clone, Default, Debug)]
+pub struct ShutdownHandle {
+    flag: Arc<AtomicBool>,
+}
+
+impl ShutdownHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask running commands to stop at their next check
+    pub fn shutdown(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+    }
+
+    /// Check whether shutdown has been requested
+    pub fn is_shutdown(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+
+    /// Clear a previous shutdown request so the client can be reused
+    pub fn reset(&self) {
+        self.flag.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Cancels the script a [`RedisEngine`](crate::RedisEngine) is running.
+///
+/// Each engine owns one token, shared by its clones, and also uses it for
+/// the engine's timeout. Cancelling terminates the running script and ends
+/// its blocking commands; blocking commands wait in short slices, so this
+/// never leaves a command half-finished on the shared connection. Only the
+/// current run is cancelled: the engine clears the token when its next
+/// script starts.
+#[derive(Clone, Debug)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    /// Milliseconds after `epoch` at which to cancel, or 0 for no deadline
+    deadline_ms: Arc<AtomicU64>,
+    epoch: Instant,
+}
+
+impl Default for CancelToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self {
+            cancelled: Arc::default(),
+            deadline_ms: Arc::default(),
+            epoch: Instant::now(),
         }
     }
+
+    /// Terminate the running script and stop its blocking commands
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Check whether cancellation has been requested or the deadline has passed
+    pub fn is_cancelled(&self) -> bool {
+        if self.cancelled.load(Ordering::SeqCst) {
+            return true;
+        }
+        self.deadline()
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// When the current run times out, if it has a timeout
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        match self.deadline_ms.load(Ordering::SeqCst) {
+            0 => None,
+            ms => Some(self.epoch + Duration::from_millis(ms)),
+        }
+    }
+
+    /// Cancel automatically once `deadline` passes (`None` clears it)
+    pub(crate) fn set_deadline(&self, deadline: Option<Instant>) {
+        let ms = deadline.map_or(0, |deadline| {
+            let ms = deadline
+                .saturating_duration_since(self.epoch)
+                .as_nanos()
+                .div_ceil(1_000_000);
+            (ms as u64).max(1)
+        });
+        self.deadline_ms.store(ms, Ordering::SeqCst);
+    }
+
+    /// Clear a cancellation left over from an earlier run
+    pub(crate) fn clear(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
 }
@@ -1,12 +1,34 @@
 //! Redis-enabled Rhai engine
 
+use crate::client::{CancelToken, ShutdownHandle};
 use crate::{RedisClient, Result};
-use rhai::{Engine, Scope};
+use rhai::{Dynamic, Engine, EvalAltResult, ModuleResolver, Scope, AST};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Default hash holding sources stored with [`RedisEngine::script_load`]
+pub const DEFAULT_SCRIPT_KEY: &str = "rhai:scripts";
+
+/// Default key prefix for libraries stored with [`RedisEngine::function_load`]
+pub const DEFAULT_FUNCTION_KEY: &str = "rhai:functions";
+
+/// Callback run every few Rhai operations; returning `Some` terminates the script
+type ProgressCallback = Rc<dyn Fn(u64) -> Option<Dynamic>>;
 
 /// A Rhai engine configured for Redis operations
 pub struct RedisEngine {
-    engine: Engine,
-    client: Option<RedisClient>,
+    pub(crate) engine: Engine,
+    pub(crate) client: Option<RedisClient>,
+    pub(crate) scripts: HashMap<String, AST>,
+    pub(crate) script_key: String,
+    pub(crate) libraries: HashMap<String, (i64, AST)>,
+    pub(crate) function_key: String,
+    timeout: Option<Duration>,
+    cancel: CancelToken,
+    cancellable: bool,
+    progress: Option<ProgressCallback>,
 }
 
 impl Default for RedisEngine {
@@ -21,37 +43,155 @@ impl RedisEngine {
         Self {
             engine,
             client: None,
+            scripts: HashMap::new(),
+            script_key: DEFAULT_SCRIPT_KEY.to_string(),
+            libraries: HashMap::new(),
+            function_key: DEFAULT_FUNCTION_KEY.to_string(),
+            timeout: None,
+            cancel: CancelToken::new(),
+            cancellable: false,
+            progress: None,
         }
     }
 
     /// Set the Redis client for this engine
     pub fn set_redis_client(&mut self, client: RedisClient) {
         self.client = Some(client);
+        self.install_progress();
+    }
+
+    /// Limit how long each script may run, including time blocked in Redis.
+    ///
+    /// Scripts that run past the timeout fail with [`Error::Cancelled`](crate::Error::Cancelled).
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+        self.install_progress();
+    }
+
+    /// Get the handle used to ask the client's blocking commands to stop
+    pub fn shutdown_handle(&self) -> Result<ShutdownHandle> {
+        Ok(self.redis_client()?.shutdown_handle())
+    }
+
+    /// Get the token used to cancel this engine's running script from another thread.
+    ///
+    /// Cancelling affects only this engine, even if other engines share its client.
+    pub fn cancel_token(&mut self) -> CancelToken {
+        self.cancellable = true;
+        self.install_progress();
+        self.cancel.clone()
+    }
+
+    /// Set a progress callback, called with the number of operations so far.
+    ///
+    /// Returning `Some` terminates the script. Use this rather than
+    /// [`Engine::on_progress`] on [`engine`](Self::engine), which is replaced
+    /// once a timeout or cancellation is in use.
+    pub fn on_progress(&mut self, callback: impl Fn(u64) -> Option<Dynamic> + 'static) {
+        self.progress = Some(Rc::new(callback));
+        self.install_progress();
+    }
+
+    /// Install the progress callback, checking for cancellation only when a
+    /// timeout or a [`CancelToken`] from this engine makes it possible
+    fn install_progress(&mut self) {
+        let cancel = (self.cancellable || self.timeout.is_some()).then(|| self.cancel.clone());
+        let progress = self.progress.clone();
+        if cancel.is_none() && progress.is_none() {
+            return;
+        }
+        self.engine.on_progress(move |operations| {
+            if cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+                return Some(Dynamic::from("cancelled"));
+            }
+            progress.as_ref().and_then(|progress| progress(operations))
+        });
+    }
+
+    /// Prepare a client for a script run, starting the timeout if one is set.
+    ///
+    /// The client carries this engine's [`CancelToken`], so other engines
+    /// sharing the connection are unaffected by its timeout or cancellation.
+    /// A cancellation left over from an earlier run is cleared, so
+    /// [`CancelToken::cancel`] only ever stops the script it interrupted.
+    pub(crate) fn start_run(&self) -> Result<RedisClient> {
+        self.cancel.clear();
+        self.continue_run()
+    }
+
+    /// Like [`start_run`](Self::start_run), but keeps a pending cancellation,
+    /// for runs that are steps of one larger operation
+    pub(crate) fn continue_run(&self) -> Result<RedisClient> {
+        let mut client = self.redis_client()?.clone();
+        client.run = self.cancel.clone();
+        if let Some(timeout) = self.timeout {
+            self.cancel.set_deadline(Some(Instant::now() + timeout));
+        }
+        Ok(client)
+    }
+
+    /// Clear a cancellation left over from an earlier run
+    pub(crate) fn clear_cancel(&self) {
+        self.cancel.clear();
+    }
+
+    /// Whether the host has cancelled the current run
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Clear the timeout started by [`start_run`](Self::start_run) and convert script errors.
+    ///
+    /// A run whose token fired is reported as cancelled even if the script
+    /// finished, since its blocking commands and scans were cut short.
+    pub(crate) fn finish_run<T>(
+        &self,
+        result: std::result::Result<T, Box<EvalAltResult>>,
+    ) -> Result<T> {
+        let cancelled = self.cancel.is_cancelled();
+        if self.timeout.is_some() {
+            self.cancel.set_deadline(None);
+        }
+        if cancelled {
+            return Err(crate::Error::Cancelled);
+        }
+        result.map_err(|e| match *e {
+            EvalAltResult::ErrorTerminated(..) => crate::Error::Cancelled,
+            e => crate::Error::Script(e.to_string()),
+        })
+    }
+
+    /// Get the configured Redis client
+    pub(crate) fn redis_client(&self) -> Result<&RedisClient> {
+        self.client
+            .as_ref()
+            .ok_or_else(|| crate::Error::Connection("No Redis client configured".into()))
+    }
+
+    /// Set the resolver used to load modules for `import` statements
+    pub fn set_module_resolver(&mut self, resolver: impl ModuleResolver + 'static) {
+        self.engine.set_module_resolver(resolver);
+    }
+
+    /// Load modules for `import` statements from `.rhai` files under a directory
+    pub fn set_module_dir(&mut self, path: impl Into<PathBuf>) {
+        self.set_module_resolver(crate::modules::FileModuleResolver::new_with_path(path));
     }
 
     /// Run a script with the configured Redis client
     pub fn run(&mut self, script: &str) -> Result<()> {
-        let client = self
-            .client
-            .as_ref()
-            .ok_or_else(|| crate::Error::Connection("No Redis client configured".into()))?;
+        let client = self.start_run()?;
 
         let mut scope = Scope::new();
         scope.push("redis", client.clone());
 
-        self.engine
-            .run_with_scope(&mut scope, script)
-            .map_err(|e| crate::Error::Script(e.to_string()))?;
-
-        Ok(())
+        let result = self.engine.run_with_scope(&mut scope, script);
+        self.finish_run(result)
     }
 
     /// Run a script with variables
     pub fn run_with_variables(&mut self, script: &str, vars: Vec<(String, String)>) -> Result<()> {
-        let client = self
-            .client
-            .as_ref()
-            .ok_or_else(|| crate::Error::Connection("No Redis client configured".into()))?;
+        let client = self.start_run()?;
 
         let mut scope = Scope::new();
         scope.push("redis", client.clone());
@@ -60,11 +200,8 @@ impl RedisEngine {
             scope.push(name, value);
         }
 
-        self.engine
-            .run_with_scope(&mut scope, script)
-            .map_err(|e| crate::Error::Script(e.to_string()))?;
-
-        Ok(())
+        let result = self.engine.run_with_scope(&mut scope, script);
+        self.finish_run(result)
     }
 
     /// Get a reference to the underlying Rhai engine for customization
@@ -92,6 +229,19 @@ pub fn create_redis_engine() -> Result<Engine> {
     // Register all Redis methods from each module
     crate::strings::register_string_methods(&mut engine);
     crate::keys::register_key_methods(&mut engine);
+    crate::scan::register_scan_methods(&mut engine);
+    crate::backup::register_backup_methods(&mut engine);
+    crate::leaderboard::register_leaderboard_methods(&mut engine);
+    #[cfg(feature = "utils")]
+    crate::lock::register_lock_methods(&mut engine);
+    #[cfg(feature = "utils")]
+    crate::cache::register_cache_methods(&mut engine);
+    #[cfg(feature = "utils")]
+    crate::semaphore::register_semaphore_methods(&mut engine);
+    #[cfg(feature = "utils")]
+    crate::idempotency::register_idempotency_methods(&mut engine);
+    crate::rate_limit::register_rate_limit_methods(&mut engine);
+    crate::queue::register_queue_methods(&mut engine);
     crate::lists::register_list_methods(&mut engine);
     crate::hashes::register_hash_methods(&mut engine);
     crate::sets::register_set_methods(&mut engine);
@@ -12,6 +12,24 @@ pub enum Error {
 
     #[error("Connection error: {0}")]
     Connection(String),
+
+    #[error("No matching script: {0}")]
+    NoScript(String),
+
+    #[error("Function not found: {0}")]
+    NoFunction(String),
+
+    #[error("Script cancelled")]
+    Cancelled,
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid backup: {0}")]
+    Backup(String),
+
+    #[error("Invalid rate limit: {0}")]
+    RateLimit(String),
 }
 
 impl From<rhai::EvalAltResult> for Error {
@@ -20,4 +38,16 @@ impl From<rhai::EvalAltResult> for Error {
     }
 }
 
+impl From<Box<rhai::EvalAltResult>> for Error {
+    fn from(err: Box<rhai::EvalAltResult>) -> Self {
+        Error::Script(err.to_string())
+    }
+}
+
+impl From<rhai::ParseError> for Error {
+    fn from(err: rhai::ParseError) -> Self {
+        Error::Script(err.to_string())
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,433 @@
+//! Function libraries, a client-side equivalent of Redis 7 FUNCTION
+//!
+//! A library is a Rhai script whose public functions become callable by name
+//! with [`RedisEngine::fcall`]. Helpers that should not be callable are
+//! declared `private`. Callable functions receive the `redis` object, the
+//! keys and the arguments:
+//!
+//! ```rhai
+//! fn incr_capped(redis, keys, args) {
+//!     let cap = parse_int(args[0]);
+//!     let value = redis.incr(keys[0]);
+//!     if value > cap { redis.set(keys[0], cap.to_string()); cap } else { value }
+//! }
+//! ```
+//!
+//! Libraries are stored in Redis under a key prefix (`rhai:functions` by
+//! default) with a version that increases on every load, so all application
+//! instances pick up a new deployment on their next call:
+//!
+//! - `{prefix}:libraries` - hash of library name to source
+//! - `{prefix}:versions` - hash of library name to version
+//! - `{prefix}:index` - hash of function name to library name
+
+use crate::{Error, RedisEngine, Result};
+use redis::Commands;
+use rhai::{Array, Dynamic, Scope, AST};
+use std::collections::HashMap;
+
+/// A function library stored in Redis
+#[derive(Debug, Clone)]
+pub struct FunctionLibrary {
+    pub name: String,
+    pub version: i64,
+    pub functions: Vec<String>,
+    pub source: String,
+}
+
+/// How [`RedisEngine::function_restore`] treats existing libraries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestorePolicy {
+    /// Fail if any restored library or function already exists
+    #[default]
+    Append,
+    /// Replace existing libraries with the restored ones
+    Replace,
+    /// Delete all existing libraries before restoring
+    Flush,
+}
+
+/// Names of the public functions defined by a library
+fn exported_functions(ast: &AST) -> Vec<String> {
+    let mut names: Vec<String> = ast
+        .iter_functions()
+        .filter(|f| f.access.is_public())
+        .map(|f| f.name.to_string())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// A library from a restore payload, compiled and ready to store
+struct RestoredLibrary {
+    name: String,
+    source: String,
+    version: Option<i64>,
+    ast: AST,
+    functions: Vec<String>,
+}
+
+fn to_array(values: &[&str]) -> Array {
+    values
+        .iter()
+        .map(|v| Dynamic::from(v.to_string()))
+        .collect()
+}
+
+impl RedisEngine {
+    /// Set the key prefix used to store function libraries (defaults to `rhai:functions`)
+    pub fn set_function_key(&mut self, prefix: &str) {
+        self.function_key = prefix.to_string();
+        self.libraries.clear();
+    }
+
+    fn libraries_key(&self) -> String {
+        format!("{}:libraries", self.function_key)
+    }
+
+    fn versions_key(&self) -> String {
+        format!("{}:versions", self.function_key)
+    }
+
+    fn index_key(&self) -> String {
+        format!("{}:index", self.function_key)
+    }
+
+    /// Store a library of functions in Redis and return its new version.
+    ///
+    /// Fails if the library exists and `replace` is false, or if one of its
+    /// functions is already provided by another library.
+    pub fn function_load(&mut self, library: &str, source: &str, replace: bool) -> Result<i64> {
+        self.store_library(library, source, replace)
+    }
+
+    fn store_library(&mut self, library: &str, source: &str, replace: bool) -> Result<i64> {
+        let ast = self.engine.compile(source)?;
+        let functions = exported_functions(&ast);
+        if functions.is_empty() {
+            return Err(Error::Script(format!(
+                "Library '{}' defines no public functions",
+                library
+            )));
+        }
+
+        let (libraries_key, versions_key, index_key) =
+            (self.libraries_key(), self.versions_key(), self.index_key());
+        let client = self.redis_client()?.clone();
+        let mut conn = client.conn.lock().unwrap();
+
+        // Check for conflicts and write in one transaction, retrying if the
+        // libraries change between the checks and EXEC
+        let version = loop {
+            redis::cmd("WATCH")
+                .arg(&libraries_key)
+                .arg(&versions_key)
+                .arg(&index_key)
+                .query::<()>(&mut *conn)?;
+
+            let exists: bool = conn.hexists(&libraries_key, library)?;
+            let index: HashMap<String, String> = conn.hgetall(&index_key)?;
+            let conflict = if !replace && exists {
+                Some(format!("Library '{}' already exists", library))
+            } else {
+                functions.iter().find_map(|function| {
+                    index
+                        .get(function)
+                        .filter(|owner| *owner != library)
+                        .map(|owner| {
+                            format!(
+                                "Function '{}' already exists in library '{}'",
+                                function, owner
+                            )
+                        })
+                })
+            };
+            if let Some(conflict) = conflict {
+                redis::cmd("UNWATCH").query::<()>(&mut *conn)?;
+                return Err(Error::Script(conflict));
+            }
+
+            let current: Option<i64> = conn.hget(&versions_key, library)?;
+            let version = current.unwrap_or(0) + 1;
+
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            for (function, _) in index.iter().filter(|(_, owner)| *owner == library) {
+                pipe.hdel(&index_key, function).ignore();
+            }
+            for function in &functions {
+                pipe.hset(&index_key, function, library).ignore();
+            }
+            pipe.hset(&libraries_key, library, source).ignore();
+            pipe.hset(&versions_key, library, version).ignore();
+            if pipe.query::<Option<()>>(&mut *conn)?.is_some() {
+                break version;
+            }
+        };
+        drop(conn);
+
+        self.libraries.insert(library.to_string(), (version, ast));
+        Ok(version)
+    }
+
+    /// Call a function from a stored library with keys and arguments
+    pub fn fcall(&mut self, function: &str, keys: &[&str], args: &[&str]) -> Result<Dynamic> {
+        let (libraries_key, versions_key, index_key) =
+            (self.libraries_key(), self.versions_key(), self.index_key());
+        let client = self.redis_client()?.clone();
+
+        let mut conn = client.conn.lock().unwrap();
+        let library: String = conn
+            .hget::<_, _, Option<String>>(&index_key, function)?
+            .ok_or_else(|| Error::NoFunction(function.to_string()))?;
+        let version: i64 = conn
+            .hget::<_, _, Option<i64>>(&versions_key, &library)?
+            .unwrap_or(0);
+
+        let cached = matches!(self.libraries.get(&library), Some((v, _)) if *v == version);
+        if !cached {
+            let source: String = conn
+                .hget::<_, _, Option<String>>(&libraries_key, &library)?
+                .ok_or_else(|| Error::NoFunction(function.to_string()))?;
+            let ast = self.engine.compile(&source)?;
+            self.libraries.insert(library.clone(), (version, ast));
+        }
+        drop(conn);
+
+        let client = self.start_run()?;
+        let (_, ast) = &self.libraries[&library];
+        let result = self.engine.call_fn::<Dynamic>(
+            &mut Scope::new(),
+            ast,
+            function,
+            (client.clone(), to_array(keys), to_array(args)),
+        );
+        self.finish_run(result)
+    }
+
+    /// List all stored libraries
+    pub fn function_list(&mut self) -> Result<Vec<FunctionLibrary>> {
+        let (libraries_key, versions_key, index_key) =
+            (self.libraries_key(), self.versions_key(), self.index_key());
+        let client = self.redis_client()?;
+        let mut conn = client.conn.lock().unwrap();
+
+        let sources: HashMap<String, String> = conn.hgetall(&libraries_key)?;
+        let versions: HashMap<String, i64> = conn.hgetall(&versions_key)?;
+        let index: HashMap<String, String> = conn.hgetall(&index_key)?;
+
+        let mut libraries: Vec<FunctionLibrary> = sources
+            .into_iter()
+            .map(|(name, source)| {
+                let mut functions: Vec<String> = index
+                    .iter()
+                    .filter(|(_, owner)| **owner == name)
+                    .map(|(function, _)| function.clone())
+                    .collect();
+                functions.sort();
+                FunctionLibrary {
+                    version: versions.get(&name).copied().unwrap_or(0),
+                    name,
+                    functions,
+                    source,
+                }
+            })
+            .collect();
+        libraries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(libraries)
+    }
+
+    /// Delete a library and its functions, returning whether it existed
+    pub fn function_delete(&mut self, library: &str) -> Result<bool> {
+        let (libraries_key, versions_key, index_key) =
+            (self.libraries_key(), self.versions_key(), self.index_key());
+        let client = self.redis_client()?;
+        let mut conn = client.conn.lock().unwrap();
+
+        let index: HashMap<String, String> = conn.hgetall(&index_key)?;
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (function, _) in index.iter().filter(|(_, owner)| *owner == library) {
+            pipe.hdel(&index_key, function).ignore();
+        }
+        pipe.hdel(&versions_key, library).ignore();
+        pipe.hdel(&libraries_key, library);
+        let (removed,): (i64,) = pipe.query(&mut *conn)?;
+        drop(conn);
+
+        self.libraries.remove(library);
+        Ok(removed > 0)
+    }
+
+    /// Serialize all stored libraries into a JSON payload
+    pub fn function_dump(&mut self) -> Result<String> {
+        let libraries: Array = self
+            .function_list()?
+            .into_iter()
+            .map(|library| {
+                let mut entry = rhai::Map::new();
+                entry.insert("name".into(), library.name.into());
+                entry.insert("version".into(), library.version.into());
+                entry.insert("source".into(), library.source.into());
+                entry.into()
+            })
+            .collect();
+
+        let mut payload = rhai::Map::new();
+        payload.insert("libraries".into(), libraries.into());
+        Ok(rhai::format_map_as_json(&payload))
+    }
+
+    /// Restore libraries from a payload produced by [`function_dump`](Self::function_dump).
+    ///
+    /// Restored libraries keep their dumped version unless the target already
+    /// has a newer one, in which case the version is bumped past it. Every
+    /// library is compiled and checked for conflicts first, and all changes
+    /// are applied in a single transaction, so a failed restore changes nothing.
+    pub fn function_restore(&mut self, payload: &str, policy: RestorePolicy) -> Result<()> {
+        let payload = self.engine.parse_json(payload, true)?;
+        let libraries = payload
+            .get("libraries")
+            .and_then(|l| l.clone().try_cast::<Array>())
+            .ok_or_else(|| Error::Script("Invalid function payload".into()))?;
+
+        let mut parsed = Vec::with_capacity(libraries.len());
+        for library in libraries {
+            let entry = library
+                .try_cast::<rhai::Map>()
+                .ok_or_else(|| Error::Script("Invalid function payload".into()))?;
+            let field = |name: &str| {
+                entry
+                    .get(name)
+                    .and_then(|v| v.clone().into_string().ok())
+                    .ok_or_else(|| Error::Script(format!("Library is missing '{}'", name)))
+            };
+            let version = entry.get("version").and_then(|v| v.as_int().ok());
+            parsed.push((field("name")?, field("source")?, version));
+        }
+
+        // Compile and check everything before touching Redis
+        let mut compiled: Vec<RestoredLibrary> = Vec::with_capacity(parsed.len());
+        let mut restored_functions: HashMap<String, String> = HashMap::new();
+        for (name, source, version) in parsed {
+            if compiled.iter().any(|library| library.name == name) {
+                return Err(Error::Script(format!(
+                    "Library '{}' appears more than once",
+                    name
+                )));
+            }
+            let ast = self.engine.compile(&source)?;
+            let functions = exported_functions(&ast);
+            if functions.is_empty() {
+                return Err(Error::Script(format!(
+                    "Library '{}' defines no public functions",
+                    name
+                )));
+            }
+            for function in &functions {
+                if let Some(owner) = restored_functions.insert(function.clone(), name.clone()) {
+                    return Err(Error::Script(format!(
+                        "Function '{}' already exists in library '{}'",
+                        function, owner
+                    )));
+                }
+            }
+            compiled.push(RestoredLibrary {
+                name,
+                source,
+                version,
+                ast,
+                functions,
+            });
+        }
+
+        let (libraries_key, versions_key, index_key) =
+            (self.libraries_key(), self.versions_key(), self.index_key());
+        let client = self.redis_client()?.clone();
+        let mut conn = client.conn.lock().unwrap();
+
+        // Check for conflicts and write in one transaction, retrying if the
+        // libraries change between the checks and EXEC
+        let versions = loop {
+            redis::cmd("WATCH")
+                .arg(&libraries_key)
+                .arg(&versions_key)
+                .arg(&index_key)
+                .query::<()>(&mut *conn)?;
+
+            let (existing, current_versions, index) = if policy == RestorePolicy::Flush {
+                (Vec::new(), HashMap::new(), HashMap::new())
+            } else {
+                (
+                    conn.hkeys::<_, Vec<String>>(&libraries_key)?,
+                    conn.hgetall::<_, HashMap<String, i64>>(&versions_key)?,
+                    conn.hgetall::<_, HashMap<String, String>>(&index_key)?,
+                )
+            };
+
+            let conflict = compiled.iter().find_map(|library| {
+                if policy == RestorePolicy::Append && existing.contains(&library.name) {
+                    return Some(format!("Library '{}' already exists", library.name));
+                }
+                // Functions of restored libraries are replaced along with them
+                library.functions.iter().find_map(|function| {
+                    index
+                        .get(function)
+                        .filter(|owner| !compiled.iter().any(|other| other.name == **owner))
+                        .map(|owner| {
+                            format!(
+                                "Function '{}' already exists in library '{}'",
+                                function, owner
+                            )
+                        })
+                })
+            });
+            if let Some(conflict) = conflict {
+                redis::cmd("UNWATCH").query::<()>(&mut *conn)?;
+                return Err(Error::Script(conflict));
+            }
+
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            if policy == RestorePolicy::Flush {
+                pipe.del(&libraries_key)
+                    .ignore()
+                    .del(&versions_key)
+                    .ignore()
+                    .del(&index_key)
+                    .ignore();
+            }
+            for (function, owner) in &index {
+                if compiled.iter().any(|library| library.name == *owner) {
+                    pipe.hdel(&index_key, function).ignore();
+                }
+            }
+            let mut versions = Vec::with_capacity(compiled.len());
+            for library in &compiled {
+                let current = current_versions.get(&library.name).copied().unwrap_or(0);
+                let version = (current + 1).max(library.version.unwrap_or(0));
+                for function in &library.functions {
+                    pipe.hset(&index_key, function, &library.name).ignore();
+                }
+                pipe.hset(&libraries_key, &library.name, &library.source)
+                    .ignore();
+                pipe.hset(&versions_key, &library.name, version).ignore();
+                versions.push(version);
+            }
+
+            if pipe.query::<Option<()>>(&mut *conn)?.is_some() {
+                break versions;
+            }
+        };
+        drop(conn);
+
+        if policy == RestorePolicy::Flush {
+            self.libraries.clear();
+        }
+        for (library, version) in compiled.into_iter().zip(versions) {
+            self.libraries.insert(library.name, (version, library.ast));
+        }
+        Ok(())
+    }
+}
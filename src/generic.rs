@@ -6,21 +6,7 @@ use rhai::{Dynamic, Engine};
 impl RedisClient {
     pub fn cmd(&mut self, command: &str, args: Vec<Dynamic>) -> Dynamic {
         let mut conn = self.conn.lock().unwrap();
-        let mut redis_cmd = redis::cmd(command);
-
-        for arg in args {
-            if let Ok(s) = arg.clone().into_immutable_string() {
-                redis_cmd.arg(s.as_str());
-            } else if let Ok(i) = arg.as_int() {
-                redis_cmd.arg(i);
-            } else if let Ok(f) = arg.as_float() {
-                redis_cmd.arg(f);
-            } else {
-                redis_cmd.arg(arg.to_string());
-            }
-        }
-
-        match redis_cmd.query::<redis::Value>(&mut *conn) {
+        match build_cmd(command, args).query::<redis::Value>(&mut *conn) {
             Ok(value) => redis_value_to_dynamic(value),
             Err(e) => {
                 eprintln!("Redis error in cmd: {}", e);
@@ -30,6 +16,25 @@ impl RedisClient {
     }
 }
 
+/// Build a Redis command from Rhai values
+pub(crate) fn build_cmd(command: &str, args: Vec<Dynamic>) -> redis::Cmd {
+    let mut redis_cmd = redis::cmd(command);
+
+    for arg in args {
+        if let Ok(s) = arg.clone().into_immutable_string() {
+            redis_cmd.arg(s.as_str());
+        } else if let Ok(i) = arg.as_int() {
+            redis_cmd.arg(i);
+        } else if let Ok(f) = arg.as_float() {
+            redis_cmd.arg(f);
+        } else {
+            redis_cmd.arg(arg.to_string());
+        }
+    }
+
+    redis_cmd
+}
+
 pub fn redis_value_to_dynamic(value: redis::Value) -> Dynamic {
     match value {
         redis::Value::Nil => Dynamic::UNIT,
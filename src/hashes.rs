@@ -1,10 +1,42 @@
 //! Hash operations for Redis Rhai integration
 //!
 //! This module provides Redis hash commands for use in Rhai scripts.
+//!
+//! Field expiration commands (`hexpire`, `hpexpire`, `httl`, `hpersist`)
+//! require Redis 7.4, and `hgetex`/`hsetex` require Redis 8.0. They return
+//! one result per requested field, in order.
 
 use crate::client::RedisClient;
+use crate::generic::redis_value_to_dynamic;
+use crate::strings::{expiry_args, option_flag};
 use redis::Commands;
-use rhai::{Dynamic, Engine};
+use rhai::{Array, Dynamic, Engine, Map};
+
+fn to_strings(values: &Array) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+/// Convert a numeric-looking string into an integer or float
+fn parse_numeric(value: String) -> Dynamic {
+    if let Ok(i) = value.parse::<i64>() {
+        i.into()
+    } else if let Some(f) = value.parse::<f64>().ok().filter(|f| f.is_finite()) {
+        f.into()
+    } else {
+        value.into()
+    }
+}
+
+/// Build a field-TTL command of the form `CMD key [args...] FIELDS numfields field...`
+fn fields_cmd(command: &str, key: &str, args: &[String], fields: &Array) -> redis::Cmd {
+    let mut cmd = redis::cmd(command);
+    cmd.arg(key)
+        .arg(args)
+        .arg("FIELDS")
+        .arg(fields.len())
+        .arg(to_strings(fields));
+    cmd
+}
 
 impl RedisClient {
     /// Set the string value of a hash field.
@@ -13,6 +45,222 @@ impl RedisClient {
         conn.hset::<_, _, _, i64>(key, field, value).unwrap_or(0)
     }
 
+    /// Set many hash fields from a map, returning how many were added.
+    pub fn hset_map(&mut self, key: &str, fields: Map) -> i64 {
+        if fields.is_empty() {
+            return 0;
+        }
+        let items: Vec<(String, String)> = fields
+            .iter()
+            .map(|(field, value)| (field.to_string(), value.to_string()))
+            .collect();
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("HSET")
+            .arg(key)
+            .arg(items)
+            .query::<i64>(&mut *conn)
+            .unwrap_or(0)
+    }
+
+    /// Set a hash field only if it does not exist.
+    pub fn hsetnx(&mut self, key: &str, field: &str, value: &str) -> bool {
+        let mut conn = self.conn.lock().unwrap();
+        conn.hset_nx::<_, _, _, bool>(key, field, value)
+            .unwrap_or(false)
+    }
+
+    /// Get several hash fields as a map, with `()` for missing fields.
+    pub fn hmget(&mut self, key: &str, fields: Array) -> Map {
+        let fields = to_strings(&fields);
+        if fields.is_empty() {
+            return Map::new();
+        }
+        let mut conn = self.conn.lock().unwrap();
+        let values: Vec<Option<String>> = redis::cmd("HMGET")
+            .arg(key)
+            .arg(&fields)
+            .query(&mut *conn)
+            .unwrap_or_else(|_| vec![None; fields.len()]);
+        fields
+            .into_iter()
+            .zip(values)
+            .map(|(field, value)| {
+                (
+                    field.into(),
+                    value.map(Dynamic::from).unwrap_or(Dynamic::UNIT),
+                )
+            })
+            .collect()
+    }
+
+    /// Increment the integer value of a hash field.
+    pub fn hincrby(&mut self, key: &str, field: &str, increment: i64) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        conn.hincr::<_, _, _, i64>(key, field, increment)
+            .unwrap_or(0)
+    }
+
+    /// Increment the floating point value of a hash field.
+    pub fn hincrbyfloat(&mut self, key: &str, field: &str, increment: f64) -> f64 {
+        let mut conn = self.conn.lock().unwrap();
+        conn.hincr::<_, _, _, f64>(key, field, increment)
+            .unwrap_or(0.0)
+    }
+
+    /// Get the length of a hash field's value.
+    pub fn hstrlen(&mut self, key: &str, field: &str) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("HSTRLEN")
+            .arg(key)
+            .arg(field)
+            .query::<i64>(&mut *conn)
+            .unwrap_or(0)
+    }
+
+    /// Get a random field name, or `()` if the hash is empty.
+    pub fn hrandfield(&mut self, key: &str) -> Dynamic {
+        let mut conn = self.conn.lock().unwrap();
+        match redis::cmd("HRANDFIELD")
+            .arg(key)
+            .query::<Option<String>>(&mut *conn)
+        {
+            Ok(Some(field)) => field.into(),
+            _ => Dynamic::UNIT,
+        }
+    }
+
+    /// Get up to `count` random fields (a negative count allows repeats).
+    ///
+    /// With `with_values`, returns `[#{field, value}]` instead of field names.
+    pub fn hrandfield_count(&mut self, key: &str, count: i64, with_values: bool) -> Array {
+        let mut cmd = redis::cmd("HRANDFIELD");
+        cmd.arg(key).arg(count);
+        if with_values {
+            cmd.arg("WITHVALUES");
+        }
+        let mut conn = self.conn.lock().unwrap();
+        let Ok(reply) = cmd.query::<Vec<redis::Value>>(&mut *conn) else {
+            return Array::new();
+        };
+        if !with_values {
+            return reply.into_iter().map(redis_value_to_dynamic).collect();
+        }
+        reply
+            .into_iter()
+            .flat_map(|item| match item {
+                // RESP3 returns [field, value] pairs, RESP2 a flat list
+                redis::Value::Array(pair) => pair,
+                item => vec![item],
+            })
+            .collect::<Vec<_>>()
+            .chunks(2)
+            .filter_map(|pair| {
+                let [field, value] = pair else { return None };
+                let mut entry = Map::new();
+                entry.insert("field".into(), redis_value_to_dynamic(field.clone()));
+                entry.insert("value".into(), redis_value_to_dynamic(value.clone()));
+                Some(Dynamic::from(entry))
+            })
+            .collect()
+    }
+
+    fn field_ints(&self, cmd: redis::Cmd) -> Array {
+        let mut conn = self.conn.lock().unwrap();
+        cmd.query::<Vec<i64>>(&mut *conn)
+            .unwrap_or_default()
+            .into_iter()
+            .map(Dynamic::from)
+            .collect()
+    }
+
+    /// Set a TTL in seconds on hash fields, optionally only if `flag`
+    /// (`NX`, `XX`, `GT` or `LT`) holds.
+    ///
+    /// Returns per field: -2 if missing, 0 if the condition failed, 1 if
+    /// set, or 2 if the field was deleted because the TTL is 0.
+    pub fn hexpire(&mut self, key: &str, seconds: i64, fields: Array, flag: &str) -> Array {
+        let mut args = vec![seconds.to_string()];
+        if !flag.is_empty() {
+            args.push(flag.to_uppercase());
+        }
+        self.field_ints(fields_cmd("HEXPIRE", key, &args, &fields))
+    }
+
+    /// Set a TTL in milliseconds on hash fields, like [`hexpire`](Self::hexpire).
+    pub fn hpexpire(&mut self, key: &str, milliseconds: i64, fields: Array, flag: &str) -> Array {
+        let mut args = vec![milliseconds.to_string()];
+        if !flag.is_empty() {
+            args.push(flag.to_uppercase());
+        }
+        self.field_ints(fields_cmd("HPEXPIRE", key, &args, &fields))
+    }
+
+    /// Get the TTL of hash fields in seconds (-1 without a TTL, -2 if missing).
+    pub fn httl(&mut self, key: &str, fields: Array) -> Array {
+        self.field_ints(fields_cmd("HTTL", key, &[], &fields))
+    }
+
+    /// Get the TTL of hash fields in milliseconds.
+    pub fn hpttl(&mut self, key: &str, fields: Array) -> Array {
+        self.field_ints(fields_cmd("HPTTL", key, &[], &fields))
+    }
+
+    /// Remove the TTL from hash fields (1 if removed, -1 without a TTL, -2 if missing).
+    pub fn hpersist(&mut self, key: &str, fields: Array) -> Array {
+        self.field_ints(fields_cmd("HPERSIST", key, &[], &fields))
+    }
+
+    /// Get hash fields as a map and update their expiry.
+    ///
+    /// Options: `ex`, `px`, `exat`, `pxat` or `persist`.
+    pub fn hgetex(&mut self, key: &str, fields: Array, options: Map) -> Map {
+        let mut cmd = redis::cmd("HGETEX");
+        cmd.arg(key);
+        expiry_args(&mut cmd, &options);
+        cmd.arg("FIELDS").arg(fields.len()).arg(to_strings(&fields));
+
+        let mut conn = self.conn.lock().unwrap();
+        let values: Vec<Option<String>> = cmd
+            .query(&mut *conn)
+            .unwrap_or_else(|_| vec![None; fields.len()]);
+        fields
+            .iter()
+            .zip(values)
+            .map(|(field, value)| {
+                (
+                    field.to_string().into(),
+                    value.map(Dynamic::from).unwrap_or(Dynamic::UNIT),
+                )
+            })
+            .collect()
+    }
+
+    /// Set hash fields from a map with an expiry.
+    ///
+    /// Options: `ex`, `px`, `exat`, `pxat` or `keepttl`, plus `fnx` (only if
+    /// no field exists) or `fxx` (only if all fields exist). Returns whether
+    /// the fields were set.
+    pub fn hsetex(&mut self, key: &str, fields: Map, options: Map) -> bool {
+        if fields.is_empty() {
+            return false;
+        }
+        let mut cmd = redis::cmd("HSETEX");
+        cmd.arg(key);
+        for name in ["fnx", "fxx"] {
+            if option_flag(&options, name) {
+                cmd.arg(name.to_uppercase());
+            }
+        }
+        expiry_args(&mut cmd, &options);
+        cmd.arg("FIELDS").arg(fields.len());
+        for (field, value) in &fields {
+            cmd.arg(field.as_str()).arg(value.to_string());
+        }
+
+        let mut conn = self.conn.lock().unwrap();
+        cmd.query::<bool>(&mut *conn).unwrap_or(false)
+    }
+
     /// Get the value of a hash field.
     pub fn hget(&mut self, key: &str, field: &str) -> Dynamic {
         let mut conn = self.conn.lock().unwrap();
@@ -72,6 +320,20 @@ impl RedisClient {
         }
         map
     }
+
+    /// Get all fields and values, converting numeric-looking values to
+    /// integers or floats when `numeric` is true.
+    pub fn hgetall_with(&mut self, key: &str, numeric: bool) -> Map {
+        if !numeric {
+            return self.hgetall(key);
+        }
+        let mut conn = self.conn.lock().unwrap();
+        let result: Vec<(String, String)> = conn.hgetall(key).unwrap_or_default();
+        result
+            .into_iter()
+            .map(|(field, value)| (field.into(), parse_numeric(value)))
+            .collect()
+    }
 }
 
 /// Register hash methods with the Rhai engine
@@ -84,5 +346,39 @@ pub fn register_hash_methods(engine: &mut Engine) {
         .register_fn("hlen", RedisClient::hlen)
         .register_fn("hkeys", RedisClient::hkeys)
         .register_fn("hvals", RedisClient::hvals)
-        .register_fn("hgetall", RedisClient::hgetall);
+        .register_fn("hgetall", RedisClient::hgetall)
+        .register_fn("hgetall", RedisClient::hgetall_with)
+        .register_fn("hset", RedisClient::hset_map)
+        .register_fn("hsetnx", RedisClient::hsetnx)
+        .register_fn("hmget", RedisClient::hmget)
+        .register_fn("hincrby", RedisClient::hincrby)
+        .register_fn("hincrbyfloat", RedisClient::hincrbyfloat)
+        .register_fn("hstrlen", RedisClient::hstrlen)
+        .register_fn("hrandfield", RedisClient::hrandfield)
+        .register_fn(
+            "hrandfield",
+            |client: &mut RedisClient, key: &str, count: i64| {
+                client.hrandfield_count(key, count, false)
+            },
+        )
+        .register_fn("hrandfield", RedisClient::hrandfield_count)
+        .register_fn(
+            "hexpire",
+            |client: &mut RedisClient, key: &str, seconds: i64, fields: Array| {
+                client.hexpire(key, seconds, fields, "")
+            },
+        )
+        .register_fn("hexpire", RedisClient::hexpire)
+        .register_fn(
+            "hpexpire",
+            |client: &mut RedisClient, key: &str, milliseconds: i64, fields: Array| {
+                client.hpexpire(key, milliseconds, fields, "")
+            },
+        )
+        .register_fn("hpexpire", RedisClient::hpexpire)
+        .register_fn("httl", RedisClient::httl)
+        .register_fn("hpttl", RedisClient::hpttl)
+        .register_fn("hpersist", RedisClient::hpersist)
+        .register_fn("hgetex", RedisClient::hgetex)
+        .register_fn("hsetex", RedisClient::hsetex);
 }
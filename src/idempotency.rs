@@ -0,0 +1,266 @@
+//! Idempotency keys and deduplication for Redis Rhai integration
+//!
+//! `redis.once(key, ttl_s, || {...})` runs the closure at most once per
+//! `key` while its record lasts, and returns the stored result to every
+//! repeat. The key is claimed with `SET NX` and a pending marker:
+//!
+//! - A repeat that arrives while the first call is still running waits up to
+//!   `wait_ms` (5s by default) for its result, then throws.
+//! - If the closure throws, the marker is removed so the event can be retried.
+//! - A marker left by a crashed worker expires after `pending_ms` (30s by
+//!   default), after which another caller may run the closure. `pending_ms`
+//!   must therefore be longer than the closure ever takes: if the marker
+//!   expires first, the call throws rather than storing its result.
+//!
+//! Results are stored as JSON for `ttl_s` seconds, so maps and arrays
+//! round-trip. A result with no JSON form, such as a timestamp, throws and
+//! releases the key. For high-volume streams where storing every key is too costly,
+//! `redis.seen(filter, key)` records keys in a RedisBloom filter instead and
+//! reports whether a key was (probably) seen before. Bloom filters have false
+//! positives, so an unseen key may occasionally be reported as seen.
+//! `redis.seen(filter, key, false)` only checks, so a key can be recorded
+//! after it has been processed successfully. `redis.seen(key)` uses the
+//! filter `"seen"`.
+//!
+//! # Example
+//! ```rhai
+//! let receipt = redis.once("webhook:" + event.id, 86400, || {
+//!     redis.hincrby("balances", event.account, event.amount);
+//!     #{applied: true}
+//! });
+//!
+//! if !redis.seen("clicks:seen", click.id) {
+//!     redis.incr("clicks");
+//! }
+//! ```
+
+use crate::client::RedisClient;
+use crate::lock::{random_token, wait_for_retry, UNLOCK_SCRIPT};
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, Map, NativeCallContext};
+use std::time::{Duration, Instant};
+
+/// Filter used by the single-argument form of `seen`
+pub const DEFAULT_SEEN_FILTER: &str = "seen";
+
+const FINISH_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    redis.call("SET", KEYS[1], ARGV[2], "EX", ARGV[3])
+    return 1
+end
+return 0
+"#;
+
+/// Tuning for [`RedisClient::run_once`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnceOptions {
+    /// How long a repeat waits for an in-flight call to finish
+    pub wait_ms: i64,
+    /// How long the pending marker lasts if its worker never finishes; must
+    /// be longer than the call takes
+    pub pending_ms: i64,
+}
+
+impl Default for OnceOptions {
+    fn default() -> Self {
+        Self {
+            wait_ms: 5_000,
+            pending_ms: 30_000,
+        }
+    }
+}
+
+impl OnceOptions {
+    fn from_map(options: &Map) -> Self {
+        let defaults = Self::default();
+        let int = |name: &str| options.get(name).and_then(|v| v.as_int().ok());
+        Self {
+            wait_ms: int("wait_ms").unwrap_or(defaults.wait_ms),
+            pending_ms: int("pending_ms").unwrap_or(defaults.pending_ms),
+        }
+    }
+}
+
+fn record(state: &str, field: &str, value: Dynamic) -> String {
+    let mut doc = Map::new();
+    doc.insert("state".into(), state.into());
+    doc.insert(field.into(), value);
+    rhai::format_map_as_json(&doc)
+}
+
+impl RedisClient {
+    /// The stored result for `key`, or `None` if it is pending or absent
+    fn once_result(&self, key: &str) -> Option<Dynamic> {
+        let mut conn = self.conn.lock().unwrap();
+        let doc = redis::cmd("GET")
+            .arg(key)
+            .query::<Option<String>>(&mut *conn)
+            .ok()
+            .flatten()?;
+        let mut doc = Engine::new_raw().parse_json(&doc, true).ok()?;
+        if doc.get("state")?.to_string() != "done" {
+            return None;
+        }
+        doc.remove("result")
+    }
+
+    fn run_once_script(&self, source: &str, key: &str, args: &[String]) -> redis::RedisResult<i64> {
+        let script = redis::Script::new(source);
+        let mut invocation = script.key(key);
+        for arg in args {
+            invocation.arg(arg);
+        }
+        let mut conn = self.conn.lock().unwrap();
+        invocation.invoke(&mut *conn)
+    }
+
+    /// Remove our pending marker so the call can be retried
+    fn release_once(&self, key: &str, pending: String) {
+        if let Err(e) = self.run_once_script(UNLOCK_SCRIPT, key, &[pending]) {
+            eprintln!("Redis error: {}", e);
+        }
+    }
+
+    /// Run `f` once for `key` and keep its result for `ttl_s` seconds.
+    ///
+    /// Repeats return the stored result. Returns an error if another call for
+    /// `key` is still running after `wait_ms`, if `f` fails, or if its result
+    /// has no JSON form; these release the key so the call can be retried.
+    /// Also returns an error if `f` outlasted `pending_ms`, as another caller
+    /// may then have run it too.
+    pub fn run_once<E: From<String>>(
+        &mut self,
+        key: &str,
+        ttl_s: i64,
+        options: &OnceOptions,
+        f: impl FnOnce() -> Result<Dynamic, E>,
+    ) -> Result<Dynamic, E> {
+        let token = random_token();
+        let pending = record("pending", "token", token.into());
+        let deadline = Instant::now() + Duration::from_millis(options.wait_ms.max(0) as u64);
+
+        loop {
+            let claimed = {
+                let mut conn = self.conn.lock().unwrap();
+                redis::cmd("SET")
+                    .arg(key)
+                    .arg(&pending)
+                    .arg("NX")
+                    .arg("PX")
+                    .arg(options.pending_ms.max(1))
+                    .query::<Option<String>>(&mut *conn)
+                    .map_err(|e| E::from(e.to_string()))?
+                    .is_some()
+            };
+
+            if claimed {
+                let value = match f() {
+                    Ok(value) => value,
+                    Err(e) => {
+                        self.release_once(key, pending);
+                        return Err(e);
+                    }
+                };
+                // Repeats could never read back a result that doesn't parse
+                let done = record("done", "result", value.clone());
+                if Engine::new_raw().parse_json(&done, true).is_err() {
+                    self.release_once(key, pending);
+                    return Err(E::from(format!(
+                        "Result for '{}' cannot be stored: it has no JSON form",
+                        key
+                    )));
+                }
+                let finished = self
+                    .run_once_script(
+                        FINISH_SCRIPT,
+                        key,
+                        &[pending, done, ttl_s.max(1).to_string()],
+                    )
+                    .map_err(|e| E::from(e.to_string()))?;
+                if finished == 0 {
+                    return Err(E::from(format!(
+                        "Claim on '{}' expired before the result was stored; raise pending_ms",
+                        key
+                    )));
+                }
+                return Ok(value);
+            }
+
+            if let Some(result) = self.once_result(key) {
+                return Ok(result);
+            }
+            if self.is_stopped() || !wait_for_retry(Some(deadline)) {
+                return Err(E::from(format!("'{}' is still being processed", key)));
+            }
+        }
+    }
+
+    /// Script form of [`run_once`](Self::run_once), with options `wait_ms` and `pending_ms`
+    pub fn once(
+        &mut self,
+        ctx: &NativeCallContext,
+        key: &str,
+        ttl_s: i64,
+        options: Map,
+        callback: FnPtr,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let options = OnceOptions::from_map(&options);
+        self.run_once(key, ttl_s, &options, || {
+            callback.call_within_context(ctx, ())
+        })
+    }
+
+    /// Record `key` in the bloom filter `filter`, returning whether it was
+    /// probably there already. With `record` false, only checks for it.
+    pub fn seen(
+        &mut self,
+        filter: &str,
+        key: &str,
+        record: bool,
+    ) -> Result<bool, Box<EvalAltResult>> {
+        let reply = if record {
+            self.bf_add(filter, key)
+        } else {
+            self.bf_exists(filter, key)
+        };
+        // RESP3 replies are booleans, RESP2 replies are 0 or 1
+        let flag = match reply.as_bool() {
+            Ok(flag) => flag,
+            Err(_) => reply
+                .as_int()
+                .map(|n| n == 1)
+                .map_err(|_| "seen requires the RedisBloom module")?,
+        };
+        // BF.ADD reports whether the key was new, BF.EXISTS whether it was present
+        Ok(flag != record)
+    }
+}
+
+/// Register idempotency helpers with the Rhai engine
+pub fn register_idempotency_methods(engine: &mut Engine) {
+    engine
+        .register_fn(
+            "once",
+            |ctx: NativeCallContext,
+             client: &mut RedisClient,
+             key: &str,
+             ttl_s: i64,
+             callback: FnPtr| { client.once(&ctx, key, ttl_s, Map::new(), callback) },
+        )
+        .register_fn(
+            "once",
+            |ctx: NativeCallContext,
+             client: &mut RedisClient,
+             key: &str,
+             ttl_s: i64,
+             options: Map,
+             callback: FnPtr| { client.once(&ctx, key, ttl_s, options, callback) },
+        )
+        .register_fn("seen", |client: &mut RedisClient, key: &str| {
+            client.seen(DEFAULT_SEEN_FILTER, key, true)
+        })
+        .register_fn(
+            "seen",
+            |client: &mut RedisClient, filter: &str, key: &str| client.seen(filter, key, true),
+        )
+        .register_fn("seen", RedisClient::seen);
+}
@@ -1,7 +1,7 @@
 //! Redis JSON operations for Rhai integration
 
 use crate::client::RedisClient;
-use rhai::{Dynamic, Engine};
+use rhai::{Array, Dynamic, Engine, Map};
 
 impl RedisClient {
     pub fn json_set(&mut self, key: &str, path: &str, value: &str) -> Dynamic {
@@ -124,6 +124,72 @@ impl RedisClient {
     }
 }
 
+/// Encode a value as JSON text.
+///
+/// Unlike [`rhai::format_map_as_json`], strings are escaped the way JSON
+/// requires, so control characters come out as `\uXXXX` rather than Rust
+/// escapes. Non-finite floats become `null` and values with no JSON
+/// equivalent are written as their string form.
+pub(crate) fn to_json(value: &Dynamic) -> String {
+    let mut out = String::new();
+    write_json(&mut out, value);
+    out
+}
+
+fn write_json(out: &mut String, value: &Dynamic) {
+    if value.is_unit() {
+        out.push_str("null");
+    } else if let Ok(b) = value.as_bool() {
+        out.push_str(if b { "true" } else { "false" });
+    } else if let Ok(i) = value.as_int() {
+        out.push_str(&i.to_string());
+    } else if let Ok(f) = value.as_float() {
+        if f.is_finite() {
+            out.push_str(&format!("{:?}", f));
+        } else {
+            out.push_str("null");
+        }
+    } else if let Some(map) = value.read_lock::<Map>() {
+        out.push('{');
+        for (i, (k, v)) in map.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write_json_string(out, k);
+            out.push(':');
+            write_json(out, v);
+        }
+        out.push('}');
+    } else if let Some(array) = value.read_lock::<Array>() {
+        out.push('[');
+        for (i, v) in array.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write_json(out, v);
+        }
+        out.push(']');
+    } else {
+        write_json_string(out, &value.to_string());
+    }
+}
+
+fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
 pub fn register_json_methods(engine: &mut Engine) {
     engine
         .register_fn("json_set", RedisClient::json_set)
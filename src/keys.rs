@@ -2,7 +2,37 @@
 
 use crate::client::RedisClient;
 use redis::Commands;
-use rhai::{Dynamic, Engine};
+use rhai::{Array, Blob, Dynamic, Engine, Map};
+
+/// Run an expiry command, appending an `NX`, `XX`, `GT` or `LT` condition if given
+fn set_expiry(
+    client: &RedisClient,
+    command: &str,
+    key: &str,
+    value: i64,
+    flag: Option<&str>,
+) -> bool {
+    let mut cmd = redis::cmd(command);
+    cmd.arg(key).arg(value);
+    if let Some(flag) = flag {
+        cmd.arg(flag.to_uppercase());
+    }
+    let mut conn = client.conn.lock().unwrap();
+    cmd.query::<bool>(&mut *conn).unwrap_or(false)
+}
+
+/// Run a command that returns an integer, or `()` for a missing key or an error
+fn optional_int(client: &RedisClient, cmd: &redis::Cmd) -> Dynamic {
+    let mut conn = client.conn.lock().unwrap();
+    match cmd.query::<Option<i64>>(&mut *conn) {
+        Ok(Some(value)) => value.into(),
+        _ => Dynamic::UNIT,
+    }
+}
+
+fn to_keys(keys: &Array) -> Vec<String> {
+    keys.iter().map(|k| k.to_string()).collect()
+}
 
 impl RedisClient {
     pub fn expire(&mut self, key: &str, seconds: i64) -> bool {
@@ -10,11 +40,261 @@ impl RedisClient {
         conn.expire::<_, bool>(key, seconds).unwrap_or(false)
     }
 
+    /// Set a timeout in seconds, only if `flag` (`NX`, `XX`, `GT` or `LT`) holds
+    pub fn expire_with(&mut self, key: &str, seconds: i64, flag: &str) -> bool {
+        set_expiry(self, "EXPIRE", key, seconds, Some(flag))
+    }
+
+    /// Set a timeout in milliseconds
+    pub fn pexpire(&mut self, key: &str, milliseconds: i64) -> bool {
+        set_expiry(self, "PEXPIRE", key, milliseconds, None)
+    }
+
+    /// Set a timeout in milliseconds, only if `flag` (`NX`, `XX`, `GT` or `LT`) holds
+    pub fn pexpire_with(&mut self, key: &str, milliseconds: i64, flag: &str) -> bool {
+        set_expiry(self, "PEXPIRE", key, milliseconds, Some(flag))
+    }
+
+    /// Expire a key at a Unix timestamp in seconds
+    pub fn expireat(&mut self, key: &str, timestamp: i64) -> bool {
+        set_expiry(self, "EXPIREAT", key, timestamp, None)
+    }
+
+    /// Expire a key at a Unix timestamp in seconds, only if `flag` holds
+    pub fn expireat_with(&mut self, key: &str, timestamp: i64, flag: &str) -> bool {
+        set_expiry(self, "EXPIREAT", key, timestamp, Some(flag))
+    }
+
+    /// Expire a key at a Unix timestamp in milliseconds
+    pub fn pexpireat(&mut self, key: &str, timestamp: i64) -> bool {
+        set_expiry(self, "PEXPIREAT", key, timestamp, None)
+    }
+
+    /// Expire a key at a Unix timestamp in milliseconds, only if `flag` holds
+    pub fn pexpireat_with(&mut self, key: &str, timestamp: i64, flag: &str) -> bool {
+        set_expiry(self, "PEXPIREAT", key, timestamp, Some(flag))
+    }
+
+    /// Get the Unix timestamp in seconds at which a key expires (-1 if it has no TTL, -2 if it is missing)
+    pub fn expiretime(&mut self, key: &str) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("EXPIRETIME")
+            .arg(key)
+            .query::<i64>(&mut *conn)
+            .unwrap_or(-2)
+    }
+
+    /// Get the Unix timestamp in milliseconds at which a key expires
+    pub fn pexpiretime(&mut self, key: &str) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("PEXPIRETIME")
+            .arg(key)
+            .query::<i64>(&mut *conn)
+            .unwrap_or(-2)
+    }
+
+    /// Remove the timeout from a key
+    pub fn persist(&mut self, key: &str) -> bool {
+        let mut conn = self.conn.lock().unwrap();
+        conn.persist::<_, bool>(key).unwrap_or(false)
+    }
+
     pub fn ttl(&mut self, key: &str) -> i64 {
         let mut conn = self.conn.lock().unwrap();
         conn.ttl::<_, i64>(key).unwrap_or(-2)
     }
 
+    /// Get the remaining time to live in milliseconds
+    pub fn pttl(&mut self, key: &str) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        conn.pttl::<_, i64>(key).unwrap_or(-2)
+    }
+
+    /// Get the type of the value stored at a key (`"none"` if it is missing)
+    pub fn key_type(&mut self, key: &str) -> String {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("TYPE")
+            .arg(key)
+            .query::<String>(&mut *conn)
+            .unwrap_or_else(|_| "none".to_string())
+    }
+
+    /// Rename a key, overwriting `new_key` if it exists
+    pub fn rename(&mut self, key: &str, new_key: &str) -> bool {
+        let mut conn = self.conn.lock().unwrap();
+        conn.rename::<_, _, ()>(key, new_key).is_ok()
+    }
+
+    /// Rename a key only if `new_key` does not exist
+    pub fn renamenx(&mut self, key: &str, new_key: &str) -> bool {
+        let mut conn = self.conn.lock().unwrap();
+        conn.rename_nx::<_, _, bool>(key, new_key).unwrap_or(false)
+    }
+
+    /// Delete several keys, returning how many were removed
+    pub fn del_many(&mut self, keys: Array) -> i64 {
+        if keys.is_empty() {
+            return 0;
+        }
+        let mut conn = self.conn.lock().unwrap();
+        conn.del::<_, i64>(to_keys(&keys)).unwrap_or(0)
+    }
+
+    /// Count how many of the given keys exist
+    pub fn exists_many(&mut self, keys: Array) -> i64 {
+        if keys.is_empty() {
+            return 0;
+        }
+        let mut conn = self.conn.lock().unwrap();
+        conn.exists::<_, i64>(to_keys(&keys)).unwrap_or(0)
+    }
+
+    /// Delete a key, reclaiming its memory in the background
+    pub fn unlink(&mut self, key: &str) -> i64 {
+        self.unlink_many(vec![key.into()])
+    }
+
+    /// Delete several keys in the background, returning how many were removed
+    pub fn unlink_many(&mut self, keys: Array) -> i64 {
+        if keys.is_empty() {
+            return 0;
+        }
+        let mut conn = self.conn.lock().unwrap();
+        conn.unlink::<_, i64>(to_keys(&keys)).unwrap_or(0)
+    }
+
+    /// Update the last access time of a key
+    pub fn touch(&mut self, key: &str) -> i64 {
+        self.touch_many(vec![key.into()])
+    }
+
+    /// Update the last access time of several keys, returning how many exist
+    pub fn touch_many(&mut self, keys: Array) -> i64 {
+        if keys.is_empty() {
+            return 0;
+        }
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("TOUCH")
+            .arg(to_keys(&keys))
+            .query::<i64>(&mut *conn)
+            .unwrap_or(0)
+    }
+
+    /// Copy a key to `destination`, failing if it exists
+    pub fn copy(&mut self, source: &str, destination: &str) -> bool {
+        self.copy_with(source, destination, Map::new())
+    }
+
+    /// Copy a key with options: `db` (target database) and `replace`
+    pub fn copy_with(&mut self, source: &str, destination: &str, options: Map) -> bool {
+        let mut cmd = redis::cmd("COPY");
+        cmd.arg(source).arg(destination);
+        if let Some(db) = options.get("db").and_then(|v| v.as_int().ok()) {
+            cmd.arg("DB").arg(db);
+        }
+        if options
+            .get("replace")
+            .and_then(|v| v.as_bool().ok())
+            .unwrap_or(false)
+        {
+            cmd.arg("REPLACE");
+        }
+        let mut conn = self.conn.lock().unwrap();
+        cmd.query::<bool>(&mut *conn).unwrap_or(false)
+    }
+
+    /// Move a key to another database
+    pub fn move_key(&mut self, key: &str, db: i64) -> bool {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("MOVE")
+            .arg(key)
+            .arg(db)
+            .query::<bool>(&mut *conn)
+            .unwrap_or(false)
+    }
+
+    /// Get a random key, or `()` if the database is empty
+    pub fn randomkey(&mut self) -> Dynamic {
+        let mut conn = self.conn.lock().unwrap();
+        match redis::cmd("RANDOMKEY").query::<Option<String>>(&mut *conn) {
+            Ok(Some(key)) => key.into(),
+            _ => Dynamic::UNIT,
+        }
+    }
+
+    /// Get the internal encoding of a key's value, or `()` if it is missing
+    pub fn object_encoding(&mut self, key: &str) -> Dynamic {
+        let mut conn = self.conn.lock().unwrap();
+        match redis::cmd("OBJECT")
+            .arg("ENCODING")
+            .arg(key)
+            .query::<Option<String>>(&mut *conn)
+        {
+            Ok(Some(encoding)) => encoding.into(),
+            _ => Dynamic::UNIT,
+        }
+    }
+
+    /// Get the seconds since a key was last accessed, or `()` if it is missing
+    pub fn object_idletime(&mut self, key: &str) -> Dynamic {
+        optional_int(self, redis::cmd("OBJECT").arg("IDLETIME").arg(key))
+    }
+
+    /// Get the LFU access frequency of a key, or `()` if it is missing or the LFU policy is off
+    pub fn object_freq(&mut self, key: &str) -> Dynamic {
+        optional_int(self, redis::cmd("OBJECT").arg("FREQ").arg(key))
+    }
+
+    /// Get the memory used by a key in bytes, or `()` if it is missing
+    pub fn memory_usage(&mut self, key: &str) -> Dynamic {
+        optional_int(self, redis::cmd("MEMORY").arg("USAGE").arg(key))
+    }
+
+    /// Serialize a key's value with DUMP, or `()` if it is missing
+    pub fn dump(&mut self, key: &str) -> Dynamic {
+        let mut conn = self.conn.lock().unwrap();
+        match redis::cmd("DUMP")
+            .arg(key)
+            .query::<Option<Vec<u8>>>(&mut *conn)
+        {
+            Ok(Some(payload)) => Dynamic::from_blob(payload),
+            _ => Dynamic::UNIT,
+        }
+    }
+
+    /// Create a key from a DUMP payload, with a TTL in milliseconds (0 for none)
+    pub fn restore(&mut self, key: &str, ttl: i64, payload: Blob) -> bool {
+        self.restore_with(key, ttl, payload, Map::new())
+    }
+
+    /// Create a key from a DUMP payload with options: `replace`, `absttl`
+    /// (`ttl` is a Unix timestamp in milliseconds), `idletime` and `freq`
+    pub fn restore_with(&mut self, key: &str, ttl: i64, payload: Blob, options: Map) -> bool {
+        let flag = |name: &str| {
+            options
+                .get(name)
+                .and_then(|v| v.as_bool().ok())
+                .unwrap_or(false)
+        };
+
+        let mut cmd = redis::cmd("RESTORE");
+        cmd.arg(key).arg(ttl).arg(payload);
+        if flag("replace") {
+            cmd.arg("REPLACE");
+        }
+        if flag("absttl") {
+            cmd.arg("ABSTTL");
+        }
+        if let Some(idle) = options.get("idletime").and_then(|v| v.as_int().ok()) {
+            cmd.arg("IDLETIME").arg(idle);
+        }
+        if let Some(freq) = options.get("freq").and_then(|v| v.as_int().ok()) {
+            cmd.arg("FREQ").arg(freq);
+        }
+        let mut conn = self.conn.lock().unwrap();
+        cmd.query::<()>(&mut *conn).is_ok()
+    }
+
     pub fn keys(&mut self, pattern: &str) -> Vec<Dynamic> {
         let mut conn = self.conn.lock().unwrap();
         match conn.keys::<_, Vec<String>>(pattern) {
@@ -40,7 +320,38 @@ impl RedisClient {
 pub fn register_key_methods(engine: &mut Engine) {
     engine
         .register_fn("expire", RedisClient::expire)
+        .register_fn("expire", RedisClient::expire_with)
+        .register_fn("pexpire", RedisClient::pexpire)
+        .register_fn("pexpire", RedisClient::pexpire_with)
+        .register_fn("expireat", RedisClient::expireat)
+        .register_fn("expireat", RedisClient::expireat_with)
+        .register_fn("pexpireat", RedisClient::pexpireat)
+        .register_fn("pexpireat", RedisClient::pexpireat_with)
+        .register_fn("expiretime", RedisClient::expiretime)
+        .register_fn("pexpiretime", RedisClient::pexpiretime)
+        .register_fn("persist", RedisClient::persist)
         .register_fn("ttl", RedisClient::ttl)
+        .register_fn("pttl", RedisClient::pttl)
+        .register_fn("type", RedisClient::key_type)
+        .register_fn("rename", RedisClient::rename)
+        .register_fn("renamenx", RedisClient::renamenx)
+        .register_fn("del", RedisClient::del_many)
+        .register_fn("exists", RedisClient::exists_many)
+        .register_fn("unlink", RedisClient::unlink)
+        .register_fn("unlink", RedisClient::unlink_many)
+        .register_fn("touch", RedisClient::touch)
+        .register_fn("touch", RedisClient::touch_many)
+        .register_fn("copy", RedisClient::copy)
+        .register_fn("copy", RedisClient::copy_with)
+        .register_fn("move", RedisClient::move_key)
+        .register_fn("randomkey", RedisClient::randomkey)
+        .register_fn("object_encoding", RedisClient::object_encoding)
+        .register_fn("object_idletime", RedisClient::object_idletime)
+        .register_fn("object_freq", RedisClient::object_freq)
+        .register_fn("memory_usage", RedisClient::memory_usage)
+        .register_fn("dump", RedisClient::dump)
+        .register_fn("restore", RedisClient::restore)
+        .register_fn("restore", RedisClient::restore_with)
         .register_fn("keys", RedisClient::keys)
         .register_fn("dbsize", RedisClient::dbsize)
         .register_fn("flushdb", RedisClient::flushdb);
@@ -0,0 +1,397 @@
+//! Leaderboards built on sorted sets
+//!
+//! `redis.leaderboard(name)` returns a board backed by the sorted set `name`.
+//! Ranks are 1-based and, by default, higher scores rank first. Entries are
+//! returned as `#{rank, member, score}`.
+//!
+//! ```rhai
+//! let board = redis.leaderboard("arcade");
+//! board.submit("ada", 1200.0);            // keep the best score
+//! board.submit("bob", 50.0, "sum");       // add to the current score
+//! board.submit("eve", 900.0, "latest");   // overwrite the score
+//!
+//! print(board.rank("ada"));               // 1
+//! for entry in board.top(10) { print(entry.rank + ". " + entry.member); }
+//! let neighbours = board.around("bob", 2);
+//! let second_page = board.page(2, 25);
+//! ```
+//!
+//! # Time-bucketed boards
+//!
+//! With a `period` of `"daily"` or `"weekly"`, scores go to a key per bucket
+//! (`{name}:2024-05-17`, or `{name}:week:2024-05-13` for the week starting
+//! that Monday, in UTC). Bucket keys expire `keep` periods after they start
+//! (7 by default), and `merge` combines recent buckets with ZUNIONSTORE:
+//!
+//! ```rhai
+//! let daily = redis.leaderboard("arcade", #{period: "daily", keep: 30});
+//! daily.submit("ada", 300.0);
+//! let yesterday = daily.previous(1);
+//! let last_week = daily.merge(7, "arcade:last7");
+//! print(last_week.top(3));
+//! ```
+
+use crate::client::RedisClient;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+const DEFAULT_KEEP: i64 = 7;
+
+/// Length of a leaderboard bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Daily,
+    Weekly,
+}
+
+impl Period {
+    fn length_ms(self) -> i64 {
+        match self {
+            Period::Daily => DAY_MS,
+            Period::Weekly => 7 * DAY_MS,
+        }
+    }
+
+    /// Start of the bucket containing `timestamp_ms`; weeks start on Monday
+    fn bucket_start(self, timestamp_ms: i64) -> i64 {
+        match self {
+            Period::Daily => timestamp_ms.div_euclid(DAY_MS) * DAY_MS,
+            // 1970-01-01 was a Thursday, so Mondays are 3 days after a multiple of 7
+            Period::Weekly => {
+                let days = timestamp_ms.div_euclid(DAY_MS);
+                (days - (days + 3).rem_euclid(7)) * DAY_MS
+            }
+        }
+    }
+}
+
+/// How [`Leaderboard::submit`] combines a new score with the current one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SubmitMode {
+    /// Keep the better of the two scores
+    #[default]
+    Best,
+    /// Replace the score
+    Latest,
+    /// Add to the score
+    Sum,
+}
+
+impl SubmitMode {
+    fn parse(mode: &str) -> Option<Self> {
+        match mode.to_ascii_lowercase().as_str() {
+            "best" => Some(SubmitMode::Best),
+            "latest" => Some(SubmitMode::Latest),
+            "sum" => Some(SubmitMode::Sum),
+            _ => None,
+        }
+    }
+}
+
+/// Format days since the Unix epoch as `YYYY-MM-DD`
+fn format_date(days: i64) -> String {
+    // Howard Hinnant's civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// A leaderboard stored in one sorted set, or one per time bucket
+#[derive(Clone)]
+pub struct Leaderboard {
+    client: RedisClient,
+    name: String,
+    period: Option<Period>,
+    keep: i64,
+    ascending: bool,
+    /// Fixed bucket start, for boards returned by [`previous`](Self::previous)
+    pinned: Option<i64>,
+}
+
+impl Leaderboard {
+    pub fn new(client: RedisClient, name: &str) -> Self {
+        Self {
+            client,
+            name: name.to_string(),
+            period: None,
+            keep: DEFAULT_KEEP,
+            ascending: false,
+            pinned: None,
+        }
+    }
+
+    /// Store scores in one key per `period`
+    pub fn with_period(mut self, period: Period) -> Self {
+        self.period = Some(period);
+        self
+    }
+
+    /// Expire bucket keys `keep` periods after they start
+    pub fn with_keep(mut self, keep: i64) -> Self {
+        self.keep = keep.max(1);
+        self
+    }
+
+    /// Rank lower scores first, e.g. for fastest times
+    pub fn ascending(mut self) -> Self {
+        self.ascending = true;
+        self
+    }
+
+    fn bucket_start(&self) -> Option<i64> {
+        let period = self.period?;
+        Some(self.pinned.unwrap_or_else(|| period.bucket_start(now_ms())))
+    }
+
+    fn key_for(&self, start: Option<i64>) -> String {
+        match (self.period, start) {
+            (Some(Period::Daily), Some(start)) => {
+                format!("{}:{}", self.name, format_date(start / DAY_MS))
+            }
+            (Some(Period::Weekly), Some(start)) => {
+                format!("{}:week:{}", self.name, format_date(start / DAY_MS))
+            }
+            _ => self.name.clone(),
+        }
+    }
+
+    /// The sorted set key currently written to
+    pub fn key(&self) -> String {
+        self.key_for(self.bucket_start())
+    }
+
+    /// The board for the bucket `n` periods before this one
+    pub fn previous(&self, n: i64) -> Self {
+        let mut board = self.clone();
+        if let (Some(period), Some(start)) = (self.period, self.bucket_start()) {
+            board.pinned = Some(period.bucket_start(start - n * period.length_ms()));
+        }
+        board
+    }
+
+    /// Fetch entries between two 0-based positions as `[#{rank, member, score}]`
+    fn entries(&mut self, start: i64, stop: i64) -> Array {
+        let key = self.key();
+        let mut options = Map::new();
+        options.insert("rev".into(), (!self.ascending).into());
+        options.insert("withscores".into(), true.into());
+        self.client
+            .zrange_with(&key, start.into(), stop.into(), options)
+            .into_iter()
+            .zip(start + 1..)
+            .filter_map(|(entry, rank)| {
+                let mut entry = entry.try_cast::<Map>()?;
+                entry.insert("rank".into(), rank.into());
+                Some(entry.into())
+            })
+            .collect()
+    }
+
+    /// Record a score and return the member's resulting score
+    pub fn submit(&mut self, member: &str, score: f64, mode: SubmitMode) -> f64 {
+        let key = self.key();
+        let result = match mode {
+            SubmitMode::Sum => self.client.zincrby(&key, score, member),
+            SubmitMode::Latest => {
+                self.client.zadd(&key, score, member);
+                score
+            }
+            SubmitMode::Best => {
+                let mut options = Map::new();
+                let flag = if self.ascending { "lt" } else { "gt" };
+                options.insert(flag.into(), true.into());
+                let _ = self.client.zadd_with(&key, score, member, options);
+                self.client.zscore(&key, member).as_float().unwrap_or(score)
+            }
+        };
+
+        if let (Some(period), Some(start)) = (self.period, self.bucket_start()) {
+            self.client
+                .pexpireat(&key, start + self.keep * period.length_ms());
+        }
+        result
+    }
+
+    /// The member's score, or `()` if it has none
+    pub fn score(&mut self, member: &str) -> Dynamic {
+        let key = self.key();
+        self.client.zscore(&key, member)
+    }
+
+    /// The member's 1-based rank, or `()` if it has no score
+    pub fn rank(&mut self, member: &str) -> Dynamic {
+        let key = self.key();
+        let rank = if self.ascending {
+            self.client.zrank(&key, member, false)
+        } else {
+            self.client.zrevrank(&key, member, false)
+        };
+        match rank.as_int() {
+            Ok(rank) => (rank + 1).into(),
+            Err(_) => Dynamic::UNIT,
+        }
+    }
+
+    /// The best `n` entries
+    pub fn top(&mut self, n: i64) -> Array {
+        if n <= 0 {
+            return Array::new();
+        }
+        self.entries(0, n - 1)
+    }
+
+    /// The member's entry with up to `n` entries on each side, or `[]` if it has no score
+    pub fn around(&mut self, member: &str, n: i64) -> Array {
+        let Ok(rank) = self.rank(member).as_int() else {
+            return Array::new();
+        };
+        let position = rank - 1;
+        self.entries((position - n.max(0)).max(0), position + n.max(0))
+    }
+
+    /// Entries on a 1-based page of `size` entries
+    pub fn page(&mut self, page: i64, size: i64) -> Array {
+        if page < 1 || size < 1 {
+            return Array::new();
+        }
+        let start = (page - 1) * size;
+        self.entries(start, start + size - 1)
+    }
+
+    /// Number of members on the board
+    pub fn count(&mut self) -> i64 {
+        let key = self.key();
+        self.client.zcard(&key)
+    }
+
+    /// Remove a member, returning whether it was on the board
+    pub fn remove(&mut self, member: &str) -> bool {
+        let key = self.key();
+        self.client.zrem(&key, member) > 0
+    }
+
+    /// Combine this bucket and the `buckets - 1` before it into `destination`.
+    ///
+    /// Scores are combined with `aggregate` (`"sum"`, `"min"` or `"max"`). The
+    /// result expires with the current bucket and is returned as a plain
+    /// board. Without a period, this copies the board.
+    pub fn merge(&mut self, buckets: i64, destination: &str, aggregate: &str) -> Leaderboard {
+        let buckets = if self.period.is_some() {
+            buckets.max(1)
+        } else {
+            1
+        };
+        let keys: Array = (0..buckets)
+            .map(|n| self.previous(n).key().into())
+            .collect();
+        let mut options = Map::new();
+        options.insert("aggregate".into(), aggregate.into());
+        self.client.zunionstore(destination, keys, options);
+
+        if let (Some(period), Some(start)) = (self.period, self.bucket_start()) {
+            self.client
+                .pexpireat(destination, start + self.keep * period.length_ms());
+        }
+
+        let mut board = Leaderboard::new(self.client.clone(), destination);
+        board.ascending = self.ascending;
+        board
+    }
+
+    fn default_aggregate(&self) -> &'static str {
+        if self.ascending {
+            "min"
+        } else {
+            "max"
+        }
+    }
+}
+
+impl RedisClient {
+    /// Create a leaderboard with options `period` (`"daily"` or `"weekly"`),
+    /// `keep` (buckets to retain) and `order` (`"desc"` or `"asc"`)
+    pub fn leaderboard(
+        &mut self,
+        name: &str,
+        options: Map,
+    ) -> Result<Leaderboard, Box<EvalAltResult>> {
+        let mut board = Leaderboard::new(self.clone(), name);
+        match options.get("period").map(|p| p.to_string()).as_deref() {
+            None => {}
+            Some("daily") => board = board.with_period(Period::Daily),
+            Some("weekly") => board = board.with_period(Period::Weekly),
+            Some(other) => return Err(format!("Unknown leaderboard period '{}'", other).into()),
+        }
+        if let Some(keep) = options.get("keep").and_then(|k| k.as_int().ok()) {
+            board = board.with_keep(keep);
+        }
+        match options.get("order").map(|o| o.to_string()).as_deref() {
+            None | Some("desc") => {}
+            Some("asc") => board = board.ascending(),
+            Some(other) => return Err(format!("Unknown leaderboard order '{}'", other).into()),
+        }
+        Ok(board)
+    }
+}
+
+/// Register the leaderboard type and its methods with the Rhai engine
+pub fn register_leaderboard_methods(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<Leaderboard>("Leaderboard")
+        .register_fn("leaderboard", |client: &mut RedisClient, name: &str| {
+            client.leaderboard(name, Map::new())
+        })
+        .register_fn("leaderboard", RedisClient::leaderboard)
+        .register_fn(
+            "submit",
+            |board: &mut Leaderboard, member: &str, score: f64| {
+                board.submit(member, score, SubmitMode::Best)
+            },
+        )
+        .register_fn(
+            "submit",
+            |board: &mut Leaderboard,
+             member: &str,
+             score: f64,
+             mode: &str|
+             -> Result<f64, Box<EvalAltResult>> {
+                let mode = SubmitMode::parse(mode)
+                    .ok_or_else(|| format!("Unknown submit mode '{}'", mode))?;
+                Ok(board.submit(member, score, mode))
+            },
+        )
+        .register_fn("score", Leaderboard::score)
+        .register_fn("rank", Leaderboard::rank)
+        .register_fn("top", Leaderboard::top)
+        .register_fn("around", Leaderboard::around)
+        .register_fn("page", Leaderboard::page)
+        .register_fn("count", Leaderboard::count)
+        .register_fn("remove", Leaderboard::remove)
+        .register_fn("key", |board: &mut Leaderboard| board.key())
+        .register_fn("previous", |board: &mut Leaderboard, n: i64| {
+            board.previous(n)
+        })
+        .register_fn(
+            "merge",
+            |board: &mut Leaderboard, buckets: i64, destination: &str| {
+                let aggregate = board.default_aggregate();
+                board.merge(buckets, destination, aggregate)
+            },
+        )
+        .register_fn("merge", Leaderboard::merge);
+}
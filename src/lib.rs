@@ -22,18 +22,32 @@
 //! "#).unwrap();
 //! ```
 
+pub mod backup;
 pub mod bitmap;
 pub mod bloom;
+#[cfg(feature = "utils")]
+pub mod cache;
 pub mod client;
 pub mod generic;
 pub mod geo;
 pub mod hashes;
 pub mod hyperloglog;
+#[cfg(feature = "utils")]
+pub mod idempotency;
 pub mod json;
 pub mod keys;
+pub mod leaderboard;
 pub mod lists;
+#[cfg(feature = "utils")]
+pub mod lock;
+pub mod modules;
 pub mod pubsub;
+pub mod queue;
+pub mod rate_limit;
+pub mod scan;
 pub mod search;
+#[cfg(feature = "utils")]
+pub mod semaphore;
 pub mod sets;
 pub mod sorted_sets;
 pub mod streams;
@@ -43,10 +57,14 @@ pub mod utils;
 
 mod engine;
 mod error;
+mod functions;
+mod scripts;
 
-pub use client::RedisClient;
-pub use engine::{create_redis_engine, RedisEngine};
+pub use client::{CancelToken, RedisClient, ShutdownHandle};
+pub use engine::{create_redis_engine, RedisEngine, DEFAULT_FUNCTION_KEY, DEFAULT_SCRIPT_KEY};
 pub use error::{Error, Result};
+pub use functions::{FunctionLibrary, RestorePolicy};
+pub use scripts::script_sha;
 
 // Re-export rhai types that users might need
 pub use rhai::{Dynamic, Engine, Scope};
@@ -1,8 +1,72 @@
 //! List operations for Redis Rhai integration
+//!
+//! Blocking commands (`blpop`, `brpop`, `blmove`, `blmpop`) take a timeout in
+//! milliseconds, where 0 waits until the client is shut down. Like blocking
+//! stream reads, they wait in short slices so shutdown and cancellation are
+//! noticed promptly. A reliable queue can be built from `blmove` and `lrem`:
+//!
+//! ```rhai
+//! let job = redis.blmove("jobs", "jobs:processing", "LEFT", "RIGHT", 5000);
+//! if job != () {
+//!     // ... process the job ...
+//!     redis.lrem("jobs:processing", 1, job);
+//! }
+//! ```
 
-use crate::client::RedisClient;
-use redis::Commands;
-use rhai::{Dynamic, Engine};
+use crate::client::{next_wait, RedisClient};
+use crate::generic::redis_value_to_dynamic;
+use redis::{Commands, FromRedisValue, Value};
+use rhai::{Array, Dynamic, Engine, Map};
+use std::num::NonZeroUsize;
+use std::time::{Duration, Instant};
+
+fn to_strings(values: &Array) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+/// Accept either a single value or an array of values
+pub(crate) fn one_or_many(values: &Dynamic) -> Vec<String> {
+    match values.read_lock::<Array>() {
+        Some(values) => to_strings(&values),
+        None => vec![values.to_string()],
+    }
+}
+
+fn optional_string(value: Value) -> Dynamic {
+    match value {
+        Value::Nil => Dynamic::UNIT,
+        value => redis_value_to_dynamic(value),
+    }
+}
+
+/// Convert a `[key, values]` reply from LMPOP/BLMPOP into `#{key, values}`
+fn mpop_reply(value: Value) -> Dynamic {
+    let Ok(Some((key, values))) = Option::<(String, Vec<String>)>::from_redis_value(&value) else {
+        return Dynamic::UNIT;
+    };
+    let mut map = Map::new();
+    map.insert("key".into(), key.into());
+    map.insert(
+        "values".into(),
+        values
+            .into_iter()
+            .map(Dynamic::from)
+            .collect::<Array>()
+            .into(),
+    );
+    map.into()
+}
+
+/// Convert a `[key, value]` reply from BLPOP/BRPOP into `#{key, value}`
+fn pop_reply(value: Value) -> Dynamic {
+    let Ok(Some((key, value))) = Option::<(String, String)>::from_redis_value(&value) else {
+        return Dynamic::UNIT;
+    };
+    let mut map = Map::new();
+    map.insert("key".into(), key.into());
+    map.insert("value".into(), value.into());
+    map.into()
+}
 
 impl RedisClient {
     pub fn lpush(&mut self, key: &str, value: &str) -> i64 {
@@ -57,6 +121,240 @@ impl RedisClient {
         let mut conn = self.conn.lock().unwrap();
         conn.lset::<_, _, ()>(key, index as isize, value).is_ok()
     }
+
+    /// Run a blocking list command in slices of at most
+    /// [`POLL_INTERVAL`](crate::client::POLL_INTERVAL).
+    ///
+    /// `command` builds the command for a timeout in seconds. Returns
+    /// `Value::Nil` on timeout, shutdown or cancellation.
+    pub(crate) fn block_on(&self, timeout_ms: i64, command: impl Fn(f64) -> redis::Cmd) -> Value {
+        let deadline =
+            (timeout_ms > 0).then(|| Instant::now() + Duration::from_millis(timeout_ms as u64));
+
+        while !self.is_stopped() {
+            let Some(slice) = next_wait(deadline, &self.run) else {
+                break;
+            };
+
+            let mut conn = self.conn.lock().unwrap();
+            match command(slice.as_secs_f64().max(0.001)).query::<Value>(&mut *conn) {
+                Ok(Value::Nil) => continue,
+                Ok(reply) => return reply,
+                Err(e) => {
+                    eprintln!("Redis error: {}", e);
+                    break;
+                }
+            }
+        }
+
+        Value::Nil
+    }
+
+    fn query_list_value(&self, cmd: &redis::Cmd) -> Value {
+        let mut conn = self.conn.lock().unwrap();
+        cmd.query::<Value>(&mut *conn).unwrap_or_else(|e| {
+            eprintln!("Redis error: {}", e);
+            Value::Nil
+        })
+    }
+
+    /// Push several values onto the head of a list, returning its new length
+    pub fn lpush_many(&mut self, key: &str, values: Array) -> i64 {
+        if values.is_empty() {
+            return self.llen(key);
+        }
+        let mut conn = self.conn.lock().unwrap();
+        conn.lpush::<_, _, i64>(key, to_strings(&values))
+            .unwrap_or(0)
+    }
+
+    /// Push several values onto the tail of a list, returning its new length
+    pub fn rpush_many(&mut self, key: &str, values: Array) -> i64 {
+        if values.is_empty() {
+            return self.llen(key);
+        }
+        let mut conn = self.conn.lock().unwrap();
+        conn.rpush::<_, _, i64>(key, to_strings(&values))
+            .unwrap_or(0)
+    }
+
+    /// Push onto the head only if the list exists, returning its length (0 if missing)
+    pub fn lpushx(&mut self, key: &str, values: Dynamic) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        conn.lpush_exists::<_, _, i64>(key, one_or_many(&values))
+            .unwrap_or(0)
+    }
+
+    /// Push onto the tail only if the list exists, returning its length (0 if missing)
+    pub fn rpushx(&mut self, key: &str, values: Dynamic) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        conn.rpush_exists::<_, _, i64>(key, one_or_many(&values))
+            .unwrap_or(0)
+    }
+
+    /// Insert `value` `BEFORE` or `AFTER` the first occurrence of `pivot`.
+    ///
+    /// Returns the new length, -1 if the pivot was not found, or 0 if the list is missing.
+    pub fn linsert(&mut self, key: &str, position: &str, pivot: &str, value: &str) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("LINSERT")
+            .arg(key)
+            .arg(position.to_uppercase())
+            .arg(pivot)
+            .arg(value)
+            .query::<i64>(&mut *conn)
+            .unwrap_or(0)
+    }
+
+    /// Remove `count` occurrences of `value` (from the tail if negative, all if 0)
+    pub fn lrem(&mut self, key: &str, count: i64, value: &str) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        conn.lrem::<_, _, i64>(key, count as isize, value)
+            .unwrap_or(0)
+    }
+
+    /// Trim a list to the elements between `start` and `stop`
+    pub fn ltrim(&mut self, key: &str, start: i64, stop: i64) -> bool {
+        let mut conn = self.conn.lock().unwrap();
+        conn.ltrim::<_, ()>(key, start as isize, stop as isize)
+            .is_ok()
+    }
+
+    /// Find the index of `element`, or `()` if it is not in the list
+    pub fn lpos(&mut self, key: &str, element: &str) -> Dynamic {
+        self.lpos_with(key, element, Map::new())
+    }
+
+    /// Find `element` with options `rank`, `count` and `maxlen`.
+    ///
+    /// With `count`, returns an array of indexes; otherwise an index or `()`.
+    pub fn lpos_with(&mut self, key: &str, element: &str, options: Map) -> Dynamic {
+        let mut cmd = redis::cmd("LPOS");
+        cmd.arg(key).arg(element);
+        for name in ["rank", "count", "maxlen"] {
+            if let Some(value) = options.get(name).and_then(|v| v.as_int().ok()) {
+                cmd.arg(name.to_uppercase()).arg(value);
+            }
+        }
+        optional_string(self.query_list_value(&cmd))
+    }
+
+    /// Pop up to `count` values from the head of a list; a `count` below 1 pops nothing
+    pub fn lpop_count(&mut self, key: &str, count: i64) -> Array {
+        let Some(count) = NonZeroUsize::new(count.max(0) as usize) else {
+            return Array::new();
+        };
+        let mut conn = self.conn.lock().unwrap();
+        conn.lpop::<_, Option<Vec<String>>>(key, Some(count))
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+            .into_iter()
+            .map(Dynamic::from)
+            .collect()
+    }
+
+    /// Pop up to `count` values from the tail of a list; a `count` below 1 pops nothing
+    pub fn rpop_count(&mut self, key: &str, count: i64) -> Array {
+        let Some(count) = NonZeroUsize::new(count.max(0) as usize) else {
+            return Array::new();
+        };
+        let mut conn = self.conn.lock().unwrap();
+        conn.rpop::<_, Option<Vec<String>>>(key, Some(count))
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+            .into_iter()
+            .map(Dynamic::from)
+            .collect()
+    }
+
+    /// Atomically pop from one end of `source` and push onto one end of
+    /// `destination` (`"LEFT"` or `"RIGHT"`), returning the moved value or `()`
+    pub fn lmove(&mut self, source: &str, destination: &str, from: &str, to: &str) -> Dynamic {
+        let mut cmd = redis::cmd("LMOVE");
+        cmd.arg(source)
+            .arg(destination)
+            .arg(from.to_uppercase())
+            .arg(to.to_uppercase());
+        optional_string(self.query_list_value(&cmd))
+    }
+
+    /// Blocking [`lmove`](Self::lmove), waiting up to `timeout_ms` for `source` to have a value
+    pub fn blmove(
+        &mut self,
+        source: &str,
+        destination: &str,
+        from: &str,
+        to: &str,
+        timeout_ms: i64,
+    ) -> Dynamic {
+        let (from, to) = (from.to_uppercase(), to.to_uppercase());
+        optional_string(self.block_on(timeout_ms, |timeout| {
+            let mut cmd = redis::cmd("BLMOVE");
+            cmd.arg(source)
+                .arg(destination)
+                .arg(&from)
+                .arg(&to)
+                .arg(timeout);
+            cmd
+        }))
+    }
+
+    /// Pop up to `count` values from the first non-empty list, as `#{key, values}` or `()`
+    pub fn lmpop(&mut self, keys: Array, direction: &str, count: i64) -> Dynamic {
+        if keys.is_empty() {
+            return Dynamic::UNIT;
+        }
+        let mut cmd = redis::cmd("LMPOP");
+        cmd.arg(keys.len())
+            .arg(to_strings(&keys))
+            .arg(direction.to_uppercase())
+            .arg("COUNT")
+            .arg(count.max(1));
+        mpop_reply(self.query_list_value(&cmd))
+    }
+
+    /// Blocking [`lmpop`](Self::lmpop), waiting up to `timeout_ms`
+    pub fn blmpop(&mut self, keys: Array, direction: &str, count: i64, timeout_ms: i64) -> Dynamic {
+        if keys.is_empty() {
+            return Dynamic::UNIT;
+        }
+        let (keys, direction) = (to_strings(&keys), direction.to_uppercase());
+        mpop_reply(self.block_on(timeout_ms, |timeout| {
+            let mut cmd = redis::cmd("BLMPOP");
+            cmd.arg(timeout)
+                .arg(keys.len())
+                .arg(&keys)
+                .arg(&direction)
+                .arg("COUNT")
+                .arg(count.max(1));
+            cmd
+        }))
+    }
+
+    /// Pop from the head of the first non-empty list, waiting up to `timeout_ms`.
+    ///
+    /// `keys` is a key or an array of keys. Returns `#{key, value}` or `()` on timeout.
+    pub fn blpop(&mut self, keys: Dynamic, timeout_ms: i64) -> Dynamic {
+        self.blocking_pop("BLPOP", one_or_many(&keys), timeout_ms)
+    }
+
+    /// Pop from the tail of the first non-empty list, waiting up to `timeout_ms`
+    pub fn brpop(&mut self, keys: Dynamic, timeout_ms: i64) -> Dynamic {
+        self.blocking_pop("BRPOP", one_or_many(&keys), timeout_ms)
+    }
+
+    fn blocking_pop(&self, command: &str, keys: Vec<String>, timeout_ms: i64) -> Dynamic {
+        if keys.is_empty() {
+            return Dynamic::UNIT;
+        }
+        pop_reply(self.block_on(timeout_ms, |timeout| {
+            let mut cmd = redis::cmd(command);
+            cmd.arg(&keys).arg(timeout);
+            cmd
+        }))
+    }
 }
 
 pub fn register_list_methods(engine: &mut Engine) {
@@ -68,5 +366,28 @@ pub fn register_list_methods(engine: &mut Engine) {
         .register_fn("llen", RedisClient::llen)
         .register_fn("lrange", RedisClient::lrange)
         .register_fn("lindex", RedisClient::lindex)
-        .register_fn("lset", RedisClient::lset);
+        .register_fn("lset", RedisClient::lset)
+        .register_fn("lpush", RedisClient::lpush_many)
+        .register_fn("rpush", RedisClient::rpush_many)
+        .register_fn("lpushx", RedisClient::lpushx)
+        .register_fn("rpushx", RedisClient::rpushx)
+        .register_fn("linsert", RedisClient::linsert)
+        .register_fn("lrem", RedisClient::lrem)
+        .register_fn("ltrim", RedisClient::ltrim)
+        .register_fn("lpos", RedisClient::lpos)
+        .register_fn("lpos", RedisClient::lpos_with)
+        .register_fn("lpop", RedisClient::lpop_count)
+        .register_fn("rpop", RedisClient::rpop_count)
+        .register_fn("lmove", RedisClient::lmove)
+        .register_fn("blmove", RedisClient::blmove)
+        .register_fn(
+            "lmpop",
+            |client: &mut RedisClient, keys: Array, direction: &str| {
+                client.lmpop(keys, direction, 1)
+            },
+        )
+        .register_fn("lmpop", RedisClient::lmpop)
+        .register_fn("blmpop", RedisClient::blmpop)
+        .register_fn("blpop", RedisClient::blpop)
+        .register_fn("brpop", RedisClient::brpop);
 }
@@ -0,0 +1,323 @@
+//! Distributed locks for Redis Rhai integration
+//!
+//! A lock is a key set with `SET NX PX` to a random token. Only the holder
+//! of the token can release or extend it, and both checks run atomically in
+//! a Lua script, so a lock that expired and was taken by another worker is
+//! never released by mistake.
+//!
+//! # Example
+//! ```rhai
+//! let lock = redis.lock("lock:report", 10000);
+//! if lock != () {
+//!     build_report();
+//!     lock.unlock();
+//! }
+//!
+//! // Wait up to 2 seconds for the lock, and release it even if the body throws
+//! let total = redis.with_lock("lock:report", 10000, 2000, || {
+//!     redis.incr("reports")
+//! });
+//! ```
+//!
+//! [`Redlock`] acquires the same lock on a majority of independent Redis
+//! instances, for callers that cannot rely on a single server.
+
+use crate::client::RedisClient;
+use rand::Rng;
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, NativeCallContext};
+use std::time::{Duration, Instant};
+
+pub(crate) const UNLOCK_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+const EXTEND_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+/// Delay between acquisition attempts, chosen at random to spread out retries
+const RETRY_JITTER_MS: std::ops::RangeInclusive<u64> = 10..=50;
+
+pub(crate) fn random_token() -> String {
+    format!("{:032x}", rand::thread_rng().r#gen::<u128>())
+}
+
+/// Sleep before the next attempt, returning `false` once `deadline` has passed.
+///
+/// With no deadline, always sleeps and returns `true`.
+pub(crate) fn wait_for_retry(deadline: Option<Instant>) -> bool {
+    let jitter = Duration::from_millis(rand::thread_rng().gen_range(RETRY_JITTER_MS));
+    let pause = match deadline {
+        Some(deadline) => jitter.min(deadline.saturating_duration_since(Instant::now())),
+        None => jitter,
+    };
+    if pause.is_zero() {
+        return false;
+    }
+    std::thread::sleep(pause);
+    true
+}
+
+/// A held lock; the key is owned for as long as it holds this handle's token
+#[derive(Clone)]
+pub struct Lock {
+    client: RedisClient,
+    name: String,
+    token: String,
+}
+
+impl Lock {
+    /// Try once to set `name` to `token` for `ttl_ms`
+    fn acquire(client: &RedisClient, name: &str, token: &str, ttl_ms: i64) -> bool {
+        let mut conn = client.conn.lock().unwrap();
+        let result: redis::RedisResult<Option<String>> = redis::cmd("SET")
+            .arg(name)
+            .arg(token)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_ms.max(1))
+            .query(&mut *conn);
+        match result {
+            Ok(reply) => reply.is_some(),
+            Err(e) => {
+                eprintln!("Redis error: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Run a compare-and-act script, returning whether it acted
+    fn run_script(&self, source: &str, extra: Option<i64>) -> bool {
+        let script = redis::Script::new(source);
+        let mut invocation = script.key(&self.name);
+        invocation.arg(&self.token);
+        if let Some(extra) = extra {
+            invocation.arg(extra);
+        }
+        let mut conn = self.client.conn.lock().unwrap();
+        match invocation.invoke::<i64>(&mut *conn) {
+            Ok(n) => n > 0,
+            Err(e) => {
+                eprintln!("Redis error: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Release the lock if it is still held, returning whether it was
+    pub fn unlock(&mut self) -> bool {
+        self.run_script(UNLOCK_SCRIPT, None)
+    }
+
+    /// Reset the lock's expiry to `ttl_ms` if it is still held
+    pub fn extend(&mut self, ttl_ms: i64) -> bool {
+        self.run_script(EXTEND_SCRIPT, Some(ttl_ms.max(1)))
+    }
+
+    /// Check whether the key still holds this handle's token
+    pub fn is_held(&mut self) -> bool {
+        let mut conn = self.client.conn.lock().unwrap();
+        redis::cmd("GET")
+            .arg(&self.name)
+            .query::<Option<String>>(&mut *conn)
+            .map(|value| value.as_deref() == Some(self.token.as_str()))
+            .unwrap_or(false)
+    }
+
+    /// The lock's key
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// The random token identifying this holder
+    pub fn token(&self) -> String {
+        self.token.clone()
+    }
+}
+
+impl RedisClient {
+    /// Try once to take the lock `name` for `ttl_ms`
+    pub fn lock(&mut self, name: &str, ttl_ms: i64) -> Option<Lock> {
+        self.try_lock(name, ttl_ms, 0)
+    }
+
+    /// Take the lock `name` for `ttl_ms`, retrying for up to `wait_ms`.
+    ///
+    /// Gives up early if the client is shut down.
+    pub fn try_lock(&mut self, name: &str, ttl_ms: i64, wait_ms: i64) -> Option<Lock> {
+        let token = random_token();
+        let deadline = Instant::now() + Duration::from_millis(wait_ms.max(0) as u64);
+        loop {
+            if Lock::acquire(self, name, &token, ttl_ms) {
+                return Some(Lock {
+                    client: self.clone(),
+                    name: name.to_string(),
+                    token,
+                });
+            }
+            if self.is_stopped() || !wait_for_retry(Some(deadline)) {
+                return None;
+            }
+        }
+    }
+
+    /// Run `callback` while holding the lock `name`, releasing it afterwards.
+    ///
+    /// Throws if the lock is not acquired within `wait_ms`. The lock is
+    /// released whether or not the callback throws.
+    pub fn with_lock(
+        &mut self,
+        ctx: &NativeCallContext,
+        name: &str,
+        ttl_ms: i64,
+        wait_ms: i64,
+        callback: FnPtr,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
+        let mut lock = self
+            .try_lock(name, ttl_ms, wait_ms)
+            .ok_or_else(|| format!("Could not acquire lock '{}'", name))?;
+        let result = callback.call_within_context::<Dynamic>(ctx, ());
+        lock.unlock();
+        result
+    }
+}
+
+/// A lock acquired across several independent Redis instances
+///
+/// Follows the Redlock algorithm: the lock is held if a majority of
+/// instances accepted it and time remains before it expires, after allowing
+/// for clock drift.
+pub struct Redlock {
+    clients: Vec<RedisClient>,
+}
+
+/// A lock held by [`Redlock`]
+pub struct RedlockGuard {
+    locks: Vec<Lock>,
+    validity: Duration,
+}
+
+impl Redlock {
+    pub fn new(clients: Vec<RedisClient>) -> Self {
+        Self { clients }
+    }
+
+    fn quorum(&self) -> usize {
+        self.clients.len() / 2 + 1
+    }
+
+    /// Try once to take the lock `name` for `ttl_ms` on a majority of instances
+    pub fn lock(&self, name: &str, ttl_ms: i64) -> Option<RedlockGuard> {
+        self.try_lock(name, ttl_ms, 0)
+    }
+
+    /// Take the lock `name` for `ttl_ms`, retrying for up to `wait_ms`
+    pub fn try_lock(&self, name: &str, ttl_ms: i64, wait_ms: i64) -> Option<RedlockGuard> {
+        if self.clients.is_empty() {
+            return None;
+        }
+        let ttl = Duration::from_millis(ttl_ms.max(1) as u64);
+        let drift = ttl / 100 + Duration::from_millis(2);
+        let deadline = Instant::now() + Duration::from_millis(wait_ms.max(0) as u64);
+
+        loop {
+            let token = random_token();
+            let started = Instant::now();
+            let acquired = self
+                .clients
+                .iter()
+                .filter(|client| Lock::acquire(client, name, &token, ttl_ms))
+                .count();
+
+            // Release on every instance, as a SET may have succeeded without a reply
+            let mut guard = RedlockGuard {
+                locks: self
+                    .clients
+                    .iter()
+                    .map(|client| Lock {
+                        client: client.clone(),
+                        name: name.to_string(),
+                        token: token.clone(),
+                    })
+                    .collect(),
+                validity: ttl.saturating_sub(started.elapsed() + drift),
+            };
+            if acquired >= self.quorum() && !guard.validity.is_zero() {
+                return Some(guard);
+            }
+            guard.unlock();
+
+            if !wait_for_retry(Some(deadline)) {
+                return None;
+            }
+        }
+    }
+}
+
+impl RedlockGuard {
+    /// How long the lock was guaranteed to be held for when it was acquired
+    pub fn validity(&self) -> Duration {
+        self.validity
+    }
+
+    /// Release the lock on every instance, returning how many still held it
+    pub fn unlock(&mut self) -> usize {
+        self.locks
+            .iter_mut()
+            .map(Lock::unlock)
+            .filter(|&released| released)
+            .count()
+    }
+}
+
+fn optional_lock(lock: Option<Lock>) -> Dynamic {
+    lock.map(Dynamic::from).unwrap_or(Dynamic::UNIT)
+}
+
+/// Register the lock type and its methods with the Rhai engine
+pub fn register_lock_methods(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<Lock>("Lock")
+        .register_fn(
+            "lock",
+            |client: &mut RedisClient, name: &str, ttl_ms: i64| {
+                optional_lock(client.lock(name, ttl_ms))
+            },
+        )
+        .register_fn(
+            "try_lock",
+            |client: &mut RedisClient, name: &str, ttl_ms: i64, wait_ms: i64| {
+                optional_lock(client.try_lock(name, ttl_ms, wait_ms))
+            },
+        )
+        .register_fn(
+            "with_lock",
+            |ctx: NativeCallContext,
+             client: &mut RedisClient,
+             name: &str,
+             ttl_ms: i64,
+             callback: FnPtr| { client.with_lock(&ctx, name, ttl_ms, 0, callback) },
+        )
+        .register_fn(
+            "with_lock",
+            |ctx: NativeCallContext,
+             client: &mut RedisClient,
+             name: &str,
+             ttl_ms: i64,
+             wait_ms: i64,
+             callback: FnPtr| {
+                client.with_lock(&ctx, name, ttl_ms, wait_ms, callback)
+            },
+        )
+        .register_fn("unlock", Lock::unlock)
+        .register_fn("extend", Lock::extend)
+        .register_fn("is_held", Lock::is_held)
+        .register_fn("name", |lock: &mut Lock| lock.name())
+        .register_fn("token", |lock: &mut Lock| lock.token());
+}
@@ -0,0 +1,283 @@
+//! Module resolution for `import` statements in Rhai scripts
+//!
+//! Scripts can share helper libraries with `import "lib/ratelimit" as rl;`.
+//! Modules can be loaded from a directory of `.rhai` files, from sources
+//! embedded in the host binary, or from Redis itself so deployed script
+//! libraries live next to the data they operate on.
+//!
+//! # Example
+//! ```no_run
+//! use rhai_redis::modules::RedisModuleResolver;
+//! use rhai_redis::{RedisClient, RedisEngine};
+//!
+//! let client = redis::Client::open("redis://localhost").unwrap();
+//! let redis = RedisClient::new(client.get_connection().unwrap());
+//!
+//! let mut engine = RedisEngine::new();
+//! engine.set_module_resolver(RedisModuleResolver::new(redis.clone()).with_prefix("rhai:lib:"));
+//! engine.set_redis_client(redis);
+//!
+//! engine.run(r#"
+//!     import "ratelimit" as rl;
+//!     rl::check(redis, "user:42");
+//! "#).unwrap();
+//! ```
+//!
+//! Module functions cannot see the caller's scope, so helpers that talk to
+//! Redis take the `redis` object as a parameter.
+
+use crate::client::RedisClient;
+use redis::Commands;
+use rhai::{Engine, EvalAltResult, Module, ModuleResolver, Position, Scope, Shared};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub use rhai::module_resolvers::{
+    FileModuleResolver, ModuleResolversCollection, StaticModuleResolver,
+};
+
+/// Compile and evaluate a module source, tagging errors with the module path
+fn compile_module(
+    engine: &Engine,
+    path: &str,
+    source: &str,
+    pos: Position,
+) -> Result<Shared<Module>, Box<EvalAltResult>> {
+    let mut ast = engine
+        .compile(source)
+        .map_err(|err| EvalAltResult::ErrorInModule(path.to_string(), err.into(), pos))?;
+    ast.set_source(path);
+
+    let mut module = Module::eval_ast_as_new(Scope::new(), &ast, engine)
+        .map_err(|err| EvalAltResult::ErrorInModule(path.to_string(), err, pos))?;
+    module.set_id(path);
+    module.build_index();
+
+    Ok(module.into())
+}
+
+struct CachedModule {
+    module: Shared<Module>,
+    loaded_at: Instant,
+}
+
+/// Cache of compiled modules shared between clones of a resolver
+#[derive(Clone, Default)]
+struct ModuleCache {
+    entries: Arc<Mutex<HashMap<String, CachedModule>>>,
+}
+
+impl ModuleCache {
+    fn get(&self, path: &str, ttl: Option<Duration>) -> Option<Shared<Module>> {
+        let entries = self.entries.lock().unwrap();
+        let cached = entries.get(path)?;
+        match ttl {
+            Some(ttl) if cached.loaded_at.elapsed() >= ttl => None,
+            _ => Some(cached.module.clone()),
+        }
+    }
+
+    fn insert(&self, path: &str, module: Shared<Module>) {
+        self.entries.lock().unwrap().insert(
+            path.to_string(),
+            CachedModule {
+                module,
+                loaded_at: Instant::now(),
+            },
+        );
+    }
+
+    fn invalidate(&self, path: &str) -> bool {
+        self.entries.lock().unwrap().remove(path).is_some()
+    }
+
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// Where a [`RedisModuleResolver`] looks up module sources
+#[derive(Clone)]
+enum RedisModuleSource {
+    /// One string key per module, named `{prefix}{path}`
+    Keys,
+    /// One field per module in a single hash, named `{prefix}{path}`
+    Hash { key: String },
+}
+
+/// Resolves modules from Redis string keys or hash fields.
+///
+/// Compiled modules are cached; clones of a resolver share the same cache,
+/// so keep a clone around to invalidate entries after deploying new sources.
+#[derive(Clone)]
+pub struct RedisModuleResolver {
+    client: RedisClient,
+    source: RedisModuleSource,
+    prefix: String,
+    cache: ModuleCache,
+    cache_enabled: bool,
+    cache_ttl: Option<Duration>,
+}
+
+impl RedisModuleResolver {
+    /// Resolve `import "name"` from the string key `name`
+    pub fn new(client: RedisClient) -> Self {
+        Self {
+            client,
+            source: RedisModuleSource::Keys,
+            prefix: String::new(),
+            cache: ModuleCache::default(),
+            cache_enabled: true,
+            cache_ttl: None,
+        }
+    }
+
+    /// Resolve `import "name"` from field `name` of the hash at `key`
+    pub fn from_hash(client: RedisClient, key: &str) -> Self {
+        Self {
+            source: RedisModuleSource::Hash {
+                key: key.to_string(),
+            },
+            ..Self::new(client)
+        }
+    }
+
+    /// Prepend a prefix to module paths when looking up keys or hash fields
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Enable or disable caching of compiled modules (enabled by default)
+    pub fn with_cache(mut self, enabled: bool) -> Self {
+        self.cache_enabled = enabled;
+        self
+    }
+
+    /// Reload cached modules from Redis once they are older than `ttl`
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Drop the cached module for `path`, returning whether it was cached
+    pub fn invalidate(&self, path: &str) -> bool {
+        self.cache.invalidate(path)
+    }
+
+    /// Drop all cached modules
+    pub fn clear_cache(&self) {
+        self.cache.clear();
+    }
+
+    /// Fetch the source of a module from Redis
+    pub fn load_source(&self, path: &str) -> Option<String> {
+        let name = format!("{}{}", self.prefix, path);
+        let mut conn = self.client.conn.lock().unwrap();
+        match &self.source {
+            RedisModuleSource::Keys => conn.get::<_, Option<String>>(name).ok().flatten(),
+            RedisModuleSource::Hash { key } => {
+                conn.hget::<_, _, Option<String>>(key, name).ok().flatten()
+            }
+        }
+    }
+}
+
+impl ModuleResolver for RedisModuleResolver {
+    fn resolve(
+        &self,
+        engine: &Engine,
+        _source: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Result<Shared<Module>, Box<EvalAltResult>> {
+        if self.cache_enabled {
+            if let Some(module) = self.cache.get(path, self.cache_ttl) {
+                return Ok(module);
+            }
+        }
+
+        let source = self
+            .load_source(path)
+            .ok_or_else(|| EvalAltResult::ErrorModuleNotFound(path.to_string(), pos))?;
+        let module = compile_module(engine, path, &source, pos)?;
+
+        if self.cache_enabled {
+            self.cache.insert(path, module.clone());
+        }
+
+        Ok(module)
+    }
+}
+
+/// Resolves modules from sources embedded in the host program.
+///
+/// Sources are compiled on first import and cached until invalidated.
+#[derive(Clone, Default)]
+pub struct EmbeddedModuleResolver {
+    sources: HashMap<String, String>,
+    cache: ModuleCache,
+}
+
+impl EmbeddedModuleResolver {
+    /// Create an empty resolver
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the source for a module path, replacing any previous source
+    pub fn insert(&mut self, path: &str, source: &str) -> &mut Self {
+        self.sources.insert(path.to_string(), source.to_string());
+        self.cache.invalidate(path);
+        self
+    }
+
+    /// Check whether a module path has a registered source
+    pub fn contains_path(&self, path: &str) -> bool {
+        self.sources.contains_key(path)
+    }
+
+    /// Drop the cached module for `path`, returning whether it was cached
+    pub fn invalidate(&self, path: &str) -> bool {
+        self.cache.invalidate(path)
+    }
+
+    /// Drop all cached modules
+    pub fn clear_cache(&self) {
+        self.cache.clear();
+    }
+}
+
+impl<P: AsRef<str>, S: AsRef<str>> FromIterator<(P, S)> for EmbeddedModuleResolver {
+    fn from_iter<I: IntoIterator<Item = (P, S)>>(iter: I) -> Self {
+        let mut resolver = Self::new();
+        for (path, source) in iter {
+            resolver.insert(path.as_ref(), source.as_ref());
+        }
+        resolver
+    }
+}
+
+impl ModuleResolver for EmbeddedModuleResolver {
+    fn resolve(
+        &self,
+        engine: &Engine,
+        _source: Option<&str>,
+        path: &str,
+        pos: Position,
+    ) -> Result<Shared<Module>, Box<EvalAltResult>> {
+        if let Some(module) = self.cache.get(path, None) {
+            return Ok(module);
+        }
+
+        let source = self
+            .sources
+            .get(path)
+            .ok_or_else(|| EvalAltResult::ErrorModuleNotFound(path.to_string(), pos))?;
+        let module = compile_module(engine, path, source, pos)?;
+        self.cache.insert(path, module.clone());
+
+        Ok(module)
+    }
+}
@@ -1,16 +1,421 @@
 //! Pub/Sub operations for Redis Rhai integration
+//!
+//! Subscriptions run on a dedicated connection, so they need a client
+//! created with [`RedisClient::open`] or [`RedisClient::from_client`]. The
+//! callback runs once per message and may use `redis` freely; returning
+//! `false` unsubscribes.
+//!
+//! # Example
+//! ```rhai
+//! let received = redis.subscribe(["events"], |channel, message| {
+//!     redis.lpush("log:" + channel, message);
+//!     message != "stop"
+//! }, 30000);
+//! ```
 
-use crate::client::RedisClient;
+use crate::client::{next_wait, RedisClient, ShutdownHandle};
+use crate::RedisEngine;
 use redis::Commands;
-use rhai::Engine;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FnPtr, Map, NativeCallContext};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+fn to_strings(values: Array) -> Vec<String> {
+    values.into_iter().map(|v| v.to_string()).collect()
+}
+
+fn timeout_from_ms(timeout_ms: i64) -> Option<Duration> {
+    (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms as u64))
+}
+
+fn redis_err(e: redis::RedisError) -> Box<EvalAltResult> {
+    e.to_string().into()
+}
+
+fn counts_to_map(counts: Vec<(String, i64)>) -> Map {
+    counts
+        .into_iter()
+        .map(|(channel, count)| (channel.into(), Dynamic::from(count)))
+        .collect()
+}
+
+/// Extract `(channel, payload)` from a sharded pub/sub message
+fn shard_message(value: redis::Value) -> Option<(String, String)> {
+    let data = match value {
+        redis::Value::Push {
+            kind: redis::PushKind::SMessage,
+            data,
+        } => data,
+        redis::Value::Array(mut data) => {
+            let kind: String = redis::from_redis_value(data.first()?).ok()?;
+            if kind != "smessage" {
+                return None;
+            }
+            data.remove(0);
+            data
+        }
+        _ => return None,
+    };
+    let mut iter = data.iter();
+    let channel = redis::from_redis_value(iter.next()?).ok()?;
+    let payload = redis::from_redis_value(iter.next()?).ok()?;
+    Some((channel, payload))
+}
 
 impl RedisClient {
     pub fn publish(&mut self, channel: &str, message: &str) -> i64 {
         let mut conn = self.conn.lock().unwrap();
         conn.publish::<_, _, i64>(channel, message).unwrap_or(0)
     }
+
+    /// Publish a message to a shard channel
+    pub fn spublish(&mut self, channel: &str, message: &str) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("SPUBLISH")
+            .arg(channel)
+            .arg(message)
+            .query::<i64>(&mut *conn)
+            .unwrap_or(0)
+    }
+
+    /// List active channels matching a pattern
+    pub fn pubsub_channels(&mut self, pattern: &str) -> Array {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("PUBSUB")
+            .arg("CHANNELS")
+            .arg(pattern)
+            .query::<Vec<String>>(&mut *conn)
+            .unwrap_or_default()
+            .into_iter()
+            .map(Dynamic::from)
+            .collect()
+    }
+
+    /// Get the number of subscribers per channel as a map
+    pub fn pubsub_numsub(&mut self, channels: Array) -> Map {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("PUBSUB")
+            .arg("NUMSUB")
+            .arg(to_strings(channels))
+            .query::<Vec<(String, i64)>>(&mut *conn)
+            .map(counts_to_map)
+            .unwrap_or_default()
+    }
+
+    /// Get the number of pattern subscriptions across all clients
+    pub fn pubsub_numpat(&mut self) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("PUBSUB")
+            .arg("NUMPAT")
+            .query::<i64>(&mut *conn)
+            .unwrap_or(0)
+    }
+
+    /// List active shard channels matching a pattern
+    pub fn pubsub_shardchannels(&mut self, pattern: &str) -> Array {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("PUBSUB")
+            .arg("SHARDCHANNELS")
+            .arg(pattern)
+            .query::<Vec<String>>(&mut *conn)
+            .unwrap_or_default()
+            .into_iter()
+            .map(Dynamic::from)
+            .collect()
+    }
+
+    /// Get the number of subscribers per shard channel as a map
+    pub fn pubsub_shardnumsub(&mut self, channels: Array) -> Map {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("PUBSUB")
+            .arg("SHARDNUMSUB")
+            .arg(to_strings(channels))
+            .query::<Vec<(String, i64)>>(&mut *conn)
+            .map(counts_to_map)
+            .unwrap_or_default()
+    }
+
+    /// Check whether the host has asked long-running scripts to stop
+    pub fn is_shutdown(&mut self) -> bool {
+        self.is_stopped()
+    }
+
+    /// Receive messages on a dedicated connection until `handler` returns
+    /// `false`, the timeout elapses, or shutdown is requested.
+    ///
+    /// Returns the number of messages handled.
+    pub fn listen<F>(
+        &self,
+        channels: &[String],
+        patterns: &[String],
+        timeout: Option<Duration>,
+        mut handler: F,
+    ) -> Result<i64, Box<EvalAltResult>>
+    where
+        F: FnMut(&redis::Msg) -> Result<bool, Box<EvalAltResult>>,
+    {
+        let mut conn = self.dedicated_connection().map_err(redis_err)?;
+        let mut pubsub = conn.as_pubsub();
+        if !channels.is_empty() {
+            pubsub.subscribe(channels).map_err(redis_err)?;
+        }
+        if !patterns.is_empty() {
+            pubsub.psubscribe(patterns).map_err(redis_err)?;
+        }
+
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut handled = 0;
+
+        while !self.is_stopped() {
+            let Some(wait) = next_wait(deadline, &self.run) else {
+                break;
+            };
+            pubsub.set_read_timeout(Some(wait)).map_err(redis_err)?;
+
+            match pubsub.get_message() {
+                Ok(msg) => {
+                    handled += 1;
+                    if !handler(&msg)? {
+                        break;
+                    }
+                }
+                Err(e) if e.is_timeout() => continue,
+                Err(e) => return Err(redis_err(e)),
+            }
+        }
+
+        Ok(handled)
+    }
+
+    /// Subscribe to channels, calling `callback(channel, message)` for each message
+    pub fn subscribe(
+        &mut self,
+        ctx: &NativeCallContext,
+        channels: Array,
+        callback: FnPtr,
+        timeout_ms: i64,
+    ) -> Result<i64, Box<EvalAltResult>> {
+        self.listen(
+            &to_strings(channels),
+            &[],
+            timeout_from_ms(timeout_ms),
+            |msg| {
+                let channel = msg.get_channel_name().to_string();
+                let payload: String = msg.get_payload().unwrap_or_default();
+                let result: Dynamic = callback.call_within_context(ctx, (channel, payload))?;
+                Ok(result.as_bool().unwrap_or(true))
+            },
+        )
+    }
+
+    /// Subscribe to patterns, calling `callback(pattern, channel, message)` for each message
+    pub fn psubscribe(
+        &mut self,
+        ctx: &NativeCallContext,
+        patterns: Array,
+        callback: FnPtr,
+        timeout_ms: i64,
+    ) -> Result<i64, Box<EvalAltResult>> {
+        self.listen(
+            &[],
+            &to_strings(patterns),
+            timeout_from_ms(timeout_ms),
+            |msg| {
+                let pattern: String = msg.get_pattern().unwrap_or_default();
+                let channel = msg.get_channel_name().to_string();
+                let payload: String = msg.get_payload().unwrap_or_default();
+                let result: Dynamic =
+                    callback.call_within_context(ctx, (pattern, channel, payload))?;
+                Ok(result.as_bool().unwrap_or(true))
+            },
+        )
+    }
+
+    /// Subscribe to shard channels, calling `callback(channel, message)` for each message
+    pub fn ssubscribe(
+        &mut self,
+        ctx: &NativeCallContext,
+        channels: Array,
+        callback: FnPtr,
+        timeout_ms: i64,
+    ) -> Result<i64, Box<EvalAltResult>> {
+        let mut conn = self.dedicated_connection().map_err(redis_err)?;
+        let subscribe = redis::cmd("SSUBSCRIBE")
+            .arg(to_strings(channels))
+            .get_packed_command();
+        conn.send_packed_command(&subscribe).map_err(redis_err)?;
+
+        let deadline = timeout_from_ms(timeout_ms).map(|t| Instant::now() + t);
+        let mut handled = 0;
+
+        while !self.is_stopped() {
+            let Some(wait) = next_wait(deadline, &self.run) else {
+                break;
+            };
+            conn.set_read_timeout(Some(wait)).map_err(redis_err)?;
+
+            match conn.recv_response() {
+                Ok(value) => {
+                    // Subscription confirmations are skipped
+                    let Some((channel, payload)) = shard_message(value) else {
+                        continue;
+                    };
+                    handled += 1;
+                    let result: Dynamic = callback.call_within_context(ctx, (channel, payload))?;
+                    if !result.as_bool().unwrap_or(true) {
+                        break;
+                    }
+                }
+                Err(e) if e.is_timeout() => continue,
+                Err(e) => return Err(redis_err(e)),
+            }
+        }
+
+        Ok(handled)
+    }
+}
+
+/// A subscriber script running on a background thread.
+///
+/// The script gets its own engine and connections. Call
+/// [`stop`](Subscriber::stop) to end blocked subscriptions and wait for the
+/// script to finish; scripts that resubscribe in a loop should check
+/// `redis.is_shutdown()`.
+///
+/// ```no_run
+/// use rhai_redis::pubsub::Subscriber;
+///
+/// let client = redis::Client::open("redis://localhost").unwrap();
+/// let subscriber = Subscriber::spawn(client, r#"
+///     while !redis.is_shutdown() {
+///         redis.subscribe(["events"], |channel, message| {
+///             redis.incr("events:count");
+///         });
+///     }
+/// "#);
+///
+/// // ... later
+/// subscriber.stop().unwrap();
+/// ```
+pub struct Subscriber {
+    shutdown: ShutdownHandle,
+    thread: JoinHandle<crate::Result<()>>,
+}
+
+impl Subscriber {
+    /// Run `script` on a new thread with a fresh [`RedisEngine`]
+    pub fn spawn(client: redis::Client, script: &str) -> Self {
+        Self::spawn_with(client, script, |_| {})
+    }
+
+    /// Like [`spawn`](Subscriber::spawn), with a hook to configure the engine first
+    pub fn spawn_with<F>(client: redis::Client, script: &str, setup: F) -> Self
+    where
+        F: FnOnce(&mut RedisEngine) + Send + 'static,
+    {
+        let shutdown = ShutdownHandle::new();
+        let handle = shutdown.clone();
+        let script = script.to_string();
+
+        let thread = std::thread::spawn(move || {
+            let mut redis_client = RedisClient::from_client(client)?;
+            redis_client.shutdown = handle;
+
+            let mut engine = RedisEngine::new();
+            engine.set_redis_client(redis_client);
+            setup(&mut engine);
+            engine.run(&script)
+        });
+
+        Self { shutdown, thread }
+    }
+
+    /// Get a handle that can request shutdown from elsewhere
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// Check whether the script has finished
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Wait for the script to finish without requesting shutdown
+    pub fn join(self) -> crate::Result<()> {
+        self.thread
+            .join()
+            .map_err(|_| crate::Error::Script("Subscriber thread panicked".into()))?
+    }
+
+    /// Request shutdown and wait for the script to finish
+    pub fn stop(self) -> crate::Result<()> {
+        self.shutdown.shutdown();
+        self.join()
+    }
 }
 
 pub fn register_pubsub_methods(engine: &mut Engine) {
-    engine.register_fn("publish", RedisClient::publish);
+    engine
+        .register_fn("publish", RedisClient::publish)
+        .register_fn("spublish", RedisClient::spublish)
+        .register_fn("pubsub_channels", RedisClient::pubsub_channels)
+        .register_fn("pubsub_channels", |client: &mut RedisClient| {
+            client.pubsub_channels("*")
+        })
+        .register_fn("pubsub_numsub", RedisClient::pubsub_numsub)
+        .register_fn("pubsub_numpat", RedisClient::pubsub_numpat)
+        .register_fn("pubsub_shardchannels", RedisClient::pubsub_shardchannels)
+        .register_fn("pubsub_shardchannels", |client: &mut RedisClient| {
+            client.pubsub_shardchannels("*")
+        })
+        .register_fn("pubsub_shardnumsub", RedisClient::pubsub_shardnumsub)
+        .register_fn("is_shutdown", RedisClient::is_shutdown)
+        .register_fn(
+            "subscribe",
+            |ctx: NativeCallContext, client: &mut RedisClient, channels: Array, callback: FnPtr| {
+                client.subscribe(&ctx, channels, callback, 0)
+            },
+        )
+        .register_fn(
+            "subscribe",
+            |ctx: NativeCallContext,
+             client: &mut RedisClient,
+             channels: Array,
+             callback: FnPtr,
+             timeout_ms: i64| {
+                client.subscribe(&ctx, channels, callback, timeout_ms)
+            },
+        )
+        .register_fn(
+            "psubscribe",
+            |ctx: NativeCallContext, client: &mut RedisClient, patterns: Array, callback: FnPtr| {
+                client.psubscribe(&ctx, patterns, callback, 0)
+            },
+        )
+        .register_fn(
+            "psubscribe",
+            |ctx: NativeCallContext,
+             client: &mut RedisClient,
+             patterns: Array,
+             callback: FnPtr,
+             timeout_ms: i64| {
+                client.psubscribe(&ctx, patterns, callback, timeout_ms)
+            },
+        )
+        .register_fn(
+            "ssubscribe",
+            |ctx: NativeCallContext, client: &mut RedisClient, channels: Array, callback: FnPtr| {
+                client.ssubscribe(&ctx, channels, callback, 0)
+            },
+        )
+        .register_fn(
+            "ssubscribe",
+            |ctx: NativeCallContext,
+             client: &mut RedisClient,
+             channels: Array,
+             callback: FnPtr,
+             timeout_ms: i64| {
+                client.ssubscribe(&ctx, channels, callback, timeout_ms)
+            },
+        );
 }
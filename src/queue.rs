@@ -0,0 +1,448 @@
+//! Reliable job queues built on lists and sorted sets
+//!
+//! `redis.queue(name)` returns a queue whose jobs survive worker crashes.
+//! Job ids move through these keys:
+//!
+//! | Key                 | Type   | Holds                                           |
+//! |---------------------|--------|-------------------------------------------------|
+//! | `{name}:pending`    | list   | Jobs ready to run                               |
+//! | `{name}:processing` | list   | Jobs reserved by a worker                       |
+//! | `{name}:leases`     | zset   | Reserved jobs, scored by visibility deadline    |
+//! | `{name}:delayed`    | zset   | Delayed and backed-off jobs, scored by due time |
+//! | `{name}:dead`       | list   | Jobs that used up `max_attempts`                |
+//! | `{name}:jobs`       | hash   | Job id to JSON document with the payload        |
+//! | `{name}:attempts`   | hash   | Job id to number of reservations                |
+//! | `{name}:seq`        | string | Counter used to assign job ids                  |
+//!
+//! `reserve` moves a job from `pending` to `processing` with BLMOVE and
+//! leases it for the queue's visibility timeout. A job that is neither
+//! acked nor nacked before its lease runs out is retried, so handlers should
+//! be idempotent. Due delayed jobs are promoted and expired leases recovered
+//! while reserving; `promote` and `recover` can also be called directly.
+//!
+//! # Example
+//! ```rhai
+//! let emails = redis.queue("emails", #{visibility_ms: 60000});
+//! emails.enqueue(#{to: "ada@example.com"});
+//! emails.enqueue(#{to: "bob@example.com"}, #{delay_ms: 5000, max_attempts: 5});
+//!
+//! let job = emails.reserve(1000);
+//! if job != () {
+//!     try {
+//!         send(job.payload.to);
+//!         emails.ack(job);
+//!     } catch (err) {
+//!         emails.nack(job, 1000 * job.attempts);   // back off before retrying
+//!     }
+//! }
+//! ```
+
+use crate::client::{next_wait, RedisClient};
+use crate::json::to_json;
+use redis::Value;
+use rhai::{Array, Dynamic, Engine, Map};
+use std::time::{Duration, Instant};
+
+const DEFAULT_VISIBILITY_MS: i64 = 30_000;
+const DEFAULT_MAX_ATTEMPTS: i64 = 3;
+/// Minimum time between recovery passes while waiting in `reserve`
+const RECOVERY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Key names and helpers shared by every queue script
+const PRELUDE: &str = r#"
+local pending, processing, leases, delayed, dead, jobs, attempts =
+    KEYS[1], KEYS[2], KEYS[3], KEYS[4], KEYS[5], KEYS[6], KEYS[7]
+local time = redis.call("TIME")
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+local function make_ready(id, job)
+    if tonumber(job.priority or 0) > 0 then
+        redis.call("RPUSH", pending, id)
+    else
+        redis.call("LPUSH", pending, id)
+    end
+end
+
+-- Requeue a released job, or bury it once it has used up its attempts
+local function retry(id, backoff)
+    local doc = redis.call("HGET", jobs, id)
+    if not doc then
+        redis.call("HDEL", attempts, id)
+        return 0
+    end
+    local job = cjson.decode(doc)
+    if tonumber(redis.call("HGET", attempts, id) or "0") >= tonumber(job.max_attempts) then
+        redis.call("LPUSH", dead, id)
+        return -1
+    end
+    if backoff > 0 then
+        redis.call("ZADD", delayed, now + backoff, id)
+    else
+        make_ready(id, job)
+    end
+    return 1
+end
+"#;
+
+const ENQUEUE: &str = r#"
+local id = tostring(redis.call("INCR", KEYS[8]))
+redis.call("HSET", jobs, id, ARGV[1])
+if tonumber(ARGV[2]) > 0 then
+    redis.call("ZADD", delayed, now + tonumber(ARGV[2]), id)
+else
+    make_ready(id, cjson.decode(ARGV[1]))
+end
+return id
+"#;
+
+const LEASE: &str = r#"
+local id = ARGV[1]
+local doc = redis.call("HGET", jobs, id)
+if not doc then
+    redis.call("LREM", processing, 1, id)
+    return false
+end
+redis.call("ZADD", leases, now + tonumber(ARGV[2]), id)
+return {redis.call("HINCRBY", attempts, id, 1), doc}
+"#;
+
+const NACK: &str = r#"
+redis.call("ZREM", leases, ARGV[1])
+if redis.call("LREM", processing, 1, ARGV[1]) == 0 then
+    return 0
+end
+return retry(ARGV[1], tonumber(ARGV[2]))
+"#;
+
+const PROMOTE: &str = r#"
+local ids = redis.call("ZRANGEBYSCORE", delayed, "-inf", now, "LIMIT", 0, 1000)
+for _, id in ipairs(ids) do
+    redis.call("ZREM", delayed, id)
+    local doc = redis.call("HGET", jobs, id)
+    if doc then make_ready(id, cjson.decode(doc)) end
+end
+return #ids
+"#;
+
+const RECOVER: &str = r#"
+-- Lease jobs orphaned between BLMOVE and their lease, so they expire too
+for _, id in ipairs(redis.call("LRANGE", processing, 0, -1)) do
+    if not redis.call("ZSCORE", leases, id) then
+        redis.call("ZADD", leases, now + tonumber(ARGV[1]), id)
+    end
+end
+local recovered = 0
+for _, id in ipairs(redis.call("ZRANGEBYSCORE", leases, "-inf", now)) do
+    redis.call("ZREM", leases, id)
+    if redis.call("LREM", processing, 1, id) > 0 then
+        retry(id, 0)
+        recovered = recovered + 1
+    end
+end
+return recovered
+"#;
+
+/// Accept a job map from `reserve` or a bare job id
+fn job_id(job: &Dynamic) -> String {
+    match job.read_lock::<Map>() {
+        Some(job) => job.get("id").map(|id| id.to_string()).unwrap_or_default(),
+        None => job.to_string(),
+    }
+}
+
+/// A reliable job queue
+#[derive(Clone)]
+pub struct Queue {
+    client: RedisClient,
+    name: String,
+    visibility_ms: i64,
+    last_recovery: Option<Instant>,
+}
+
+impl Queue {
+    pub fn new(client: RedisClient, name: &str) -> Self {
+        Self {
+            client,
+            name: name.to_string(),
+            visibility_ms: DEFAULT_VISIBILITY_MS,
+            last_recovery: None,
+        }
+    }
+
+    /// Retry reserved jobs that are not acked within `visibility_ms`
+    pub fn with_visibility(mut self, visibility_ms: i64) -> Self {
+        self.visibility_ms = visibility_ms.max(1);
+        self
+    }
+
+    fn key(&self, suffix: &str) -> String {
+        format!("{}:{}", self.name, suffix)
+    }
+
+    fn keys(&self) -> [String; 8] {
+        [
+            "pending",
+            "processing",
+            "leases",
+            "delayed",
+            "dead",
+            "jobs",
+            "attempts",
+            "seq",
+        ]
+        .map(|suffix| self.key(suffix))
+    }
+
+    fn run_script(&self, body: &str, args: &[String]) -> Value {
+        let script = redis::Script::new(&format!("{}{}", PRELUDE, body));
+        let mut invocation = script.prepare_invoke();
+        for key in self.keys() {
+            invocation.key(key);
+        }
+        for arg in args {
+            invocation.arg(arg);
+        }
+        let mut conn = self.client.conn.lock().unwrap();
+        invocation.invoke(&mut *conn).unwrap_or_else(|e| {
+            eprintln!("Redis error in queue '{}': {}", self.name, e);
+            Value::Nil
+        })
+    }
+
+    fn run_count(&self, body: &str, args: &[String]) -> i64 {
+        redis::from_redis_value(&self.run_script(body, args)).unwrap_or(0)
+    }
+
+    /// Turn a stored job document into `#{id, payload, attempts, max_attempts}`
+    fn job_map(id: &str, doc: &str, attempts: i64) -> Dynamic {
+        let Ok(mut job) = Engine::new_raw().parse_json(doc, true) else {
+            return Dynamic::UNIT;
+        };
+        job.insert("id".into(), id.into());
+        job.insert("attempts".into(), attempts.into());
+        job.into()
+    }
+
+    /// Add a job and return its id, or `()` if it could not be stored.
+    ///
+    /// Options: `delay_ms` (run later), `priority` (above 0 jumps to the front
+    /// of the queue) and `max_attempts` (reservations before dead-lettering).
+    pub fn enqueue(&mut self, payload: Dynamic, options: Map) -> Dynamic {
+        let int = |name: &str| options.get(name).and_then(|v| v.as_int().ok());
+        let mut doc = Map::new();
+        doc.insert("payload".into(), payload);
+        doc.insert(
+            "max_attempts".into(),
+            int("max_attempts")
+                .unwrap_or(DEFAULT_MAX_ATTEMPTS)
+                .max(1)
+                .into(),
+        );
+        doc.insert("priority".into(), int("priority").unwrap_or(0).into());
+
+        let delay_ms = int("delay_ms").unwrap_or(0).max(0);
+        let reply = self.run_script(ENQUEUE, &[to_json(&doc.into()), delay_ms.to_string()]);
+        // A failed script has already been reported by `run_script`
+        if reply == Value::Nil {
+            return Dynamic::UNIT;
+        }
+        match redis::from_redis_value::<String>(&reply) {
+            Ok(id) => id.into(),
+            Err(e) => {
+                eprintln!("Redis error in queue '{}': {}", self.name, e);
+                Dynamic::UNIT
+            }
+        }
+    }
+
+    /// Lease a job taken off `pending`, returning it or `()` if it was deleted
+    fn lease(&self, id: &str) -> Dynamic {
+        let reply = self.run_script(LEASE, &[id.to_string(), self.visibility_ms.to_string()]);
+        match redis::from_redis_value::<Option<(i64, String)>>(&reply) {
+            Ok(Some((attempts, doc))) => Self::job_map(id, &doc, attempts),
+            _ => Dynamic::UNIT,
+        }
+    }
+
+    /// Wait up to `timeout_ms` for a job, as `#{id, payload, attempts, max_attempts}`.
+    ///
+    /// A timeout of 0 waits until the client is shut down. Returns `()` on timeout.
+    pub fn reserve(&mut self, timeout_ms: i64) -> Dynamic {
+        let deadline =
+            (timeout_ms > 0).then(|| Instant::now() + Duration::from_millis(timeout_ms as u64));
+        let (pending, processing) = (self.key("pending"), self.key("processing"));
+
+        while !self.client.is_stopped() {
+            self.promote();
+            if self
+                .last_recovery
+                .is_none_or(|last| last.elapsed() >= RECOVERY_INTERVAL)
+            {
+                self.recover();
+                self.last_recovery = Some(Instant::now());
+            }
+
+            // Promotion and recovery run between slices, so BLMOVE is issued directly
+            let Some(slice) = next_wait(deadline, &self.client.run) else {
+                break;
+            };
+            let reply = {
+                let mut conn = self.client.conn.lock().unwrap();
+                redis::cmd("BLMOVE")
+                    .arg(&pending)
+                    .arg(&processing)
+                    .arg("RIGHT")
+                    .arg("LEFT")
+                    .arg(slice.as_secs_f64().max(0.001))
+                    .query::<Option<String>>(&mut *conn)
+            };
+            match reply {
+                Ok(Some(id)) => {
+                    let job = self.lease(&id);
+                    if !job.is_unit() {
+                        return job;
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("Redis error: {}", e);
+                    break;
+                }
+            }
+        }
+
+        Dynamic::UNIT
+    }
+
+    /// Mark a reserved job as done and delete it, returning whether it was reserved
+    pub fn ack(&mut self, job: Dynamic) -> bool {
+        let id = job_id(&job);
+        let mut conn = self.client.conn.lock().unwrap();
+        let result: redis::RedisResult<(i64,)> = redis::pipe()
+            .atomic()
+            .lrem(self.key("processing"), 1, &id)
+            .zrem(self.key("leases"), &id)
+            .ignore()
+            .hdel(self.key("jobs"), &id)
+            .ignore()
+            .hdel(self.key("attempts"), &id)
+            .ignore()
+            .query(&mut *conn);
+        match result {
+            Ok((removed,)) => removed > 0,
+            Err(e) => {
+                eprintln!("Redis error: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Release a reserved job for another attempt after `backoff_ms`.
+    ///
+    /// Returns `"retry"`, `"dead"` if it was moved to the dead-letter list, or
+    /// `()` if the job was not reserved.
+    pub fn nack(&mut self, job: Dynamic, backoff_ms: i64) -> Dynamic {
+        let reply = self.run_script(NACK, &[job_id(&job), backoff_ms.max(0).to_string()]);
+        match redis::from_redis_value::<i64>(&reply) {
+            Ok(1) => "retry".into(),
+            Ok(-1) => "dead".into(),
+            _ => Dynamic::UNIT,
+        }
+    }
+
+    /// Move delayed jobs that are due onto the queue, returning how many moved
+    pub fn promote(&mut self) -> i64 {
+        self.run_count(PROMOTE, &[])
+    }
+
+    /// Retry jobs whose lease has expired, returning how many were recovered
+    pub fn recover(&mut self) -> i64 {
+        self.run_count(RECOVER, &[self.visibility_ms.to_string()])
+    }
+
+    /// Number of jobs as `#{pending, processing, delayed, dead}`
+    pub fn stats(&mut self) -> Map {
+        let mut conn = self.client.conn.lock().unwrap();
+        let (pending, processing, delayed, dead): (i64, i64, i64, i64) = redis::pipe()
+            .llen(self.key("pending"))
+            .llen(self.key("processing"))
+            .zcard(self.key("delayed"))
+            .llen(self.key("dead"))
+            .query(&mut *conn)
+            .unwrap_or_default();
+
+        let mut stats = Map::new();
+        stats.insert("pending".into(), pending.into());
+        stats.insert("processing".into(), processing.into());
+        stats.insert("delayed".into(), delayed.into());
+        stats.insert("dead".into(), dead.into());
+        stats
+    }
+
+    /// The most recently dead-lettered jobs, up to `count`
+    pub fn dead_letters(&mut self, count: i64) -> Array {
+        if count <= 0 {
+            return Array::new();
+        }
+        let mut conn = self.client.conn.lock().unwrap();
+        let ids: Vec<String> = redis::cmd("LRANGE")
+            .arg(self.key("dead"))
+            .arg(0)
+            .arg(count - 1)
+            .query(&mut *conn)
+            .unwrap_or_default();
+        if ids.is_empty() {
+            return Array::new();
+        }
+        let (docs, attempts): (Vec<Option<String>>, Vec<Option<i64>>) = redis::pipe()
+            .cmd("HMGET")
+            .arg(self.key("jobs"))
+            .arg(&ids)
+            .cmd("HMGET")
+            .arg(self.key("attempts"))
+            .arg(&ids)
+            .query(&mut *conn)
+            .unwrap_or_default();
+
+        ids.iter()
+            .zip(docs)
+            .zip(attempts)
+            .filter_map(|((id, doc), attempts)| {
+                Some(Self::job_map(id, &doc?, attempts.unwrap_or(0)))
+            })
+            .collect()
+    }
+}
+
+impl RedisClient {
+    /// Open the job queue `name`, with option `visibility_ms`
+    pub fn queue(&mut self, name: &str, options: Map) -> Queue {
+        let queue = Queue::new(self.clone(), name);
+        match options.get("visibility_ms").and_then(|v| v.as_int().ok()) {
+            Some(visibility_ms) => queue.with_visibility(visibility_ms),
+            None => queue,
+        }
+    }
+}
+
+/// Register the queue type and its methods with the Rhai engine
+pub fn register_queue_methods(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<Queue>("Queue")
+        .register_fn("queue", |client: &mut RedisClient, name: &str| {
+            client.queue(name, Map::new())
+        })
+        .register_fn("queue", RedisClient::queue)
+        .register_fn("enqueue", |queue: &mut Queue, payload: Dynamic| {
+            queue.enqueue(payload, Map::new())
+        })
+        .register_fn("enqueue", Queue::enqueue)
+        .register_fn("reserve", Queue::reserve)
+        .register_fn("ack", Queue::ack)
+        .register_fn("nack", |queue: &mut Queue, job: Dynamic| queue.nack(job, 0))
+        .register_fn("nack", Queue::nack)
+        .register_fn("promote", Queue::promote)
+        .register_fn("recover", Queue::recover)
+        .register_fn("stats", Queue::stats)
+        .register_fn("dead_letters", Queue::dead_letters);
+}
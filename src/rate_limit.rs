@@ -0,0 +1,261 @@
+//! Rate limiting for Redis Rhai integration
+//!
+//! `redis.rate_limit(key, options)` records a request against `key` and
+//! reports whether it is allowed. Each algorithm runs as a single Lua script
+//! using the server's clock, so concurrent workers share one consistent view
+//! of the limit.
+//!
+//! | `algorithm`        | Behaviour                                                     |
+//! |--------------------|---------------------------------------------------------------|
+//! | `"fixed_window"`   | `limit` requests per window of `period_ms`, starting on first use |
+//! | `"sliding_window"` | Weighted count over the current and previous window (default) |
+//! | `"token_bucket"`   | Bursts of up to `limit`, refilled at `limit` per `period_ms`  |
+//! | `"gcra"`           | Like a token bucket, but stored as a single timestamp         |
+//!
+//! The result is `#{allowed, remaining, retry_after_ms, reset_ms}`:
+//! `retry_after_ms` is 0 when allowed and -1 if `cost` exceeds `limit`, and
+//! `reset_ms` is how long until the limiter is back to full capacity. A
+//! `cost` of 0 checks the limit without consuming anything.
+//!
+//! # Example
+//! ```rhai
+//! let result = redis.rate_limit("rl:api:" + user, #{
+//!     algorithm: "token_bucket",
+//!     limit: 100,
+//!     period_ms: 60000,
+//! });
+//! if !result.allowed {
+//!     throw "rate limited, retry in " + result.retry_after_ms + "ms";
+//! }
+//! ```
+
+use crate::client::RedisClient;
+use crate::error::{Error, Result};
+use rhai::{Engine, EvalAltResult, Map};
+
+/// Current server time in milliseconds, shared by every script below
+const NOW: &str = r#"
+local time = redis.call("TIME")
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local limit, period, cost = tonumber(ARGV[1]), tonumber(ARGV[2]), tonumber(ARGV[3])
+"#;
+
+const FIXED_WINDOW: &str = r#"
+local count = tonumber(redis.call("GET", KEYS[1]) or "0")
+local ttl = redis.call("PTTL", KEYS[1])
+if ttl < 0 then ttl = period end
+if count + cost > limit then
+    return {0, limit - count, ttl, ttl}
+end
+count = redis.call("INCRBY", KEYS[1], cost)
+if redis.call("PTTL", KEYS[1]) < 0 then
+    redis.call("PEXPIRE", KEYS[1], period)
+end
+return {1, limit - count, 0, ttl}
+"#;
+
+const SLIDING_WINDOW: &str = r#"
+local window = now - now % period
+local data = redis.call("HMGET", KEYS[1], "window", "current", "previous")
+local current, previous = tonumber(data[2]) or 0, tonumber(data[3]) or 0
+if tonumber(data[1]) ~= window then
+    if tonumber(data[1]) == window - period then previous = current else previous = 0 end
+    current = 0
+end
+local elapsed = now - window
+local used = previous * (period - elapsed) / period + current
+local allowed = used + cost <= limit
+local retry = 0
+if allowed then
+    current = current + cost
+    used = used + cost
+    redis.call("HSET", KEYS[1], "window", window, "current", current, "previous", previous)
+    redis.call("PEXPIRE", KEYS[1], 2 * period)
+elseif previous > 0 and current + cost <= limit then
+    retry = math.ceil(period - (limit - current - cost) * period / previous) - elapsed
+else
+    retry = period - elapsed + math.ceil(period - (limit - cost) * period / math.max(current, 1))
+end
+local reset = 0
+if current > 0 then reset = 2 * period - elapsed elseif previous > 0 then reset = period - elapsed end
+return {allowed and 1 or 0, math.max(0, math.floor(limit - used)), math.max(0, retry), reset}
+"#;
+
+const TOKEN_BUCKET: &str = r#"
+local data = redis.call("HMGET", KEYS[1], "tokens", "updated")
+local tokens = tonumber(data[1]) or limit
+local updated = tonumber(data[2]) or now
+tokens = math.min(limit, tokens + math.max(0, now - updated) * limit / period)
+local allowed = tokens >= cost
+local retry = 0
+if allowed then
+    tokens = tokens - cost
+    redis.call("HSET", KEYS[1], "tokens", tokens, "updated", now)
+    redis.call("PEXPIRE", KEYS[1], period)
+else
+    retry = math.ceil((cost - tokens) * period / limit)
+end
+local reset = math.ceil((limit - tokens) * period / limit)
+return {allowed and 1 or 0, math.floor(tokens), retry, reset}
+"#;
+
+const GCRA: &str = r#"
+local interval = period / limit
+local tat = math.max(tonumber(redis.call("GET", KEYS[1]) or now), now)
+local new_tat = tat + interval * cost
+local allow_at = new_tat - period
+if now < allow_at then
+    local remaining = math.max(0, math.floor((now - (tat - period)) / interval))
+    return {0, remaining, math.ceil(allow_at - now), math.ceil(tat - now)}
+end
+redis.call("SET", KEYS[1], new_tat, "PX", math.max(1, math.ceil(new_tat - now)))
+local remaining = math.floor((now - (new_tat - period)) / interval)
+return {1, remaining, 0, math.ceil(new_tat - now)}
+"#;
+
+/// Rate limiting algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Algorithm {
+    FixedWindow,
+    #[default]
+    SlidingWindow,
+    TokenBucket,
+    Gcra,
+}
+
+impl Algorithm {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "fixed_window" => Some(Algorithm::FixedWindow),
+            "sliding_window" => Some(Algorithm::SlidingWindow),
+            "token_bucket" => Some(Algorithm::TokenBucket),
+            "gcra" => Some(Algorithm::Gcra),
+            _ => None,
+        }
+    }
+
+    fn script(self) -> &'static str {
+        match self {
+            Algorithm::FixedWindow => FIXED_WINDOW,
+            Algorithm::SlidingWindow => SLIDING_WINDOW,
+            Algorithm::TokenBucket => TOKEN_BUCKET,
+            Algorithm::Gcra => GCRA,
+        }
+    }
+}
+
+/// A rate limit of `limit` units per `period_ms`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub algorithm: Algorithm,
+    pub limit: i64,
+    pub period_ms: i64,
+    /// Units consumed by each request
+    pub cost: i64,
+}
+
+impl RateLimit {
+    pub fn new(algorithm: Algorithm, limit: i64, period_ms: i64) -> Self {
+        Self {
+            algorithm,
+            limit,
+            period_ms,
+            cost: 1,
+        }
+    }
+
+    /// Consume `cost` units per request
+    pub fn with_cost(mut self, cost: i64) -> Self {
+        self.cost = cost;
+        self
+    }
+
+    /// Build a limit from a script's options map
+    fn from_map(options: &Map) -> std::result::Result<Self, Box<EvalAltResult>> {
+        let algorithm = match options.get("algorithm") {
+            None => Algorithm::default(),
+            Some(name) => {
+                let name = name.to_string();
+                Algorithm::parse(&name)
+                    .ok_or_else(|| format!("Unknown rate limit algorithm '{}'", name))?
+            }
+        };
+        let int = |name: &str| options.get(name).and_then(|v| v.as_int().ok());
+        let limit = int("limit").ok_or("rate_limit requires an integer 'limit'")?;
+        let period_ms = int("period_ms").ok_or("rate_limit requires an integer 'period_ms'")?;
+        Ok(Self::new(algorithm, limit, period_ms).with_cost(int("cost").unwrap_or(1)))
+    }
+}
+
+/// The outcome of a rate-limited request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub allowed: bool,
+    /// Units still available after this request
+    pub remaining: i64,
+    /// How long to wait before retrying: 0 if allowed, -1 if `cost` exceeds `limit`
+    pub retry_after_ms: i64,
+    /// How long until the limiter is back to full capacity
+    pub reset_ms: i64,
+}
+
+impl RateLimitStatus {
+    pub fn to_map(self) -> Map {
+        let mut map = Map::new();
+        map.insert("allowed".into(), self.allowed.into());
+        map.insert("remaining".into(), self.remaining.into());
+        map.insert("retry_after_ms".into(), self.retry_after_ms.into());
+        map.insert("reset_ms".into(), self.reset_ms.into());
+        map
+    }
+}
+
+impl RedisClient {
+    /// Record a request against `key` and report whether `limit` allows it
+    pub fn check_rate_limit(&self, key: &str, limit: &RateLimit) -> Result<RateLimitStatus> {
+        if limit.limit < 1 || limit.period_ms < 1 || limit.cost < 0 {
+            return Err(Error::RateLimit(
+                "limit and period_ms must be positive and cost non-negative".into(),
+            ));
+        }
+
+        let script = redis::Script::new(&format!("{}{}", NOW, limit.algorithm.script()));
+        let mut conn = self.conn.lock().unwrap();
+        let (allowed, remaining, retry_after_ms, reset_ms): (i64, i64, i64, i64) = script
+            .key(key)
+            .arg(limit.limit)
+            .arg(limit.period_ms)
+            .arg(limit.cost)
+            .invoke(&mut *conn)?;
+
+        let allowed = allowed == 1;
+        Ok(RateLimitStatus {
+            allowed,
+            remaining: remaining.max(0),
+            retry_after_ms: if !allowed && limit.cost > limit.limit {
+                -1
+            } else {
+                retry_after_ms
+            },
+            reset_ms,
+        })
+    }
+
+    /// Script form of [`check_rate_limit`](Self::check_rate_limit), with options
+    /// `algorithm`, `limit`, `period_ms` and `cost`
+    pub fn rate_limit(
+        &mut self,
+        key: &str,
+        options: Map,
+    ) -> std::result::Result<Map, Box<EvalAltResult>> {
+        let limit = RateLimit::from_map(&options)?;
+        self.check_rate_limit(key, &limit)
+            .map(RateLimitStatus::to_map)
+            .map_err(|e| e.to_string().into())
+    }
+}
+
+/// Register rate limiting with the Rhai engine
+pub fn register_rate_limit_methods(engine: &mut Engine) {
+    engine.register_fn("rate_limit", RedisClient::rate_limit);
+}
@@ -0,0 +1,257 @@
+//! SCAN-family cursors for Redis Rhai integration
+//!
+//! `scan`, `hscan`, `sscan` and `zscan` return iterators that drive the
+//! cursor lazily, fetching one batch per round trip as the script consumes
+//! them. Unlike `keys`, this never blocks Redis on a large keyspace, and
+//! each loop iteration counts towards the engine's operation limit.
+//!
+//! # Example
+//! ```rhai
+//! for key in redis.scan("user:*", #{count: 500, type: "hash"}) {
+//!     print(key);
+//! }
+//!
+//! for entry in redis.hscan("user:1") {
+//!     print(entry.field + " = " + entry.value);
+//! }
+//!
+//! for entry in redis.zscan("leaderboard") {
+//!     print(entry.member + ": " + entry.score);
+//! }
+//! ```
+//!
+//! As with the underlying commands, an element may be returned more than
+//! once if the collection changes during iteration.
+
+use crate::client::RedisClient;
+use crate::generic::redis_value_to_dynamic;
+use redis::Value;
+use rhai::{Dynamic, Engine, Map};
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanKind {
+    Keys,
+    Hash,
+    Set,
+    SortedSet,
+}
+
+impl ScanKind {
+    fn command(self) -> &'static str {
+        match self {
+            ScanKind::Keys => "SCAN",
+            ScanKind::Hash => "HSCAN",
+            ScanKind::Set => "SSCAN",
+            ScanKind::SortedSet => "ZSCAN",
+        }
+    }
+}
+
+/// Lazily iterates a SCAN, HSCAN, SSCAN or ZSCAN cursor.
+///
+/// Yields key names for `scan`, members for `sscan`, `#{field, value}` for
+/// `hscan` and `#{member, score}` for `zscan`. Iteration stops early if the
+/// client is shut down or the script is cancelled or times out.
+#[derive(Clone)]
+pub struct ScanCursor {
+    client: RedisClient,
+    kind: ScanKind,
+    key: Option<String>,
+    pattern: Option<String>,
+    count: Option<i64>,
+    key_type: Option<String>,
+    cursor: u64,
+    started: bool,
+    buffer: VecDeque<Dynamic>,
+}
+
+impl ScanCursor {
+    fn new(
+        client: RedisClient,
+        kind: ScanKind,
+        key: Option<&str>,
+        pattern: &str,
+        options: &Map,
+    ) -> Self {
+        Self {
+            client,
+            kind,
+            key: key.map(str::to_string),
+            pattern: (!pattern.is_empty() && pattern != "*").then(|| pattern.to_string()),
+            count: options.get("count").and_then(|v| v.as_int().ok()),
+            // Only SCAN accepts TYPE; the keyed variants reject it as a syntax error
+            key_type: options
+                .get("type")
+                .filter(|_| kind == ScanKind::Keys)
+                .map(|v| v.to_string()),
+            cursor: 0,
+            started: false,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    /// Check whether the cursor has been fully iterated
+    pub fn is_finished(&self) -> bool {
+        self.started && self.cursor == 0 && self.buffer.is_empty()
+    }
+
+    /// Fetch the next batch into the buffer, returning `false` once the scan is complete
+    fn fetch(&mut self) -> bool {
+        if self.started && self.cursor == 0 {
+            return false;
+        }
+
+        let mut cmd = redis::cmd(self.kind.command());
+        if let Some(key) = &self.key {
+            cmd.arg(key);
+        }
+        cmd.arg(self.cursor);
+        if let Some(pattern) = &self.pattern {
+            cmd.arg("MATCH").arg(pattern);
+        }
+        if let Some(count) = self.count {
+            cmd.arg("COUNT").arg(count);
+        }
+        if let Some(key_type) = &self.key_type {
+            cmd.arg("TYPE").arg(key_type);
+        }
+
+        let mut conn = self.client.conn.lock().unwrap();
+        let (cursor, items) = match cmd.query::<(u64, Vec<Value>)>(&mut *conn) {
+            Ok(reply) => reply,
+            Err(e) => {
+                eprintln!("Redis error in {}: {}", self.kind.command(), e);
+                self.started = true;
+                self.cursor = 0;
+                return false;
+            }
+        };
+        drop(conn);
+
+        self.started = true;
+        self.cursor = cursor;
+        match self.kind {
+            ScanKind::Keys | ScanKind::Set => {
+                self.buffer
+                    .extend(items.into_iter().map(redis_value_to_dynamic));
+            }
+            ScanKind::Hash => {
+                self.buffer.extend(items.chunks(2).filter_map(|pair| {
+                    let [field, value] = pair else { return None };
+                    let mut entry = Map::new();
+                    entry.insert("field".into(), redis_value_to_dynamic(field.clone()));
+                    entry.insert("value".into(), redis_value_to_dynamic(value.clone()));
+                    Some(Dynamic::from(entry))
+                }));
+            }
+            ScanKind::SortedSet => {
+                self.buffer.extend(items.chunks(2).filter_map(|pair| {
+                    let [member, score] = pair else { return None };
+                    let mut entry = Map::new();
+                    entry.insert("member".into(), redis_value_to_dynamic(member.clone()));
+                    entry.insert(
+                        "score".into(),
+                        redis::from_redis_value::<f64>(score).ok()?.into(),
+                    );
+                    Some(Dynamic::from(entry))
+                }));
+            }
+        }
+        true
+    }
+}
+
+impl Iterator for ScanCursor {
+    type Item = Dynamic;
+
+    fn next(&mut self) -> Option<Dynamic> {
+        loop {
+            if let Some(item) = self.buffer.pop_front() {
+                return Some(item);
+            }
+            // A sparse MATCH can return many empty batches in a row, so check
+            // before every round trip rather than once per item
+            if self.client.is_stopped() || !self.fetch() {
+                return None;
+            }
+        }
+    }
+}
+
+impl RedisClient {
+    /// Iterate over keys matching `pattern`.
+    ///
+    /// Options: `count` (batch size hint) and `type` (only keys of this type).
+    pub fn scan(&mut self, pattern: &str, options: Map) -> ScanCursor {
+        ScanCursor::new(self.clone(), ScanKind::Keys, None, pattern, &options)
+    }
+
+    /// Iterate over the fields of a hash as `#{field, value}`
+    pub fn hscan(&mut self, key: &str, pattern: &str, options: Map) -> ScanCursor {
+        ScanCursor::new(self.clone(), ScanKind::Hash, Some(key), pattern, &options)
+    }
+
+    /// Iterate over the members of a set
+    pub fn sscan(&mut self, key: &str, pattern: &str, options: Map) -> ScanCursor {
+        ScanCursor::new(self.clone(), ScanKind::Set, Some(key), pattern, &options)
+    }
+
+    /// Iterate over the members of a sorted set as `#{member, score}`
+    pub fn zscan(&mut self, key: &str, pattern: &str, options: Map) -> ScanCursor {
+        ScanCursor::new(
+            self.clone(),
+            ScanKind::SortedSet,
+            Some(key),
+            pattern,
+            &options,
+        )
+    }
+}
+
+/// Register SCAN-family methods and the cursor iterator with the Rhai engine
+pub fn register_scan_methods(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<ScanCursor>("ScanCursor")
+        .register_iterator::<ScanCursor>()
+        .register_fn("is_finished", |cursor: &mut ScanCursor| {
+            cursor.is_finished()
+        })
+        .register_fn("scan", |client: &mut RedisClient| {
+            client.scan("*", Map::new())
+        })
+        .register_fn("scan", |client: &mut RedisClient, pattern: &str| {
+            client.scan(pattern, Map::new())
+        })
+        .register_fn("scan", RedisClient::scan)
+        .register_fn("hscan", |client: &mut RedisClient, key: &str| {
+            client.hscan(key, "*", Map::new())
+        })
+        .register_fn(
+            "hscan",
+            |client: &mut RedisClient, key: &str, pattern: &str| {
+                client.hscan(key, pattern, Map::new())
+            },
+        )
+        .register_fn("hscan", RedisClient::hscan)
+        .register_fn("sscan", |client: &mut RedisClient, key: &str| {
+            client.sscan(key, "*", Map::new())
+        })
+        .register_fn(
+            "sscan",
+            |client: &mut RedisClient, key: &str, pattern: &str| {
+                client.sscan(key, pattern, Map::new())
+            },
+        )
+        .register_fn("sscan", RedisClient::sscan)
+        .register_fn("zscan", |client: &mut RedisClient, key: &str| {
+            client.zscan(key, "*", Map::new())
+        })
+        .register_fn(
+            "zscan",
+            |client: &mut RedisClient, key: &str, pattern: &str| {
+                client.zscan(key, pattern, Map::new())
+            },
+        )
+        .register_fn("zscan", RedisClient::zscan);
+}
@@ -0,0 +1,119 @@
+//! Named script storage, analogous to SCRIPT LOAD / EVALSHA
+//!
+//! Script sources are stored in a Redis hash keyed by their SHA1 digest, so
+//! any application instance sharing the Redis server can run a script that
+//! was loaded by another. Compiled ASTs are cached locally per engine.
+//!
+//! Scripts run with the usual `redis` object plus `KEYS` and `ARGV` arrays,
+//! mirroring the Lua calling convention.
+//!
+//! # Example
+//! ```no_run
+//! use rhai_redis::{RedisClient, RedisEngine};
+//!
+//! let client = redis::Client::open("redis://localhost").unwrap();
+//! let mut engine = RedisEngine::new();
+//! engine.set_redis_client(RedisClient::new(client.get_connection().unwrap()));
+//!
+//! let sha = engine.script_load(r#"redis.incrby(KEYS[0], parse_int(ARGV[0]))"#).unwrap();
+//! let total = engine.evalsha(&sha, &["counter"], &["5"]).unwrap();
+//! ```
+
+use crate::{Error, RedisEngine, Result};
+use redis::Commands;
+use rhai::{Array, Dynamic, Scope, AST};
+
+/// SHA1 digest of a script source, as used for script names
+pub fn script_sha(source: &str) -> String {
+    redis::Script::new(source).get_hash().to_string()
+}
+
+fn to_array(values: &[&str]) -> Array {
+    values
+        .iter()
+        .map(|v| Dynamic::from(v.to_string()))
+        .collect()
+}
+
+impl RedisEngine {
+    /// Set the hash used to store script sources (defaults to `rhai:scripts`)
+    pub fn set_script_key(&mut self, key: &str) {
+        self.script_key = key.to_string();
+    }
+
+    /// Compile a script, store its source in Redis and return its SHA1
+    pub fn script_load(&mut self, source: &str) -> Result<String> {
+        let ast = self.engine.compile(source)?;
+        let sha = script_sha(source);
+
+        let client = self.redis_client()?;
+        let mut conn = client.conn.lock().unwrap();
+        conn.hset::<_, _, _, ()>(&self.script_key, &sha, source)?;
+        drop(conn);
+
+        self.scripts.insert(sha.clone(), ast);
+        Ok(sha)
+    }
+
+    /// Check which of the given SHA1s have a stored script
+    pub fn script_exists(&mut self, shas: &[&str]) -> Result<Vec<bool>> {
+        let client = self.redis_client()?;
+        let mut conn = client.conn.lock().unwrap();
+        let mut exists = Vec::with_capacity(shas.len());
+        for sha in shas {
+            exists.push(conn.hexists::<_, _, bool>(&self.script_key, sha.to_lowercase())?);
+        }
+        Ok(exists)
+    }
+
+    /// Remove all stored scripts and clear the local compiled cache
+    pub fn script_flush(&mut self) -> Result<()> {
+        let client = self.redis_client()?;
+        let mut conn = client.conn.lock().unwrap();
+        conn.del::<_, ()>(&self.script_key)?;
+        drop(conn);
+
+        self.scripts.clear();
+        Ok(())
+    }
+
+    /// Run a stored script by SHA1 with `KEYS` and `ARGV` in scope.
+    ///
+    /// Scripts compiled by this engine are reused without contacting Redis,
+    /// so a flush from another instance only affects scripts this engine has
+    /// not run yet.
+    pub fn evalsha(&mut self, sha: &str, keys: &[&str], args: &[&str]) -> Result<Dynamic> {
+        let sha = sha.to_lowercase();
+        if !self.scripts.contains_key(&sha) {
+            let ast = self.fetch_script(&sha)?;
+            self.scripts.insert(sha.clone(), ast);
+        }
+
+        let client = self.start_run()?;
+        let mut scope = Scope::new();
+        scope.push("redis", client.clone());
+        scope.push("KEYS", to_array(keys));
+        scope.push("ARGV", to_array(args));
+
+        let ast = &self.scripts[&sha];
+        let result = self.engine.eval_ast_with_scope::<Dynamic>(&mut scope, ast);
+        self.finish_run(result)
+    }
+
+    /// Load a script source from Redis and compile it
+    fn fetch_script(&self, sha: &str) -> Result<AST> {
+        let client = self.redis_client()?;
+        let mut conn = client.conn.lock().unwrap();
+        let source: Option<String> = conn.hget(&self.script_key, sha)?;
+        drop(conn);
+
+        let source = source.ok_or_else(|| Error::NoScript(sha.to_string()))?;
+        if script_sha(&source) != sha {
+            return Err(Error::Script(format!(
+                "Stored script does not match its SHA1: {}",
+                sha
+            )));
+        }
+        Ok(self.engine.compile(&source)?)
+    }
+}
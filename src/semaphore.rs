@@ -0,0 +1,337 @@
+//! Counting semaphores and countdown latches for Redis Rhai integration
+//!
+//! `redis.semaphore(name, permits, ttl_ms)` caps how many workers hold a
+//! permit at once. Holders are kept in a sorted set scored by when they last
+//! acquired or refreshed their permit, and holders older than `ttl_ms` are
+//! treated as crashed and dropped. Each handle holds at most one permit.
+//!
+//! `redis.latch(name, count, ttl_ms)` opens once `count_down` has been called
+//! `count` times, waking every worker blocked in `wait`. (`await` is a
+//! reserved word in Rhai.) The counter is kept in `{name}:count` and waiters
+//! block with BLPOP on `{name}:gate`. Both keys expire `ttl_ms` after the
+//! latch is created (a day by default), after which the name can be reused;
+//! an expired latch counts as open.
+//!
+//! As with blocking list commands, timeouts are in milliseconds and 0 waits
+//! until the client is shut down.
+//!
+//! # Example
+//! ```rhai
+//! let api = redis.semaphore("sem:geocoder", 5, 30000);
+//! if api.acquire(2000) {
+//!     try { call_geocoder(); } catch (err) { print(err); }
+//!     api.release();
+//! }
+//!
+//! let ready = redis.latch("latch:import", 3);
+//! // each of three loaders calls: redis.latch("latch:import", 3).count_down();
+//! if ready.wait(60000) { print("all loaders finished"); }
+//! ```
+
+use crate::client::{next_wait, RedisClient};
+use crate::lock::{random_token, wait_for_retry};
+use rhai::Engine;
+use std::time::{Duration, Instant};
+
+const ACQUIRE_SCRIPT: &str = r#"
+local time = redis.call("TIME")
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local ttl = tonumber(ARGV[3])
+redis.call("ZREMRANGEBYSCORE", KEYS[1], "-inf", now - ttl)
+if redis.call("ZSCORE", KEYS[1], ARGV[1]) or redis.call("ZCARD", KEYS[1]) < tonumber(ARGV[2]) then
+    redis.call("ZADD", KEYS[1], now, ARGV[1])
+    redis.call("PEXPIRE", KEYS[1], ttl)
+    return 1
+end
+return 0
+"#;
+
+const REFRESH_SCRIPT: &str = r#"
+local time = redis.call("TIME")
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local ttl = tonumber(ARGV[2])
+local score = redis.call("ZSCORE", KEYS[1], ARGV[1])
+if not score or tonumber(score) <= now - ttl then
+    return 0
+end
+redis.call("ZADD", KEYS[1], now, ARGV[1])
+redis.call("PEXPIRE", KEYS[1], ttl)
+return 1
+"#;
+
+/// Lifetime of a latch created without an explicit TTL
+pub const DEFAULT_LATCH_TTL_MS: i64 = 86_400_000;
+
+const COUNT_DOWN_SCRIPT: &str = r#"
+if redis.call("EXISTS", KEYS[1]) == 0 then
+    return 0
+end
+local remaining = redis.call("DECR", KEYS[1])
+if remaining == 0 then
+    redis.call("RPUSH", KEYS[2], "open")
+    local ttl = redis.call("PTTL", KEYS[1])
+    if ttl > 0 then redis.call("PEXPIRE", KEYS[2], ttl) end
+end
+return math.max(remaining, 0)
+"#;
+
+/// Push the open signal back for other waiters, expiring with the counter
+const REOPEN_SCRIPT: &str = r#"
+local ttl = redis.call("PTTL", KEYS[1])
+if ttl == -2 then
+    return 0
+end
+redis.call("RPUSH", KEYS[2], ARGV[1])
+if ttl > 0 then redis.call("PEXPIRE", KEYS[2], ttl) end
+return 1
+"#;
+
+/// Deadline for a blocking wait, where 0 means no deadline
+fn deadline_from_ms(timeout_ms: i64) -> Option<Instant> {
+    (timeout_ms > 0).then(|| Instant::now() + Duration::from_millis(timeout_ms as u64))
+}
+
+fn run_script(client: &RedisClient, source: &str, keys: &[&str], args: &[String]) -> i64 {
+    let script = redis::Script::new(source);
+    let mut invocation = script.prepare_invoke();
+    for key in keys {
+        invocation.key(*key);
+    }
+    for arg in args {
+        invocation.arg(arg);
+    }
+    let mut conn = client.conn.lock().unwrap();
+    invocation.invoke(&mut *conn).unwrap_or_else(|e| {
+        eprintln!("Redis error: {}", e);
+        0
+    })
+}
+
+/// A handle that can hold one permit of a counting semaphore
+#[derive(Clone)]
+pub struct Semaphore {
+    client: RedisClient,
+    name: String,
+    permits: i64,
+    ttl_ms: i64,
+    token: String,
+    held: bool,
+}
+
+impl Semaphore {
+    pub fn new(client: RedisClient, name: &str, permits: i64, ttl_ms: i64) -> Self {
+        Self {
+            client,
+            name: name.to_string(),
+            permits: permits.max(1),
+            ttl_ms: ttl_ms.max(1),
+            token: random_token(),
+            held: false,
+        }
+    }
+
+    /// Try once to take a permit
+    pub fn try_acquire(&mut self) -> bool {
+        let args = [
+            self.token.clone(),
+            self.permits.to_string(),
+            self.ttl_ms.to_string(),
+        ];
+        self.held = run_script(&self.client, ACQUIRE_SCRIPT, &[&self.name], &args) == 1;
+        self.held
+    }
+
+    /// Wait up to `timeout_ms` for a permit, returning whether one was taken
+    pub fn acquire(&mut self, timeout_ms: i64) -> bool {
+        let deadline = deadline_from_ms(timeout_ms);
+        loop {
+            if self.try_acquire() {
+                return true;
+            }
+            if self.client.is_stopped() || !wait_for_retry(deadline) {
+                return false;
+            }
+        }
+    }
+
+    /// Give the permit back, returning whether it was still held
+    pub fn release(&mut self) -> bool {
+        self.held = false;
+        let mut conn = self.client.conn.lock().unwrap();
+        redis::cmd("ZREM")
+            .arg(&self.name)
+            .arg(&self.token)
+            .query::<i64>(&mut *conn)
+            .map(|removed| removed > 0)
+            .unwrap_or(false)
+    }
+
+    /// Restart the permit's `ttl_ms`, returning `false` if it had already expired
+    pub fn refresh(&mut self) -> bool {
+        let args = [self.token.clone(), self.ttl_ms.to_string()];
+        self.held = run_script(&self.client, REFRESH_SCRIPT, &[&self.name], &args) == 1;
+        self.held
+    }
+
+    /// Whether this handle believes it holds a permit
+    pub fn is_held(&self) -> bool {
+        self.held
+    }
+
+    /// Number of permits not held by live holders
+    pub fn available(&mut self) -> i64 {
+        let mut conn = self.client.conn.lock().unwrap();
+        let now: (i64, i64) = match redis::cmd("TIME").query(&mut *conn) {
+            Ok(time) => time,
+            Err(e) => {
+                eprintln!("Redis error: {}", e);
+                return 0;
+            }
+        };
+        let cutoff = now.0 * 1000 + now.1 / 1000 - self.ttl_ms;
+        let holders: i64 = redis::cmd("ZCOUNT")
+            .arg(&self.name)
+            .arg(format!("({}", cutoff))
+            .arg("+inf")
+            .query(&mut *conn)
+            .unwrap_or(0);
+        (self.permits - holders).max(0)
+    }
+}
+
+/// A countdown latch that opens once counted down to zero
+#[derive(Clone)]
+pub struct Latch {
+    client: RedisClient,
+    count_key: String,
+    gate_key: String,
+}
+
+impl Latch {
+    /// Open the latch `name`, creating it with `count` if it does not exist.
+    ///
+    /// A new latch expires after `ttl_ms`; an existing one keeps its expiry.
+    pub fn new(client: RedisClient, name: &str, count: i64, ttl_ms: i64) -> Self {
+        let latch = Self {
+            client,
+            count_key: format!("{}:count", name),
+            gate_key: format!("{}:gate", name),
+        };
+        let mut conn = latch.client.conn.lock().unwrap();
+        if let Err(e) = redis::cmd("SET")
+            .arg(&latch.count_key)
+            .arg(count.max(1))
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_ms.max(1))
+            .query::<Option<String>>(&mut *conn)
+        {
+            eprintln!("Redis error: {}", e);
+        }
+        drop(conn);
+        latch
+    }
+
+    /// Count down by one, returning how many counts remain.
+    ///
+    /// Does nothing if the latch has expired.
+    pub fn count_down(&mut self) -> i64 {
+        run_script(
+            &self.client,
+            COUNT_DOWN_SCRIPT,
+            &[&self.count_key, &self.gate_key],
+            &[],
+        )
+    }
+
+    /// How many counts remain before the latch opens
+    pub fn count(&mut self) -> i64 {
+        let mut conn = self.client.conn.lock().unwrap();
+        redis::cmd("GET")
+            .arg(&self.count_key)
+            .query::<Option<i64>>(&mut *conn)
+            .ok()
+            .flatten()
+            .map_or(0, |count| count.max(0))
+    }
+
+    /// Wait up to `timeout_ms` for the latch to open, returning whether it did
+    pub fn wait(&mut self, timeout_ms: i64) -> bool {
+        if self.count() == 0 {
+            return true;
+        }
+        let deadline = deadline_from_ms(timeout_ms);
+        while !self.client.is_stopped() {
+            let Some(slice) = next_wait(deadline, &self.client.run) else {
+                return false;
+            };
+            let reply = {
+                let mut conn = self.client.conn.lock().unwrap();
+                redis::cmd("BLPOP")
+                    .arg(&self.gate_key)
+                    .arg(slice.as_secs_f64().max(0.001))
+                    .query::<Option<(String, String)>>(&mut *conn)
+            };
+            match reply {
+                Ok(Some((_, signal))) => {
+                    // Put the signal back so every other waiter wakes too
+                    run_script(
+                        &self.client,
+                        REOPEN_SCRIPT,
+                        &[&self.count_key, &self.gate_key],
+                        &[signal],
+                    );
+                    return true;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("Redis error: {}", e);
+                    return false;
+                }
+            }
+            // Also open if the count reached zero without a signal, e.g. after a restore
+            if self.count() == 0 {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl RedisClient {
+    /// A semaphore handle for `permits` concurrent holders, who lose their
+    /// permit after `ttl_ms` unless they refresh it
+    pub fn semaphore(&mut self, name: &str, permits: i64, ttl_ms: i64) -> Semaphore {
+        Semaphore::new(self.clone(), name, permits, ttl_ms)
+    }
+
+    /// The countdown latch `name`, created with `count` and a lifetime of
+    /// `ttl_ms` if it does not exist
+    pub fn latch(&mut self, name: &str, count: i64, ttl_ms: i64) -> Latch {
+        Latch::new(self.clone(), name, count, ttl_ms)
+    }
+}
+
+/// Register semaphore and latch types and their methods with the Rhai engine
+pub fn register_semaphore_methods(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<Semaphore>("Semaphore")
+        .register_type_with_name::<Latch>("Latch")
+        .register_fn("semaphore", RedisClient::semaphore)
+        .register_fn("acquire", Semaphore::acquire)
+        .register_fn("try_acquire", Semaphore::try_acquire)
+        .register_fn("release", Semaphore::release)
+        .register_fn("refresh", Semaphore::refresh)
+        .register_fn("is_held", |semaphore: &mut Semaphore| semaphore.is_held())
+        .register_fn("available", Semaphore::available)
+        .register_fn(
+            "latch",
+            |client: &mut RedisClient, name: &str, count: i64| {
+                client.latch(name, count, DEFAULT_LATCH_TTL_MS)
+            },
+        )
+        .register_fn("latch", RedisClient::latch)
+        .register_fn("count_down", Latch::count_down)
+        .register_fn("count", Latch::count)
+        .register_fn("wait", Latch::wait);
+}
@@ -1,8 +1,24 @@
 //! Set operations for Redis Rhai integration
+//!
+//! Set algebra runs on the server, so scripts can combine large sets without
+//! pulling them into Rhai:
+//!
+//! ```rhai
+//! let shared = redis.sinter(["user:1:tags", "user:2:tags"]);
+//! let overlap = redis.sintercard(["user:1:perms", "role:admin"], 1);
+//! ```
 
 use crate::client::RedisClient;
 use redis::Commands;
-use rhai::{Dynamic, Engine};
+use rhai::{Array, Dynamic, Engine};
+
+fn to_strings(values: &Array) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+fn to_array(values: Vec<String>) -> Array {
+    values.into_iter().map(Dynamic::from).collect()
+}
 
 impl RedisClient {
     pub fn sadd(&mut self, key: &str, member: &str) -> i64 {
@@ -32,6 +48,157 @@ impl RedisClient {
         let mut conn = self.conn.lock().unwrap();
         conn.scard::<_, i64>(key).unwrap_or(0)
     }
+
+    /// Add several members, returning how many were new
+    pub fn sadd_many(&mut self, key: &str, members: Array) -> i64 {
+        if members.is_empty() {
+            return 0;
+        }
+        let mut conn = self.conn.lock().unwrap();
+        conn.sadd::<_, _, i64>(key, to_strings(&members))
+            .unwrap_or(0)
+    }
+
+    /// Remove several members, returning how many were removed
+    pub fn srem_many(&mut self, key: &str, members: Array) -> i64 {
+        if members.is_empty() {
+            return 0;
+        }
+        let mut conn = self.conn.lock().unwrap();
+        conn.srem::<_, _, i64>(key, to_strings(&members))
+            .unwrap_or(0)
+    }
+
+    /// Check membership of several members at once
+    pub fn smismember(&mut self, key: &str, members: Array) -> Array {
+        if members.is_empty() {
+            return Array::new();
+        }
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("SMISMEMBER")
+            .arg(key)
+            .arg(to_strings(&members))
+            .query::<Vec<bool>>(&mut *conn)
+            .unwrap_or_else(|_| vec![false; members.len()])
+            .into_iter()
+            .map(Dynamic::from)
+            .collect()
+    }
+
+    fn set_algebra(&self, command: &str, keys: &Array) -> Array {
+        if keys.is_empty() {
+            return Array::new();
+        }
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd(command)
+            .arg(to_strings(keys))
+            .query::<Vec<String>>(&mut *conn)
+            .map(to_array)
+            .unwrap_or_default()
+    }
+
+    fn set_algebra_store(&self, command: &str, destination: &str, keys: &Array) -> i64 {
+        if keys.is_empty() {
+            return 0;
+        }
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd(command)
+            .arg(destination)
+            .arg(to_strings(keys))
+            .query::<i64>(&mut *conn)
+            .unwrap_or(0)
+    }
+
+    /// Members present in all of the given sets
+    pub fn sinter(&mut self, keys: Array) -> Array {
+        self.set_algebra("SINTER", &keys)
+    }
+
+    /// Members present in any of the given sets
+    pub fn sunion(&mut self, keys: Array) -> Array {
+        self.set_algebra("SUNION", &keys)
+    }
+
+    /// Members of the first set that are in none of the others
+    pub fn sdiff(&mut self, keys: Array) -> Array {
+        self.set_algebra("SDIFF", &keys)
+    }
+
+    /// Store the intersection in `destination`, returning its size
+    pub fn sinterstore(&mut self, destination: &str, keys: Array) -> i64 {
+        self.set_algebra_store("SINTERSTORE", destination, &keys)
+    }
+
+    /// Store the union in `destination`, returning its size
+    pub fn sunionstore(&mut self, destination: &str, keys: Array) -> i64 {
+        self.set_algebra_store("SUNIONSTORE", destination, &keys)
+    }
+
+    /// Store the difference in `destination`, returning its size
+    pub fn sdiffstore(&mut self, destination: &str, keys: Array) -> i64 {
+        self.set_algebra_store("SDIFFSTORE", destination, &keys)
+    }
+
+    /// Size of the intersection, counting at most `limit` members (0 for no limit)
+    pub fn sintercard(&mut self, keys: Array, limit: i64) -> i64 {
+        if keys.is_empty() {
+            return 0;
+        }
+        let mut cmd = redis::cmd("SINTERCARD");
+        cmd.arg(keys.len()).arg(to_strings(&keys));
+        if limit > 0 {
+            cmd.arg("LIMIT").arg(limit);
+        }
+        let mut conn = self.conn.lock().unwrap();
+        cmd.query::<i64>(&mut *conn).unwrap_or(0)
+    }
+
+    /// Remove and return a random member, or `()` if the set is empty
+    pub fn spop(&mut self, key: &str) -> Dynamic {
+        let mut conn = self.conn.lock().unwrap();
+        match conn.spop::<_, Option<String>>(key) {
+            Ok(Some(member)) => member.into(),
+            _ => Dynamic::UNIT,
+        }
+    }
+
+    /// Remove and return up to `count` random members
+    pub fn spop_count(&mut self, key: &str, count: i64) -> Array {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("SPOP")
+            .arg(key)
+            .arg(count.max(0))
+            .query::<Vec<String>>(&mut *conn)
+            .map(to_array)
+            .unwrap_or_default()
+    }
+
+    /// Return a random member without removing it, or `()` if the set is empty
+    pub fn srandmember(&mut self, key: &str) -> Dynamic {
+        let mut conn = self.conn.lock().unwrap();
+        match conn.srandmember::<_, Option<String>>(key) {
+            Ok(Some(member)) => member.into(),
+            _ => Dynamic::UNIT,
+        }
+    }
+
+    /// Return up to `count` random members (a negative count allows repeats)
+    pub fn srandmember_count(&mut self, key: &str, count: i64) -> Array {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("SRANDMEMBER")
+            .arg(key)
+            .arg(count)
+            .query::<Vec<String>>(&mut *conn)
+            .map(to_array)
+            .unwrap_or_default()
+    }
+
+    /// Move a member from one set to another, returning whether it was moved
+    pub fn smove(&mut self, source: &str, destination: &str, member: &str) -> bool {
+        let mut conn = self.conn.lock().unwrap();
+        conn.smove::<_, _, _, bool>(source, destination, member)
+            .unwrap_or(false)
+    }
 }
 
 pub fn register_set_methods(engine: &mut Engine) {
@@ -40,5 +207,23 @@ pub fn register_set_methods(engine: &mut Engine) {
         .register_fn("srem", RedisClient::srem)
         .register_fn("sismember", RedisClient::sismember)
         .register_fn("smembers", RedisClient::smembers)
-        .register_fn("scard", RedisClient::scard);
+        .register_fn("scard", RedisClient::scard)
+        .register_fn("sadd", RedisClient::sadd_many)
+        .register_fn("srem", RedisClient::srem_many)
+        .register_fn("smismember", RedisClient::smismember)
+        .register_fn("sinter", RedisClient::sinter)
+        .register_fn("sunion", RedisClient::sunion)
+        .register_fn("sdiff", RedisClient::sdiff)
+        .register_fn("sinterstore", RedisClient::sinterstore)
+        .register_fn("sunionstore", RedisClient::sunionstore)
+        .register_fn("sdiffstore", RedisClient::sdiffstore)
+        .register_fn("sintercard", |client: &mut RedisClient, keys: Array| {
+            client.sintercard(keys, 0)
+        })
+        .register_fn("sintercard", RedisClient::sintercard)
+        .register_fn("spop", RedisClient::spop)
+        .register_fn("spop", RedisClient::spop_count)
+        .register_fn("srandmember", RedisClient::srandmember)
+        .register_fn("srandmember", RedisClient::srandmember_count)
+        .register_fn("smove", RedisClient::smove);
 }
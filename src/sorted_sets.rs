@@ -1,8 +1,139 @@
 //! Sorted set operations for Redis Rhai integration
+//!
+//! Commands that return scores yield `[#{member, score}]`. Score and lex
+//! bounds accept anything Redis does: numbers, `"-inf"`/`"+inf"`, exclusive
+//! bounds such as `"(10"`, and lex bounds such as `"[a"` or `"-"`.
+//!
+//! ```rhai
+//! redis.zadd("scores", #{ada: 12, bob: 7});
+//! let top = redis.zrange("scores", 0, 2, #{rev: true, withscores: true});
+//! let range = redis.zrange("scores", 5, "+inf", #{byscore: true, limit: [0, 10]});
+//! ```
 
 use crate::client::RedisClient;
-use redis::Commands;
-use rhai::{Dynamic, Engine};
+use crate::generic::redis_value_to_dynamic;
+use crate::lists::one_or_many;
+use crate::strings::option_flag;
+use redis::{Commands, Value};
+use rhai::{Array, Dynamic, Engine, Map};
+
+fn to_strings(values: &Array) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+fn score_of(value: &Value) -> Option<f64> {
+    redis::from_redis_value(value).ok()
+}
+
+fn entry(member: Dynamic, score: f64) -> Dynamic {
+    let mut map = Map::new();
+    map.insert("member".into(), member);
+    map.insert("score".into(), score.into());
+    map.into()
+}
+
+/// Convert a WITHSCORES reply into `[#{member, score}]`.
+///
+/// RESP2 returns a flat `[member, score, ...]` array, RESP3 `[[member, score], ...]`.
+fn scored_entries(value: Value) -> Array {
+    let Value::Array(items) = value else {
+        return Array::new();
+    };
+    let flat: Vec<Value> = items
+        .into_iter()
+        .flat_map(|item| match item {
+            Value::Array(pair) => pair,
+            item => vec![item],
+        })
+        .collect();
+    flat.chunks(2)
+        .filter_map(|pair| {
+            let [member, score] = pair else { return None };
+            Some(entry(
+                redis_value_to_dynamic(member.clone()),
+                score_of(score)?,
+            ))
+        })
+        .collect()
+}
+
+/// Convert a `[key, member, score]` reply from BZPOPMIN/BZPOPMAX into `#{key, member, score}`
+fn bzpop_reply(value: Value) -> Dynamic {
+    let Value::Array(items) = value else {
+        return Dynamic::UNIT;
+    };
+    let [key, member, score] = items.as_slice() else {
+        return Dynamic::UNIT;
+    };
+    let Some(score) = score_of(score) else {
+        return Dynamic::UNIT;
+    };
+    let mut map = Map::new();
+    map.insert("key".into(), redis_value_to_dynamic(key.clone()));
+    map.insert("member".into(), redis_value_to_dynamic(member.clone()));
+    map.insert("score".into(), score.into());
+    map.into()
+}
+
+/// Convert a `[key, [[member, score], ...]]` reply from ZMPOP/BZMPOP into `#{key, entries}`
+fn mpop_reply(value: Value) -> Dynamic {
+    let Value::Array(mut items) = value else {
+        return Dynamic::UNIT;
+    };
+    if items.len() != 2 {
+        return Dynamic::UNIT;
+    }
+    let entries = scored_entries(items.pop().unwrap());
+    let mut map = Map::new();
+    map.insert("key".into(), redis_value_to_dynamic(items.pop().unwrap()));
+    map.insert("entries".into(), entries.into());
+    map.into()
+}
+
+/// Append `ZADD` flags from an options map
+fn zadd_flags(cmd: &mut redis::Cmd, options: &Map) {
+    for name in ["nx", "xx", "gt", "lt", "ch", "incr"] {
+        if option_flag(options, name) {
+            cmd.arg(name.to_uppercase());
+        }
+    }
+}
+
+/// Append `BYSCORE`, `BYLEX`, `REV` and `LIMIT offset count` from an options map
+fn range_flags(cmd: &mut redis::Cmd, options: &Map) {
+    for name in ["byscore", "bylex", "rev"] {
+        if option_flag(options, name) {
+            cmd.arg(name.to_uppercase());
+        }
+    }
+    if let Some(limit) = options
+        .get("limit")
+        .and_then(|l| l.read_lock::<Array>().map(|l| l.clone()))
+    {
+        if let [offset, count] = limit.as_slice() {
+            cmd.arg("LIMIT")
+                .arg(offset.as_int().unwrap_or(0))
+                .arg(count.as_int().unwrap_or(-1));
+        }
+    }
+}
+
+/// Append `WEIGHTS` and `AGGREGATE` for ZUNION/ZINTER from an options map
+fn combine_flags(cmd: &mut redis::Cmd, options: &Map) {
+    if let Some(weights) = options
+        .get("weights")
+        .and_then(|w| w.read_lock::<Array>().map(|w| w.clone()))
+    {
+        cmd.arg("WEIGHTS");
+        for weight in weights {
+            cmd.arg(weight.to_string());
+        }
+    }
+    if let Some(aggregate) = options.get("aggregate") {
+        cmd.arg("AGGREGATE")
+            .arg(aggregate.to_string().to_uppercase());
+    }
+}
 
 impl RedisClient {
     pub fn zadd(&mut self, key: &str, score: f64, member: &str) -> i64 {
@@ -36,6 +167,314 @@ impl RedisClient {
             Err(_) => vec![],
         }
     }
+
+    fn query_zset(&self, cmd: &redis::Cmd) -> Value {
+        let mut conn = self.conn.lock().unwrap();
+        cmd.query::<Value>(&mut *conn).unwrap_or_else(|e| {
+            eprintln!("Redis error: {}", e);
+            Value::Nil
+        })
+    }
+
+    fn query_count(&self, cmd: &redis::Cmd) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        cmd.query::<i64>(&mut *conn).unwrap_or_else(|e| {
+            eprintln!("Redis error: {}", e);
+            0
+        })
+    }
+
+    /// Add one member with flags `nx`, `xx`, `gt`, `lt`, `ch` and `incr`.
+    ///
+    /// Returns the new score (or `()` if a condition failed) with `incr`, and
+    /// the number of added (or with `ch`, changed) members otherwise.
+    pub fn zadd_with(&mut self, key: &str, score: f64, member: &str, options: Map) -> Dynamic {
+        let mut members = Map::new();
+        members.insert(member.into(), score.into());
+        self.zadd_map_with(key, members, options)
+    }
+
+    /// Add members from a `#{member: score}` map, returning how many were added
+    pub fn zadd_map(&mut self, key: &str, members: Map) -> i64 {
+        self.zadd_map_with(key, members, Map::new())
+            .as_int()
+            .unwrap_or(0)
+    }
+
+    /// Add members from a `#{member: score}` map with ZADD flags
+    pub fn zadd_map_with(&mut self, key: &str, members: Map, options: Map) -> Dynamic {
+        if members.is_empty() {
+            return Dynamic::from(0_i64);
+        }
+        let mut cmd = redis::cmd("ZADD");
+        cmd.arg(key);
+        zadd_flags(&mut cmd, &options);
+        for (member, score) in &members {
+            cmd.arg(score.to_string()).arg(member.as_str());
+        }
+        match self.query_zset(&cmd) {
+            Value::Int(count) => count.into(),
+            Value::Nil => Dynamic::UNIT,
+            value => score_of(&value).map(Dynamic::from).unwrap_or(Dynamic::UNIT),
+        }
+    }
+
+    /// Increment a member's score, returning the new score
+    pub fn zincrby(&mut self, key: &str, increment: f64, member: &str) -> f64 {
+        let mut conn = self.conn.lock().unwrap();
+        conn.zincr::<_, _, _, f64>(key, member, increment)
+            .unwrap_or(0.0)
+    }
+
+    fn rank(&self, command: &str, key: &str, member: &str, with_score: bool) -> Dynamic {
+        let mut cmd = redis::cmd(command);
+        cmd.arg(key).arg(member);
+        if with_score {
+            cmd.arg("WITHSCORE");
+        }
+        match self.query_zset(&cmd) {
+            Value::Int(rank) => rank.into(),
+            Value::Array(items) => match items.as_slice() {
+                [Value::Int(rank), score] => {
+                    let mut map = Map::new();
+                    map.insert("rank".into(), (*rank).into());
+                    map.insert("score".into(), score_of(score).unwrap_or(0.0).into());
+                    map.into()
+                }
+                _ => Dynamic::UNIT,
+            },
+            _ => Dynamic::UNIT,
+        }
+    }
+
+    /// Get a member's rank (lowest score first), or `()` if it is missing.
+    ///
+    /// With `with_score`, returns `#{rank, score}`.
+    pub fn zrank(&mut self, key: &str, member: &str, with_score: bool) -> Dynamic {
+        self.rank("ZRANK", key, member, with_score)
+    }
+
+    /// Get a member's rank (highest score first), or `()` if it is missing
+    pub fn zrevrank(&mut self, key: &str, member: &str, with_score: bool) -> Dynamic {
+        self.rank("ZREVRANK", key, member, with_score)
+    }
+
+    /// Count members with scores between `min` and `max`
+    pub fn zcount(&mut self, key: &str, min: Dynamic, max: Dynamic) -> i64 {
+        self.query_count(
+            redis::cmd("ZCOUNT")
+                .arg(key)
+                .arg(min.to_string())
+                .arg(max.to_string()),
+        )
+    }
+
+    /// Count members between two lex bounds
+    pub fn zlexcount(&mut self, key: &str, min: &str, max: &str) -> i64 {
+        self.query_count(redis::cmd("ZLEXCOUNT").arg(key).arg(min).arg(max))
+    }
+
+    /// Get a range of members with options `byscore`, `bylex`, `rev`,
+    /// `limit: [offset, count]` and `withscores`.
+    ///
+    /// Returns member names, or `[#{member, score}]` with `withscores`.
+    pub fn zrange_with(&mut self, key: &str, start: Dynamic, stop: Dynamic, options: Map) -> Array {
+        let mut cmd = redis::cmd("ZRANGE");
+        cmd.arg(key).arg(start.to_string()).arg(stop.to_string());
+        range_flags(&mut cmd, &options);
+        if option_flag(&options, "withscores") {
+            cmd.arg("WITHSCORES");
+            return scored_entries(self.query_zset(&cmd));
+        }
+        match self.query_zset(&cmd) {
+            Value::Array(items) => items.into_iter().map(redis_value_to_dynamic).collect(),
+            _ => Array::new(),
+        }
+    }
+
+    /// Store a range of `source` in `destination`, returning its size
+    pub fn zrangestore(
+        &mut self,
+        destination: &str,
+        source: &str,
+        start: Dynamic,
+        stop: Dynamic,
+        options: Map,
+    ) -> i64 {
+        let mut cmd = redis::cmd("ZRANGESTORE");
+        cmd.arg(destination)
+            .arg(source)
+            .arg(start.to_string())
+            .arg(stop.to_string());
+        range_flags(&mut cmd, &options);
+        self.query_count(&cmd)
+    }
+
+    /// Remove and return up to `count` members with the lowest scores
+    pub fn zpopmin(&mut self, key: &str, count: i64) -> Array {
+        scored_entries(self.query_zset(redis::cmd("ZPOPMIN").arg(key).arg(count.max(1))))
+    }
+
+    /// Remove and return up to `count` members with the highest scores
+    pub fn zpopmax(&mut self, key: &str, count: i64) -> Array {
+        scored_entries(self.query_zset(redis::cmd("ZPOPMAX").arg(key).arg(count.max(1))))
+    }
+
+    /// Pop the lowest-scored member of the first non-empty set, waiting up to
+    /// `timeout_ms`. Returns `#{key, member, score}` or `()` on timeout.
+    pub fn bzpopmin(&mut self, keys: Dynamic, timeout_ms: i64) -> Dynamic {
+        self.bzpop("BZPOPMIN", one_or_many(&keys), timeout_ms)
+    }
+
+    /// Pop the highest-scored member of the first non-empty set, waiting up to `timeout_ms`
+    pub fn bzpopmax(&mut self, keys: Dynamic, timeout_ms: i64) -> Dynamic {
+        self.bzpop("BZPOPMAX", one_or_many(&keys), timeout_ms)
+    }
+
+    fn bzpop(&self, command: &str, keys: Vec<String>, timeout_ms: i64) -> Dynamic {
+        if keys.is_empty() {
+            return Dynamic::UNIT;
+        }
+        bzpop_reply(self.block_on(timeout_ms, |timeout| {
+            let mut cmd = redis::cmd(command);
+            cmd.arg(&keys).arg(timeout);
+            cmd
+        }))
+    }
+
+    /// Pop up to `count` members from the first non-empty set, from the
+    /// `"MIN"` or `"MAX"` end. Returns `#{key, entries}` or `()`.
+    pub fn zmpop(&mut self, keys: Array, direction: &str, count: i64) -> Dynamic {
+        if keys.is_empty() {
+            return Dynamic::UNIT;
+        }
+        mpop_reply(
+            self.query_zset(
+                redis::cmd("ZMPOP")
+                    .arg(keys.len())
+                    .arg(to_strings(&keys))
+                    .arg(direction.to_uppercase())
+                    .arg("COUNT")
+                    .arg(count.max(1)),
+            ),
+        )
+    }
+
+    /// Blocking [`zmpop`](Self::zmpop), waiting up to `timeout_ms`
+    pub fn bzmpop(&mut self, keys: Array, direction: &str, count: i64, timeout_ms: i64) -> Dynamic {
+        if keys.is_empty() {
+            return Dynamic::UNIT;
+        }
+        let (keys, direction) = (to_strings(&keys), direction.to_uppercase());
+        mpop_reply(self.block_on(timeout_ms, |timeout| {
+            let mut cmd = redis::cmd("BZMPOP");
+            cmd.arg(timeout)
+                .arg(keys.len())
+                .arg(&keys)
+                .arg(&direction)
+                .arg("COUNT")
+                .arg(count.max(1));
+            cmd
+        }))
+    }
+
+    /// Remove members with scores between `min` and `max`
+    pub fn zremrangebyscore(&mut self, key: &str, min: Dynamic, max: Dynamic) -> i64 {
+        self.query_count(
+            redis::cmd("ZREMRANGEBYSCORE")
+                .arg(key)
+                .arg(min.to_string())
+                .arg(max.to_string()),
+        )
+    }
+
+    /// Remove members with ranks between `start` and `stop`
+    pub fn zremrangebyrank(&mut self, key: &str, start: i64, stop: i64) -> i64 {
+        self.query_count(redis::cmd("ZREMRANGEBYRANK").arg(key).arg(start).arg(stop))
+    }
+
+    /// Remove members between two lex bounds
+    pub fn zremrangebylex(&mut self, key: &str, min: &str, max: &str) -> i64 {
+        self.query_count(redis::cmd("ZREMRANGEBYLEX").arg(key).arg(min).arg(max))
+    }
+
+    fn combine(&self, command: &str, keys: &Array, options: &Map) -> Array {
+        if keys.is_empty() {
+            return Array::new();
+        }
+        let mut cmd = redis::cmd(command);
+        cmd.arg(keys.len()).arg(to_strings(keys));
+        if command != "ZDIFF" {
+            combine_flags(&mut cmd, options);
+        }
+        if option_flag(options, "withscores") {
+            cmd.arg("WITHSCORES");
+            return scored_entries(self.query_zset(&cmd));
+        }
+        match self.query_zset(&cmd) {
+            Value::Array(items) => items.into_iter().map(redis_value_to_dynamic).collect(),
+            _ => Array::new(),
+        }
+    }
+
+    fn combine_store(&self, command: &str, destination: &str, keys: &Array, options: &Map) -> i64 {
+        if keys.is_empty() {
+            return 0;
+        }
+        let mut cmd = redis::cmd(command);
+        cmd.arg(destination).arg(keys.len()).arg(to_strings(keys));
+        if command != "ZDIFFSTORE" {
+            combine_flags(&mut cmd, options);
+        }
+        self.query_count(&cmd)
+    }
+
+    /// Union of sorted sets with options `weights`, `aggregate` (`"sum"`,
+    /// `"min"` or `"max"`) and `withscores`
+    pub fn zunion(&mut self, keys: Array, options: Map) -> Array {
+        self.combine("ZUNION", &keys, &options)
+    }
+
+    /// Intersection of sorted sets with options `weights`, `aggregate` and `withscores`
+    pub fn zinter(&mut self, keys: Array, options: Map) -> Array {
+        self.combine("ZINTER", &keys, &options)
+    }
+
+    /// Members of the first sorted set that are in none of the others, with option `withscores`
+    pub fn zdiff(&mut self, keys: Array, options: Map) -> Array {
+        self.combine("ZDIFF", &keys, &options)
+    }
+
+    /// Store the union in `destination`, returning its size
+    pub fn zunionstore(&mut self, destination: &str, keys: Array, options: Map) -> i64 {
+        self.combine_store("ZUNIONSTORE", destination, &keys, &options)
+    }
+
+    /// Store the intersection in `destination`, returning its size
+    pub fn zinterstore(&mut self, destination: &str, keys: Array, options: Map) -> i64 {
+        self.combine_store("ZINTERSTORE", destination, &keys, &options)
+    }
+
+    /// Store the difference in `destination`, returning its size
+    pub fn zdiffstore(&mut self, destination: &str, keys: Array) -> i64 {
+        self.combine_store("ZDIFFSTORE", destination, &keys, &Map::new())
+    }
+
+    /// Get the scores of several members, with `()` for missing members
+    pub fn zmscore(&mut self, key: &str, members: Array) -> Array {
+        if members.is_empty() {
+            return Array::new();
+        }
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("ZMSCORE")
+            .arg(key)
+            .arg(to_strings(&members))
+            .query::<Vec<Option<f64>>>(&mut *conn)
+            .unwrap_or_else(|_| vec![None; members.len()])
+            .into_iter()
+            .map(|score| score.map(Dynamic::from).unwrap_or(Dynamic::UNIT))
+            .collect()
+    }
 }
 
 pub fn register_sorted_set_methods(engine: &mut Engine) {
@@ -44,5 +483,66 @@ pub fn register_sorted_set_methods(engine: &mut Engine) {
         .register_fn("zrem", RedisClient::zrem)
         .register_fn("zcard", RedisClient::zcard)
         .register_fn("zscore", RedisClient::zscore)
-        .register_fn("zrange", RedisClient::zrange);
+        .register_fn("zrange", RedisClient::zrange)
+        .register_fn("zadd", RedisClient::zadd_with)
+        .register_fn("zadd", RedisClient::zadd_map)
+        .register_fn("zadd", RedisClient::zadd_map_with)
+        .register_fn("zincrby", RedisClient::zincrby)
+        .register_fn(
+            "zrank",
+            |client: &mut RedisClient, key: &str, member: &str| client.zrank(key, member, false),
+        )
+        .register_fn("zrank", RedisClient::zrank)
+        .register_fn(
+            "zrevrank",
+            |client: &mut RedisClient, key: &str, member: &str| client.zrevrank(key, member, false),
+        )
+        .register_fn("zrevrank", RedisClient::zrevrank)
+        .register_fn("zcount", RedisClient::zcount)
+        .register_fn("zlexcount", RedisClient::zlexcount)
+        .register_fn("zrange", RedisClient::zrange_with)
+        .register_fn("zrangestore", RedisClient::zrangestore)
+        .register_fn("zpopmin", |client: &mut RedisClient, key: &str| {
+            client.zpopmin(key, 1)
+        })
+        .register_fn("zpopmin", RedisClient::zpopmin)
+        .register_fn("zpopmax", |client: &mut RedisClient, key: &str| {
+            client.zpopmax(key, 1)
+        })
+        .register_fn("zpopmax", RedisClient::zpopmax)
+        .register_fn("bzpopmin", RedisClient::bzpopmin)
+        .register_fn("bzpopmax", RedisClient::bzpopmax)
+        .register_fn("zmpop", RedisClient::zmpop)
+        .register_fn("bzmpop", RedisClient::bzmpop)
+        .register_fn("zremrangebyscore", RedisClient::zremrangebyscore)
+        .register_fn("zremrangebyrank", RedisClient::zremrangebyrank)
+        .register_fn("zremrangebylex", RedisClient::zremrangebylex)
+        .register_fn("zunion", |client: &mut RedisClient, keys: Array| {
+            client.zunion(keys, Map::new())
+        })
+        .register_fn("zunion", RedisClient::zunion)
+        .register_fn("zinter", |client: &mut RedisClient, keys: Array| {
+            client.zinter(keys, Map::new())
+        })
+        .register_fn("zinter", RedisClient::zinter)
+        .register_fn("zdiff", |client: &mut RedisClient, keys: Array| {
+            client.zdiff(keys, Map::new())
+        })
+        .register_fn("zdiff", RedisClient::zdiff)
+        .register_fn(
+            "zunionstore",
+            |client: &mut RedisClient, destination: &str, keys: Array| {
+                client.zunionstore(destination, keys, Map::new())
+            },
+        )
+        .register_fn("zunionstore", RedisClient::zunionstore)
+        .register_fn(
+            "zinterstore",
+            |client: &mut RedisClient, destination: &str, keys: Array| {
+                client.zinterstore(destination, keys, Map::new())
+            },
+        )
+        .register_fn("zinterstore", RedisClient::zinterstore)
+        .register_fn("zdiffstore", RedisClient::zdiffstore)
+        .register_fn("zmscore", RedisClient::zmscore);
 }
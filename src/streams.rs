@@ -1,7 +1,170 @@
 //! Stream operations for Redis Rhai integration
+//!
+//! Commands that return entries produce maps of the form
+//! `#{id: "1700000000000-0", fields: #{field: "value"}}`.
 
-use crate::client::RedisClient;
-use rhai::{Dynamic, Engine};
+use crate::client::{next_wait, CancelToken, RedisClient, ShutdownHandle};
+use crate::generic::{build_cmd, redis_value_to_dynamic};
+use crate::{Error, RedisEngine, Result};
+use redis::Value;
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+fn value_to_string(value: &Value) -> Option<String> {
+    redis::from_redis_value(value).ok()
+}
+
+fn value_to_int(value: &Value) -> i64 {
+    redis::from_redis_value(value).unwrap_or(0)
+}
+
+fn to_strings(values: Array) -> Vec<String> {
+    values.into_iter().map(|v| v.to_string()).collect()
+}
+
+/// Convert a flat `[field, value, ...]` reply into a map
+fn fields_to_map(value: &Value) -> Map {
+    let mut map = Map::new();
+    match value {
+        Value::Array(items) => {
+            for pair in items.chunks(2) {
+                if let [field, value] = pair {
+                    if let Some(field) = value_to_string(field) {
+                        map.insert(field.into(), redis_value_to_dynamic(value.clone()));
+                    }
+                }
+            }
+        }
+        Value::Map(items) => {
+            for (field, value) in items {
+                if let Some(field) = value_to_string(field) {
+                    map.insert(field.into(), redis_value_to_dynamic(value.clone()));
+                }
+            }
+        }
+        _ => {}
+    }
+    map
+}
+
+/// Convert an `[id, [field, value, ...]]` reply into an entry map
+pub(crate) fn parse_entry(value: &Value) -> Option<Map> {
+    let Value::Array(parts) = value else {
+        return None;
+    };
+    let id = value_to_string(parts.first()?)?;
+
+    let mut entry = Map::new();
+    entry.insert("id".into(), id.into());
+    entry.insert(
+        "fields".into(),
+        parts.get(1).map(fields_to_map).unwrap_or_default().into(),
+    );
+    Some(entry)
+}
+
+/// Convert an array of raw entries into entry maps, skipping anything malformed
+pub(crate) fn parse_entries(value: &Value) -> Array {
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .filter_map(parse_entry)
+            .map(Dynamic::from)
+            .collect(),
+        _ => Array::new(),
+    }
+}
+
+/// Convert an XREAD/XREADGROUP reply into `(stream, entries)` pairs
+pub(crate) fn parse_stream_reply(value: &Value) -> Vec<(String, Array)> {
+    let pairs: Vec<(&Value, &Value)> = match value {
+        Value::Array(streams) => streams
+            .iter()
+            .filter_map(|stream| match stream {
+                Value::Array(parts) if parts.len() == 2 => Some((&parts[0], &parts[1])),
+                _ => None,
+            })
+            .collect(),
+        Value::Map(streams) => streams.iter().map(|(k, v)| (k, v)).collect(),
+        _ => vec![],
+    };
+
+    pairs
+        .into_iter()
+        .filter_map(|(name, entries)| Some((value_to_string(name)?, parse_entries(entries))))
+        .collect()
+}
+
+/// Convert a flat `[name, value, ...]` XINFO reply into a map.
+///
+/// Names are converted to snake case (`last-delivered-id` becomes
+/// `last_delivered_id`) and nested entries, groups and consumers are
+/// converted recursively.
+fn info_to_map(value: &Value) -> Map {
+    let pairs: Vec<(&Value, &Value)> = match value {
+        Value::Array(items) => items
+            .chunks(2)
+            .filter_map(|pair| match pair {
+                [name, value] => Some((name, value)),
+                _ => None,
+            })
+            .collect(),
+        Value::Map(items) => items.iter().map(|(k, v)| (k, v)).collect(),
+        _ => vec![],
+    };
+
+    let mut map = Map::new();
+    for (name, value) in pairs {
+        let Some(name) = value_to_string(name) else {
+            continue;
+        };
+        let converted = match name.as_str() {
+            "first-entry" | "last-entry" => parse_entry(value).map_or(Dynamic::UNIT, Dynamic::from),
+            "entries" => parse_entries(value).into(),
+            "groups" | "consumers" => match value {
+                Value::Array(items) => items
+                    .iter()
+                    .map(|item| Dynamic::from(info_to_map(item)))
+                    .collect::<Array>()
+                    .into(),
+                _ => redis_value_to_dynamic(value.clone()),
+            },
+            _ => redis_value_to_dynamic(value.clone()),
+        };
+        map.insert(name.replace('-', "_").into(), converted);
+    }
+    map
+}
+
+/// Convert an XREAD/XREADGROUP reply into a map of stream name to entries
+fn stream_reply_to_map(value: &Value) -> Map {
+    parse_stream_reply(value)
+        .into_iter()
+        .map(|(stream, entries)| (stream.into(), Dynamic::from(entries)))
+        .collect()
+}
+
+/// Convert an extended XPENDING reply into maps
+fn parse_pending_entries(value: &Value) -> Array {
+    let Value::Array(items) = value else {
+        return Array::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| match item {
+            Value::Array(parts) if parts.len() == 4 => {
+                let mut pending = Map::new();
+                pending.insert("id".into(), value_to_string(&parts[0])?.into());
+                pending.insert("consumer".into(), value_to_string(&parts[1])?.into());
+                pending.insert("idle_ms".into(), value_to_int(&parts[2]).into());
+                pending.insert("deliveries".into(), value_to_int(&parts[3]).into());
+                Some(Dynamic::from(pending))
+            }
+            _ => None,
+        })
+        .collect()
+}
 
 impl RedisClient {
     pub fn xadd(&mut self, key: &str, id: &str, fields: Vec<Dynamic>) -> Dynamic {
@@ -13,36 +176,208 @@ impl RedisClient {
         self.cmd("XADD", args)
     }
 
-    pub fn xread(&mut self, count: i64, streams: Vec<Dynamic>) -> Dynamic {
+    /// Append an entry built from a map of fields.
+    ///
+    /// Supported options: `maxlen` (int) or `minid` (string) to trim the
+    /// stream, `approx` (bool) for `~` trimming, `limit` (int) to cap the
+    /// work done by approximate trimming, and `nomkstream` (bool) to avoid
+    /// creating a missing stream.
+    ///
+    /// # Rhai Example
+    /// ```rhai
+    /// let id = redis.xadd("events", "*", #{type: "click", x: 10}, #{maxlen: 1000, approx: true});
+    /// ```
+    ///
+    /// # Returns
+    /// The ID of the new entry, or `()` if the stream was not created
+    pub fn xadd_map(&mut self, key: &str, id: &str, fields: Map, options: Map) -> Dynamic {
+        let flag = |name: &str| {
+            options
+                .get(name)
+                .and_then(|v| v.as_bool().ok())
+                .unwrap_or(false)
+        };
+
+        let mut args = vec![Dynamic::from(key.to_string())];
+        if flag("nomkstream") {
+            args.push(Dynamic::from("NOMKSTREAM"));
+        }
+        let trim = options
+            .get("maxlen")
+            .map(|v| ("MAXLEN", v))
+            .or_else(|| options.get("minid").map(|v| ("MINID", v)));
+        if let Some((strategy, threshold)) = trim {
+            args.push(Dynamic::from(strategy));
+            args.push(Dynamic::from(if flag("approx") { "~" } else { "=" }));
+            args.push(threshold.clone());
+            if let Some(limit) = options.get("limit") {
+                args.push(Dynamic::from("LIMIT"));
+                args.push(limit.clone());
+            }
+        }
+        args.push(Dynamic::from(id.to_string()));
+        for (field, value) in fields {
+            args.push(Dynamic::from(field.to_string()));
+            args.push(value);
+        }
+        self.cmd("XADD", args)
+    }
+
+    /// Run a command and return the raw reply, or `None` on error
+    fn query_value(&mut self, command: &str, args: Vec<Dynamic>) -> Option<Value> {
+        let mut conn = self.conn.lock().unwrap();
+        match build_cmd(command, args).query::<Value>(&mut *conn) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                eprintln!("Redis error in {}: {}", command, e);
+                None
+            }
+        }
+    }
+
+    /// Read entries from one or more streams.
+    ///
+    /// `streams` lists the stream names followed by the ID to read after for
+    /// each, e.g. `["s1", "s2", "0", "0"]`.
+    ///
+    /// # Returns
+    /// A map of stream name to entries (`[#{id, fields}]`), empty if nothing was read
+    pub fn xread(&mut self, count: i64, streams: Vec<Dynamic>) -> Map {
         let mut args = vec![
             Dynamic::from("COUNT"),
             Dynamic::from(count),
             Dynamic::from("STREAMS"),
         ];
         args.extend(streams);
-        self.cmd("XREAD", args)
+        self.query_value("XREAD", args)
+            .map(|reply| stream_reply_to_map(&reply))
+            .unwrap_or_default()
     }
 
-    pub fn xrange(&mut self, key: &str, start: &str, end: &str) -> Dynamic {
-        self.cmd(
-            "XRANGE",
-            vec![
-                Dynamic::from(key.to_string()),
-                Dynamic::from(start.to_string()),
-                Dynamic::from(end.to_string()),
-            ],
-        )
+    /// Read entries from streams, blocking up to `block_ms` for new ones (`0` blocks
+    /// until shutdown).
+    ///
+    /// The wait happens in short slices, so other scripts sharing this client
+    /// can use the connection meanwhile and a host can cancel the script
+    /// without leaving a command half-finished.
+    ///
+    /// # Rhai Example
+    /// ```rhai
+    /// let batch = redis.xread(10, ["events", "$"], 5000);
+    /// if batch == () {
+    ///     print("No new events");
+    /// }
+    /// ```
+    ///
+    /// # Returns
+    /// A map like the non-blocking form, or `()` on timeout
+    pub fn xread_block(&mut self, count: i64, streams: Vec<Dynamic>, block_ms: i64) -> Dynamic {
+        let streams = match self.resolve_last_ids(streams) {
+            Ok(streams) => streams,
+            Err(e) => {
+                eprintln!("Redis error in XREAD: {}", e);
+                return Dynamic::UNIT;
+            }
+        };
+        let prefix = vec![Dynamic::from("COUNT"), Dynamic::from(count)];
+        match self.read_blocking("XREAD", prefix, streams, block_ms) {
+            Ok(Some(reply)) => stream_reply_to_map(&reply).into(),
+            Ok(None) => Dynamic::UNIT,
+            Err(e) => {
+                eprintln!("Redis error in XREAD: {}", e);
+                Dynamic::UNIT
+            }
+        }
     }
 
-    pub fn xrevrange(&mut self, key: &str, end: &str, start: &str) -> Dynamic {
-        self.cmd(
-            "XREVRANGE",
-            vec![
-                Dynamic::from(key.to_string()),
-                Dynamic::from(end.to_string()),
-                Dynamic::from(start.to_string()),
-            ],
-        )
+    /// Replace `$` IDs with each stream's current last ID.
+    ///
+    /// Blocking reads are issued in slices, and repeating `$` on every slice
+    /// would skip entries added between them.
+    fn resolve_last_ids(&mut self, mut streams: Vec<Dynamic>) -> redis::RedisResult<Vec<Dynamic>> {
+        let half = streams.len() / 2;
+        let mut conn = self.conn.lock().unwrap();
+        for i in half..streams.len() {
+            if streams[i].to_string() != "$" {
+                continue;
+            }
+            let key = streams[i - half].to_string();
+            let info = redis::cmd("XINFO")
+                .arg("STREAM")
+                .arg(&key)
+                .query::<Value>(&mut *conn);
+            let last_id = match info {
+                Ok(info) => info_to_map(&info)
+                    .get("last_generated_id")
+                    .map(|id| id.to_string())
+                    .unwrap_or_else(|| "0-0".into()),
+                // A missing stream has no entries yet, so anything added is new
+                Err(e) if e.kind() == redis::ErrorKind::ResponseError => "0-0".into(),
+                Err(e) => return Err(e),
+            };
+            streams[i] = Dynamic::from(last_id);
+        }
+        Ok(streams)
+    }
+
+    /// Issue a blocking XREAD/XREADGROUP in slices of at most
+    /// [`POLL_INTERVAL`](crate::client::POLL_INTERVAL).
+    ///
+    /// Returns `None` on timeout, shutdown or cancellation.
+    fn read_blocking(
+        &self,
+        command: &str,
+        prefix: Vec<Dynamic>,
+        streams: Vec<Dynamic>,
+        block_ms: i64,
+    ) -> redis::RedisResult<Option<Value>> {
+        let deadline =
+            (block_ms > 0).then(|| Instant::now() + Duration::from_millis(block_ms as u64));
+
+        while !self.is_stopped() {
+            let Some(slice) = next_wait(deadline, &self.run) else {
+                break;
+            };
+
+            let mut args = prefix.clone();
+            args.push(Dynamic::from("BLOCK"));
+            args.push(Dynamic::from((slice.as_millis() as i64).max(1)));
+            args.push(Dynamic::from("STREAMS"));
+            args.extend(streams.iter().cloned());
+
+            let mut conn = self.conn.lock().unwrap();
+            match build_cmd(command, args).query::<Value>(&mut *conn)? {
+                Value::Nil => continue,
+                Value::Array(items) if items.is_empty() => continue,
+                reply => return Ok(Some(reply)),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Get entries with IDs between `start` and `end` as `[#{id, fields}]`
+    pub fn xrange(&mut self, key: &str, start: &str, end: &str) -> Array {
+        let args = vec![
+            Dynamic::from(key.to_string()),
+            Dynamic::from(start.to_string()),
+            Dynamic::from(end.to_string()),
+        ];
+        self.query_value("XRANGE", args)
+            .map(|reply| parse_entries(&reply))
+            .unwrap_or_default()
+    }
+
+    /// Get entries with IDs between `end` and `start` in reverse order as `[#{id, fields}]`
+    pub fn xrevrange(&mut self, key: &str, end: &str, start: &str) -> Array {
+        let args = vec![
+            Dynamic::from(key.to_string()),
+            Dynamic::from(end.to_string()),
+            Dynamic::from(start.to_string()),
+        ];
+        self.query_value("XREVRANGE", args)
+            .map(|reply| parse_entries(&reply))
+            .unwrap_or_default()
     }
 
     pub fn xlen(&mut self, key: &str) -> Dynamic {
@@ -89,13 +424,14 @@ impl RedisClient {
         )
     }
 
+    /// Read entries for a consumer group, returning a map like [`xread`](Self::xread)
     pub fn xreadgroup(
         &mut self,
         group: &str,
         consumer: &str,
         count: i64,
         streams: Vec<Dynamic>,
-    ) -> Dynamic {
+    ) -> Map {
         let mut args = vec![
             Dynamic::from("GROUP"),
             Dynamic::from(group.to_string()),
@@ -105,14 +441,649 @@ impl RedisClient {
             Dynamic::from("STREAMS"),
         ];
         args.extend(streams);
-        self.cmd("XREADGROUP", args)
+        self.query_value("XREADGROUP", args)
+            .map(|reply| stream_reply_to_map(&reply))
+            .unwrap_or_default()
+    }
+
+    /// Get information about a stream.
+    ///
+    /// With `full`, the reply includes the stream's entries and every
+    /// group's consumers and pending entries.
+    ///
+    /// # Rhai Example
+    /// ```rhai
+    /// let info = redis.xinfo_stream("orders", false);
+    /// print(info.length + " entries, last " + info.last_generated_id);
+    /// ```
+    pub fn xinfo_stream(&mut self, key: &str, full: bool) -> Map {
+        let mut args = vec![Dynamic::from("STREAM"), Dynamic::from(key.to_string())];
+        if full {
+            args.push(Dynamic::from("FULL"));
+        }
+        self.query_value("XINFO", args)
+            .map(|reply| info_to_map(&reply))
+            .unwrap_or_default()
+    }
+
+    /// Get the consumer groups of a stream.
+    ///
+    /// Returns `[#{name, consumers, pending, last_delivered_id, entries_read, lag}]`;
+    /// `lag` is `()` when Redis can't compute it.
+    pub fn xinfo_groups(&mut self, key: &str) -> Array {
+        let args = vec![Dynamic::from("GROUPS"), Dynamic::from(key.to_string())];
+        match self.query_value("XINFO", args) {
+            Some(Value::Array(groups)) => groups
+                .iter()
+                .map(|group| Dynamic::from(info_to_map(group)))
+                .collect(),
+            _ => Array::new(),
+        }
+    }
+
+    /// Get the consumers of a group.
+    ///
+    /// Returns `[#{name, pending, idle, inactive}]` with times in milliseconds.
+    pub fn xinfo_consumers(&mut self, key: &str, group: &str) -> Array {
+        let args = vec![
+            Dynamic::from("CONSUMERS"),
+            Dynamic::from(key.to_string()),
+            Dynamic::from(group.to_string()),
+        ];
+        match self.query_value("XINFO", args) {
+            Some(Value::Array(consumers)) => consumers
+                .iter()
+                .map(|consumer| Dynamic::from(info_to_map(consumer)))
+                .collect(),
+            _ => Array::new(),
+        }
+    }
+
+    /// Create a consumer in a group, returning `true` if it was created
+    pub fn xgroup_createconsumer(&mut self, key: &str, group: &str, consumer: &str) -> bool {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("XGROUP")
+            .arg("CREATECONSUMER")
+            .arg(key)
+            .arg(group)
+            .arg(consumer)
+            .query::<bool>(&mut *conn)
+            .unwrap_or(false)
+    }
+
+    /// Delete a consumer from a group, returning how many pending entries it had
+    pub fn xgroup_delconsumer(&mut self, key: &str, group: &str, consumer: &str) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("XGROUP")
+            .arg("DELCONSUMER")
+            .arg(key)
+            .arg(group)
+            .arg(consumer)
+            .query::<i64>(&mut *conn)
+            .unwrap_or(0)
+    }
+
+    /// Set a group's last delivered ID, optionally with its entries-read counter
+    pub fn xgroup_setid(
+        &mut self,
+        key: &str,
+        group: &str,
+        id: &str,
+        entries_read: Option<i64>,
+    ) -> bool {
+        let mut conn = self.conn.lock().unwrap();
+        let mut cmd = redis::cmd("XGROUP");
+        cmd.arg("SETID").arg(key).arg(group).arg(id);
+        if let Some(entries_read) = entries_read {
+            cmd.arg("ENTRIESREAD").arg(entries_read);
+        }
+        cmd.query::<()>(&mut *conn).is_ok()
+    }
+
+    /// Set the last generated ID of a stream
+    pub fn xsetid(&mut self, key: &str, last_id: &str) -> bool {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("XSETID")
+            .arg(key)
+            .arg(last_id)
+            .query::<()>(&mut *conn)
+            .is_ok()
+    }
+
+    /// Read entries for a consumer group, blocking up to `block_ms` like
+    /// [`xread_block`](Self::xread_block).
+    ///
+    /// # Returns
+    /// A map of stream name to entries, or `()` on timeout
+    pub fn xreadgroup_block(
+        &mut self,
+        group: &str,
+        consumer: &str,
+        count: i64,
+        streams: Vec<Dynamic>,
+        block_ms: i64,
+    ) -> Dynamic {
+        let prefix = vec![
+            Dynamic::from("GROUP"),
+            Dynamic::from(group.to_string()),
+            Dynamic::from(consumer.to_string()),
+            Dynamic::from("COUNT"),
+            Dynamic::from(count),
+        ];
+        match self.read_blocking("XREADGROUP", prefix, streams, block_ms) {
+            Ok(Some(reply)) => stream_reply_to_map(&reply).into(),
+            Ok(None) => Dynamic::UNIT,
+            Err(e) => {
+                eprintln!("Redis error in XREADGROUP: {}", e);
+                Dynamic::UNIT
+            }
+        }
+    }
+
+    /// Acknowledge entries, removing them from the group's pending list
+    pub fn xack(&mut self, key: &str, group: &str, ids: Array) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("XACK")
+            .arg(key)
+            .arg(group)
+            .arg(to_strings(ids))
+            .query::<i64>(&mut *conn)
+            .unwrap_or(0)
+    }
+
+    /// Summarize a group's pending entries.
+    ///
+    /// Returns `#{count, min, max, consumers: #{name: count}}`.
+    pub fn xpending(&mut self, key: &str, group: &str) -> Map {
+        let mut conn = self.conn.lock().unwrap();
+        let reply = redis::cmd("XPENDING")
+            .arg(key)
+            .arg(group)
+            .query::<Value>(&mut *conn);
+
+        let mut summary = Map::new();
+        let Ok(Value::Array(parts)) = reply else {
+            return summary;
+        };
+        if parts.len() != 4 {
+            return summary;
+        }
+        summary.insert("count".into(), value_to_int(&parts[0]).into());
+        summary.insert(
+            "min".into(),
+            value_to_string(&parts[1]).map_or(Dynamic::UNIT, Dynamic::from),
+        );
+        summary.insert(
+            "max".into(),
+            value_to_string(&parts[2]).map_or(Dynamic::UNIT, Dynamic::from),
+        );
+        let consumers: Map = match &parts[3] {
+            Value::Array(consumers) => consumers
+                .iter()
+                .filter_map(|c| match c {
+                    Value::Array(pair) if pair.len() == 2 => Some((
+                        value_to_string(&pair[0])?.into(),
+                        Dynamic::from(value_to_int(&pair[1])),
+                    )),
+                    _ => None,
+                })
+                .collect(),
+            _ => Map::new(),
+        };
+        summary.insert("consumers".into(), consumers.into());
+        summary
+    }
+
+    /// List pending entries in an ID range, optionally for one consumer.
+    ///
+    /// Returns `[#{id, consumer, idle_ms, deliveries}]`.
+    pub fn xpending_range(
+        &mut self,
+        key: &str,
+        group: &str,
+        start: &str,
+        end: &str,
+        count: i64,
+        consumer: Option<&str>,
+    ) -> Array {
+        let mut conn = self.conn.lock().unwrap();
+        let mut cmd = redis::cmd("XPENDING");
+        cmd.arg(key).arg(group).arg(start).arg(end).arg(count);
+        if let Some(consumer) = consumer {
+            cmd.arg(consumer);
+        }
+        match cmd.query::<Value>(&mut *conn) {
+            Ok(value) => parse_pending_entries(&value),
+            Err(_) => Array::new(),
+        }
+    }
+
+    /// Take ownership of pending entries idle for at least `min_idle_ms`.
+    ///
+    /// Returns the claimed entries.
+    pub fn xclaim(
+        &mut self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle_ms: i64,
+        ids: Array,
+    ) -> Array {
+        let mut conn = self.conn.lock().unwrap();
+        match redis::cmd("XCLAIM")
+            .arg(key)
+            .arg(group)
+            .arg(consumer)
+            .arg(min_idle_ms)
+            .arg(to_strings(ids))
+            .query::<Value>(&mut *conn)
+        {
+            Ok(value) => parse_entries(&value),
+            Err(_) => Array::new(),
+        }
+    }
+
+    /// Scan the pending list from `start` and claim entries idle for at least `min_idle_ms`.
+    ///
+    /// Returns `#{next, entries, deleted}` where `next` is the cursor for the
+    /// following call (`"0-0"` once the scan is complete).
+    pub fn xautoclaim(
+        &mut self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle_ms: i64,
+        start: &str,
+        count: i64,
+    ) -> Map {
+        let mut conn = self.conn.lock().unwrap();
+        let reply = redis::cmd("XAUTOCLAIM")
+            .arg(key)
+            .arg(group)
+            .arg(consumer)
+            .arg(min_idle_ms)
+            .arg(start)
+            .arg("COUNT")
+            .arg(count)
+            .query::<Value>(&mut *conn);
+
+        let mut result = Map::new();
+        let Ok(Value::Array(parts)) = reply else {
+            return result;
+        };
+        result.insert(
+            "next".into(),
+            parts
+                .first()
+                .and_then(value_to_string)
+                .unwrap_or_else(|| "0-0".into())
+                .into(),
+        );
+        result.insert(
+            "entries".into(),
+            parts.get(1).map(parse_entries).unwrap_or_default().into(),
+        );
+        let deleted: Array = match parts.get(2) {
+            Some(Value::Array(ids)) => ids
+                .iter()
+                .filter_map(value_to_string)
+                .map(Dynamic::from)
+                .collect(),
+            _ => Array::new(),
+        };
+        result.insert("deleted".into(), deleted.into());
+        result
+    }
+}
+
+/// Counters reported by a [`StreamWorker`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WorkerStats {
+    /// Entries handled successfully and acknowledged
+    pub processed: u64,
+    /// Handler runs that threw an exception
+    pub failed: u64,
+    /// Entries moved to the dead-letter stream
+    pub dead_lettered: u64,
+}
+
+impl std::ops::AddAssign for WorkerStats {
+    fn add_assign(&mut self, other: Self) {
+        self.processed += other.processed;
+        self.failed += other.failed;
+        self.dead_lettered += other.dead_lettered;
+    }
+}
+
+/// Runs a Rhai handler for every entry delivered to a consumer group.
+///
+/// The handler script runs once per entry with `redis` and `entry` in scope,
+/// where `entry` is `#{id, stream, fields, deliveries}`. Entries are
+/// acknowledged when the script succeeds. When it throws, the entry stays
+/// pending and is retried once it has been idle for `min_idle_ms`; after
+/// `max_deliveries` attempts it is copied to the dead-letter stream (with
+/// `_id`, `_error` and `_deliveries` fields added) and acknowledged. A
+/// timeout set with [`RedisEngine::set_timeout`] applies to each handler run.
+/// A handler that times out or is cancelled has not failed: its entry stays
+/// pending without counting a delivery failure, and is reclaimed later.
+///
+/// ```no_run
+/// use rhai_redis::streams::StreamWorker;
+/// use rhai_redis::{RedisClient, RedisEngine};
+///
+/// let mut engine = RedisEngine::new();
+/// engine.set_redis_client(RedisClient::open("redis://localhost").unwrap());
+///
+/// let mut worker = StreamWorker::new(engine, "orders", "billing", "worker-1", r#"
+///     redis.incrby("revenue", parse_int(entry.fields.amount));
+/// "#)
+/// .unwrap()
+/// .with_max_deliveries(5);
+///
+/// let stats = worker.run().unwrap();
+/// ```
+pub struct StreamWorker {
+    engine: RedisEngine,
+    handler: AST,
+    stream: String,
+    group: String,
+    consumer: String,
+    dead_letter: String,
+    count: i64,
+    block_ms: i64,
+    max_deliveries: i64,
+    min_idle_ms: i64,
+}
+
+impl StreamWorker {
+    /// Create a worker, compiling the handler script up front
+    pub fn new(
+        engine: RedisEngine,
+        stream: &str,
+        group: &str,
+        consumer: &str,
+        script: &str,
+    ) -> Result<Self> {
+        engine.redis_client()?;
+        let handler = engine.engine.compile(script)?;
+        Ok(Self {
+            engine,
+            handler,
+            stream: stream.to_string(),
+            group: group.to_string(),
+            consumer: consumer.to_string(),
+            dead_letter: format!("{}:dead", stream),
+            count: 10,
+            block_ms: 1000,
+            max_deliveries: 3,
+            min_idle_ms: 30_000,
+        })
+    }
+
+    /// Maximum entries to read per batch (default 10)
+    pub fn with_count(mut self, count: i64) -> Self {
+        self.count = count.max(1);
+        self
+    }
+
+    /// How long each read waits for new entries before [`run`](Self::run)
+    /// looks for failed entries to retry (default 1000 ms)
+    pub fn with_block_ms(mut self, block_ms: i64) -> Self {
+        self.block_ms = block_ms.max(1);
+        self
+    }
+
+    /// Attempts before an entry is dead-lettered (default 3)
+    pub fn with_max_deliveries(mut self, max_deliveries: i64) -> Self {
+        self.max_deliveries = max_deliveries.max(1);
+        self
+    }
+
+    /// Idle time before a pending entry is reclaimed and retried (default 30 s)
+    pub fn with_min_idle_ms(mut self, min_idle_ms: i64) -> Self {
+        self.min_idle_ms = min_idle_ms.max(0);
+        self
+    }
+
+    /// Stream that receives entries which exhausted their deliveries
+    /// (default `{stream}:dead`)
+    pub fn with_dead_letter(mut self, stream: &str) -> Self {
+        self.dead_letter = stream.to_string();
+        self
+    }
+
+    /// Handle for stopping [`run`](Self::run) from another thread
+    pub fn shutdown_handle(&self) -> Result<ShutdownHandle> {
+        self.engine.shutdown_handle()
+    }
+
+    /// Token for terminating the running handler from another thread
+    pub fn cancel_token(&mut self) -> CancelToken {
+        self.engine.cancel_token()
+    }
+
+    /// Create the consumer group (and stream) if they don't exist yet
+    pub fn ensure_group(&mut self) -> Result<()> {
+        let client = self.engine.redis_client()?;
+        let mut conn = client.conn.lock().unwrap();
+        let created = redis::cmd("XGROUP")
+            .arg("CREATE")
+            .arg(&self.stream)
+            .arg(&self.group)
+            .arg("$")
+            .arg("MKSTREAM")
+            .query::<()>(&mut *conn);
+        match created {
+            Err(e) if e.code() != Some("BUSYGROUP") => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reclaim stale pending entries, then process new entries until shutdown.
+    ///
+    /// Pending entries are reclaimed whenever a read comes back empty, and at
+    /// least every `min_idle_ms` under steady load. Stops, leaving unprocessed
+    /// entries pending, on shutdown or when [`cancel_token`](Self::cancel_token)
+    /// is cancelled.
+    pub fn run(&mut self) -> Result<WorkerStats> {
+        let shutdown = self.engine.redis_client()?.shutdown_handle();
+        self.engine.clear_cancel();
+        self.ensure_group()?;
+
+        let reclaim_interval = Duration::from_millis(self.min_idle_ms as u64);
+        let mut stats = self.reclaim_pending()?;
+        let mut last_reclaim = Instant::now();
+        while !shutdown.is_shutdown() && !self.engine.is_cancelled() {
+            let batch = self.read_batch()?;
+            // Retry failed entries when idle, and on a fixed interval so they
+            // aren't starved while new entries keep arriving
+            if batch == WorkerStats::default() || last_reclaim.elapsed() >= reclaim_interval {
+                stats += self.reclaim_pending()?;
+                last_reclaim = Instant::now();
+            }
+            stats += batch;
+        }
+        Ok(stats)
+    }
+
+    /// Read and process one batch of new entries, blocking up to `block_ms`
+    pub fn run_once(&mut self) -> Result<WorkerStats> {
+        self.engine.clear_cancel();
+        self.read_batch()
+    }
+
+    /// Claim entries that have been pending for at least `min_idle_ms` and process them again
+    pub fn reclaim(&mut self) -> Result<WorkerStats> {
+        self.engine.clear_cancel();
+        self.reclaim_pending()
+    }
+
+    /// Whether to stop before the next entry, leaving it pending
+    fn should_stop(&self) -> Result<bool> {
+        Ok(self.engine.redis_client()?.shutdown.is_shutdown() || self.engine.is_cancelled())
+    }
+
+    fn read_batch(&mut self) -> Result<WorkerStats> {
+        let client = self.engine.redis_client()?;
+        let prefix = vec![
+            Dynamic::from("GROUP"),
+            Dynamic::from(self.group.clone()),
+            Dynamic::from(self.consumer.clone()),
+            Dynamic::from("COUNT"),
+            Dynamic::from(self.count),
+        ];
+        let streams = vec![Dynamic::from(self.stream.clone()), Dynamic::from(">")];
+        let Some(reply) = client.read_blocking("XREADGROUP", prefix, streams, self.block_ms)?
+        else {
+            return Ok(WorkerStats::default());
+        };
+
+        let mut stats = WorkerStats::default();
+        for (_, entries) in parse_stream_reply(&reply) {
+            for entry in entries {
+                if self.should_stop()? {
+                    return Ok(stats);
+                }
+                stats += self.process(entry.cast::<Map>(), 1)?;
+            }
+        }
+        Ok(stats)
+    }
+
+    fn reclaim_pending(&mut self) -> Result<WorkerStats> {
+        let mut client = self.engine.redis_client()?.clone();
+        let mut stats = WorkerStats::default();
+        let mut start = "-".to_string();
+
+        while !self.should_stop()? {
+            let pending =
+                client.xpending_range(&self.stream, &self.group, &start, "+", self.count, None);
+            let Some(last) = pending.last() else {
+                break;
+            };
+            let last_id = last.clone().cast::<Map>()["id"].to_string();
+
+            let mut deliveries = HashMap::new();
+            for entry in &pending {
+                let entry = entry.clone().cast::<Map>();
+                if entry["idle_ms"].as_int().unwrap_or(0) >= self.min_idle_ms {
+                    deliveries.insert(
+                        entry["id"].to_string(),
+                        entry["deliveries"].as_int().unwrap_or(0),
+                    );
+                }
+            }
+
+            if !deliveries.is_empty() {
+                let ids = deliveries.keys().cloned().map(Dynamic::from).collect();
+                let claimed = client.xclaim(
+                    &self.stream,
+                    &self.group,
+                    &self.consumer,
+                    self.min_idle_ms,
+                    ids,
+                );
+                for entry in claimed {
+                    if self.should_stop()? {
+                        return Ok(stats);
+                    }
+                    let entry = entry.cast::<Map>();
+                    let previous = deliveries[&entry["id"].to_string()];
+                    stats += self.process(entry, previous + 1)?;
+                }
+            }
+
+            if (pending.len() as i64) < self.count {
+                break;
+            }
+            // Exclusive range start so the last entry isn't listed twice
+            start = format!("({}", last_id);
+        }
+
+        Ok(stats)
+    }
+
+    /// Run the handler for one entry and acknowledge or dead-letter it
+    fn process(&mut self, mut entry: Map, deliveries: i64) -> Result<WorkerStats> {
+        let mut stats = WorkerStats::default();
+        let id = entry["id"].to_string();
+        entry.insert("stream".into(), self.stream.clone().into());
+        entry.insert("deliveries".into(), deliveries.into());
+        let fields = entry["fields"].clone().cast::<Map>();
+
+        // Run through the engine so handlers get the same timeout as scripts,
+        // without clearing a cancellation meant for the whole batch
+        let client = self.engine.continue_run()?;
+        let mut scope = Scope::new();
+        scope.push("redis", client.clone());
+        scope.push("entry", entry);
+
+        let result = self
+            .engine
+            .engine
+            .run_ast_with_scope(&mut scope, &self.handler);
+        let outcome = self.engine.finish_run(result);
+
+        let mut conn = client.conn.lock().unwrap();
+        match outcome {
+            // Interrupted rather than failed: leave the entry pending for a retry
+            Err(Error::Cancelled) => {}
+            Ok(()) => {
+                redis::cmd("XACK")
+                    .arg(&self.stream)
+                    .arg(&self.group)
+                    .arg(&id)
+                    .query::<()>(&mut *conn)?;
+                stats.processed += 1;
+            }
+            Err(err) => {
+                stats.failed += 1;
+                if deliveries >= self.max_deliveries {
+                    let mut xadd = redis::cmd("XADD");
+                    xadd.arg(&self.dead_letter).arg("*");
+                    for (field, value) in &fields {
+                        xadd.arg(field.as_str()).arg(value.to_string());
+                    }
+                    xadd.arg("_id")
+                        .arg(&id)
+                        .arg("_error")
+                        .arg(match err {
+                            Error::Script(message) => message,
+                            err => err.to_string(),
+                        })
+                        .arg("_deliveries")
+                        .arg(deliveries);
+
+                    redis::pipe()
+                        .atomic()
+                        .add_command(xadd)
+                        .ignore()
+                        .cmd("XACK")
+                        .arg(&self.stream)
+                        .arg(&self.group)
+                        .arg(&id)
+                        .ignore()
+                        .query::<()>(&mut *conn)?;
+                    stats.dead_lettered += 1;
+                }
+            }
+        }
+        Ok(stats)
     }
 }
 
 pub fn register_stream_methods(engine: &mut Engine) {
     engine
         .register_fn("xadd", RedisClient::xadd)
+        .register_fn(
+            "xadd",
+            |client: &mut RedisClient, key: &str, id: &str, fields: Map| {
+                client.xadd_map(key, id, fields, Map::new())
+            },
+        )
+        .register_fn("xadd", RedisClient::xadd_map)
         .register_fn("xread", RedisClient::xread)
+        .register_fn("xread", RedisClient::xread_block)
         .register_fn("xrange", RedisClient::xrange)
         .register_fn("xrevrange", RedisClient::xrevrange)
         .register_fn("xlen", RedisClient::xlen)
@@ -120,5 +1091,52 @@ pub fn register_stream_methods(engine: &mut Engine) {
         .register_fn("xtrim", RedisClient::xtrim)
         .register_fn("xgroup_create", RedisClient::xgroup_create)
         .register_fn("xgroup_destroy", RedisClient::xgroup_destroy)
-        .register_fn("xreadgroup", RedisClient::xreadgroup);
+        .register_fn("xreadgroup", RedisClient::xreadgroup)
+        .register_fn("xreadgroup", RedisClient::xreadgroup_block)
+        .register_fn("xinfo_stream", RedisClient::xinfo_stream)
+        .register_fn("xinfo_stream", |client: &mut RedisClient, key: &str| {
+            client.xinfo_stream(key, false)
+        })
+        .register_fn("xinfo_groups", RedisClient::xinfo_groups)
+        .register_fn("xinfo_consumers", RedisClient::xinfo_consumers)
+        .register_fn("xgroup_createconsumer", RedisClient::xgroup_createconsumer)
+        .register_fn("xgroup_delconsumer", RedisClient::xgroup_delconsumer)
+        .register_fn(
+            "xgroup_setid",
+            |client: &mut RedisClient, key: &str, group: &str, id: &str| {
+                client.xgroup_setid(key, group, id, None)
+            },
+        )
+        .register_fn(
+            "xgroup_setid",
+            |client: &mut RedisClient, key: &str, group: &str, id: &str, entries_read: i64| {
+                client.xgroup_setid(key, group, id, Some(entries_read))
+            },
+        )
+        .register_fn("xsetid", RedisClient::xsetid)
+        .register_fn("xack", RedisClient::xack)
+        .register_fn("xpending", RedisClient::xpending)
+        .register_fn(
+            "xpending",
+            |client: &mut RedisClient,
+             key: &str,
+             group: &str,
+             start: &str,
+             end: &str,
+             count: i64| { client.xpending_range(key, group, start, end, count, None) },
+        )
+        .register_fn(
+            "xpending",
+            |client: &mut RedisClient,
+             key: &str,
+             group: &str,
+             start: &str,
+             end: &str,
+             count: i64,
+             consumer: &str| {
+                client.xpending_range(key, group, start, end, count, Some(consumer))
+            },
+        )
+        .register_fn("xclaim", RedisClient::xclaim)
+        .register_fn("xautoclaim", RedisClient::xautoclaim);
 }
@@ -11,8 +11,56 @@
 //! ```
 
 use crate::client::RedisClient;
+use crate::generic::redis_value_to_dynamic;
 use redis::Commands;
-use rhai::{Dynamic, Engine};
+use rhai::{Array, Dynamic, Engine, Map};
+
+pub(crate) fn option_flag(options: &Map, name: &str) -> bool {
+    options
+        .get(name)
+        .and_then(|v| v.as_bool().ok())
+        .unwrap_or(false)
+}
+
+/// Append `EX`, `PX`, `EXAT`, `PXAT`, `KEEPTTL` or `PERSIST` from an options map
+pub(crate) fn expiry_args(cmd: &mut redis::Cmd, options: &Map) {
+    for name in ["ex", "px", "exat", "pxat"] {
+        if let Some(value) = options.get(name).and_then(|v| v.as_int().ok()) {
+            cmd.arg(name.to_uppercase()).arg(value);
+        }
+    }
+    for name in ["keepttl", "persist"] {
+        if option_flag(options, name) {
+            cmd.arg(name.to_uppercase());
+        }
+    }
+}
+
+/// Run a command whose reply is a string or nil
+fn optional_string(client: &RedisClient, cmd: &redis::Cmd) -> Dynamic {
+    let mut conn = client.conn.lock().unwrap();
+    match cmd.query::<Option<String>>(&mut *conn) {
+        Ok(Some(value)) => Dynamic::from(value),
+        Ok(None) => Dynamic::UNIT,
+        Err(e) => {
+            eprintln!("Redis error: {}", e);
+            Dynamic::UNIT
+        }
+    }
+}
+
+/// Flatten `#{key: value}` or `[key, value, ...]` into command arguments
+fn pairs_to_args(pairs: &Dynamic) -> Vec<String> {
+    if let Some(map) = pairs.read_lock::<Map>() {
+        map.iter()
+            .flat_map(|(k, v)| [k.to_string(), v.to_string()])
+            .collect()
+    } else if let Some(array) = pairs.read_lock::<Array>() {
+        array.iter().map(|v| v.to_string()).collect()
+    } else {
+        Vec::new()
+    }
+}
 
 impl RedisClient {
     /// Get the value of a key.
@@ -59,6 +107,279 @@ impl RedisClient {
         conn.set::<_, _, ()>(key, value).is_ok()
     }
 
+    /// Set a key with options.
+    ///
+    /// Options: `ex`/`px` (TTL in seconds or milliseconds), `exat`/`pxat`
+    /// (Unix timestamp), `keepttl`, `nx` (only if missing), `xx` (only if
+    /// present) and `get` (return the previous value).
+    ///
+    /// # Rhai Example
+    /// ```rhai
+    /// if redis.set("lock", "token", #{nx: true, px: 30000}) {
+    ///     print("Acquired");
+    /// }
+    /// let previous = redis.set("name", "Bob", #{get: true});
+    /// ```
+    ///
+    /// # Returns
+    /// - With `get`: the previous value, or `()` if the key did not exist
+    /// - Otherwise `true` if the key was set, `false` if a condition failed or on error
+    pub fn set_with(&mut self, key: &str, value: &str, options: Map) -> Dynamic {
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(key).arg(value);
+        expiry_args(&mut cmd, &options);
+        for name in ["nx", "xx", "get"] {
+            if option_flag(&options, name) {
+                cmd.arg(name.to_uppercase());
+            }
+        }
+
+        if option_flag(&options, "get") {
+            return optional_string(self, &cmd);
+        }
+        let mut conn = self.conn.lock().unwrap();
+        match cmd.query::<Option<String>>(&mut *conn) {
+            Ok(reply) => Dynamic::from(reply.is_some()),
+            Err(e) => {
+                eprintln!("Redis error: {}", e);
+                Dynamic::FALSE
+            }
+        }
+    }
+
+    /// Set a key only if it does not exist.
+    ///
+    /// # Returns
+    /// `true` if the key was set
+    pub fn setnx(&mut self, key: &str, value: &str) -> bool {
+        let mut conn = self.conn.lock().unwrap();
+        conn.set_nx::<_, _, bool>(key, value).unwrap_or(false)
+    }
+
+    /// Set a key with a TTL in seconds.
+    ///
+    /// # Returns
+    /// `true` if the key was set
+    pub fn setex(&mut self, key: &str, seconds: i64, value: &str) -> bool {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("SETEX")
+            .arg(key)
+            .arg(seconds)
+            .arg(value)
+            .query::<()>(&mut *conn)
+            .is_ok()
+    }
+
+    /// Get the values of several keys.
+    ///
+    /// # Rhai Example
+    /// ```rhai
+    /// let values = redis.mget(["a", "b", "c"]);
+    /// ```
+    ///
+    /// # Returns
+    /// An array with each value, or `()` for missing keys
+    pub fn mget(&mut self, keys: Array) -> Array {
+        if keys.is_empty() {
+            return Array::new();
+        }
+        let keys: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+        let mut conn = self.conn.lock().unwrap();
+        match conn.mget::<_, Vec<Option<String>>>(&keys) {
+            Ok(values) => values
+                .into_iter()
+                .map(|v| v.map(Dynamic::from).unwrap_or(Dynamic::UNIT))
+                .collect(),
+            Err(e) => {
+                eprintln!("Redis error: {}", e);
+                vec![Dynamic::UNIT; keys.len()]
+            }
+        }
+    }
+
+    /// Set several keys from a map, or an array of alternating keys and values.
+    ///
+    /// # Rhai Example
+    /// ```rhai
+    /// redis.mset(#{a: "1", b: "2"});
+    /// redis.mset(["a", "1", "b", "2"]);
+    /// ```
+    ///
+    /// # Returns
+    /// `true` if the keys were set
+    pub fn mset(&mut self, pairs: Dynamic) -> bool {
+        let args = pairs_to_args(&pairs);
+        if args.is_empty() || !args.len().is_multiple_of(2) {
+            return false;
+        }
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("MSET").arg(args).query::<()>(&mut *conn).is_ok()
+    }
+
+    /// Set several keys only if none of them exist.
+    ///
+    /// # Returns
+    /// `true` if all keys were set, `false` if any already existed
+    pub fn msetnx(&mut self, pairs: Dynamic) -> bool {
+        let args = pairs_to_args(&pairs);
+        if args.is_empty() || !args.len().is_multiple_of(2) {
+            return false;
+        }
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("MSETNX")
+            .arg(args)
+            .query::<bool>(&mut *conn)
+            .unwrap_or(false)
+    }
+
+    /// Set a key and return its previous value.
+    ///
+    /// # Returns
+    /// The previous value, or `()` if the key did not exist
+    pub fn getset(&mut self, key: &str, value: &str) -> Dynamic {
+        optional_string(self, redis::cmd("GETSET").arg(key).arg(value))
+    }
+
+    /// Get a key and delete it.
+    ///
+    /// # Returns
+    /// The value, or `()` if the key did not exist
+    pub fn getdel(&mut self, key: &str) -> Dynamic {
+        optional_string(self, redis::cmd("GETDEL").arg(key))
+    }
+
+    /// Get a key and update its expiry.
+    ///
+    /// Options: `ex`, `px`, `exat`, `pxat` or `persist`.
+    ///
+    /// # Rhai Example
+    /// ```rhai
+    /// let session = redis.getex("session:1", #{ex: 1800});
+    /// ```
+    ///
+    /// # Returns
+    /// The value, or `()` if the key did not exist
+    pub fn getex(&mut self, key: &str, options: Map) -> Dynamic {
+        let mut cmd = redis::cmd("GETEX");
+        cmd.arg(key);
+        expiry_args(&mut cmd, &options);
+        optional_string(self, &cmd)
+    }
+
+    /// Append a value to a key, creating it if needed.
+    ///
+    /// # Returns
+    /// The length of the string after the append
+    pub fn append(&mut self, key: &str, value: &str) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        conn.append::<_, _, i64>(key, value).unwrap_or(0)
+    }
+
+    /// Get the length of a string value.
+    ///
+    /// # Returns
+    /// The length, or 0 if the key does not exist
+    pub fn strlen(&mut self, key: &str) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        conn.strlen::<_, i64>(key).unwrap_or(0)
+    }
+
+    /// Get a substring between two offsets (inclusive, negative counts from the end).
+    ///
+    /// # Returns
+    /// The substring, or `""` if the key does not exist
+    pub fn getrange(&mut self, key: &str, start: i64, end: i64) -> String {
+        let mut conn = self.conn.lock().unwrap();
+        conn.getrange::<_, String>(key, start as isize, end as isize)
+            .unwrap_or_default()
+    }
+
+    /// Overwrite part of a string starting at `offset`.
+    ///
+    /// # Returns
+    /// The length of the string after the change
+    pub fn setrange(&mut self, key: &str, offset: i64, value: &str) -> i64 {
+        let mut conn = self.conn.lock().unwrap();
+        conn.setrange::<_, _, i64>(key, offset as isize, value)
+            .unwrap_or(0)
+    }
+
+    /// Increment the floating point value of a key.
+    ///
+    /// # Rhai Example
+    /// ```rhai
+    /// let balance = redis.incrbyfloat("balance", 10.5);
+    /// ```
+    ///
+    /// # Returns
+    /// The new value, or 0.0 on error
+    pub fn incrbyfloat(&mut self, key: &str, increment: f64) -> f64 {
+        let mut conn = self.conn.lock().unwrap();
+        conn.incr::<_, _, f64>(key, increment).unwrap_or(0.0)
+    }
+
+    /// Find the longest common subsequence of two string values.
+    ///
+    /// # Returns
+    /// The common subsequence, or `""` on error
+    pub fn lcs(&mut self, key1: &str, key2: &str) -> String {
+        let mut conn = self.conn.lock().unwrap();
+        redis::cmd("LCS")
+            .arg(key1)
+            .arg(key2)
+            .query::<String>(&mut *conn)
+            .unwrap_or_default()
+    }
+
+    /// Find the longest common subsequence with options.
+    ///
+    /// Options: `len` (return only the length), `idx` (return match
+    /// positions), `minmatchlen` and `withmatchlen`.
+    ///
+    /// # Rhai Example
+    /// ```rhai
+    /// let result = redis.lcs("a", "b", #{idx: true, minmatchlen: 4});
+    /// print(result.len);
+    /// for m in result.matches { print(m); }
+    /// ```
+    ///
+    /// # Returns
+    /// - With `len`: the length
+    /// - With `idx`: `#{matches, len}`
+    /// - Otherwise the subsequence
+    pub fn lcs_with(&mut self, key1: &str, key2: &str, options: Map) -> Dynamic {
+        let mut cmd = redis::cmd("LCS");
+        cmd.arg(key1).arg(key2);
+        for name in ["len", "idx", "withmatchlen"] {
+            if option_flag(&options, name) {
+                cmd.arg(name.to_uppercase());
+            }
+        }
+        if let Some(min) = options.get("minmatchlen").and_then(|v| v.as_int().ok()) {
+            cmd.arg("MINMATCHLEN").arg(min);
+        }
+
+        let mut conn = self.conn.lock().unwrap();
+        match cmd.query::<redis::Value>(&mut *conn) {
+            // RESP2 returns the IDX reply as a flat [name, value, ...] array
+            Ok(redis::Value::Array(items)) => {
+                let mut map = Map::new();
+                for pair in items.chunks(2) {
+                    if let [name, value] = pair {
+                        let name = redis_value_to_dynamic(name.clone()).to_string();
+                        map.insert(name.into(), redis_value_to_dynamic(value.clone()));
+                    }
+                }
+                map.into()
+            }
+            Ok(value) => redis_value_to_dynamic(value),
+            Err(e) => {
+                eprintln!("Redis error: {}", e);
+                Dynamic::UNIT
+            }
+        }
+    }
+
     /// Delete a key.
     ///
     /// # Rhai Example
@@ -162,6 +483,22 @@ pub fn register_string_methods(engine: &mut Engine) {
     engine
         .register_fn("get", RedisClient::get)
         .register_fn("set", RedisClient::set)
+        .register_fn("set", RedisClient::set_with)
+        .register_fn("setnx", RedisClient::setnx)
+        .register_fn("setex", RedisClient::setex)
+        .register_fn("mget", RedisClient::mget)
+        .register_fn("mset", RedisClient::mset)
+        .register_fn("msetnx", RedisClient::msetnx)
+        .register_fn("getset", RedisClient::getset)
+        .register_fn("getdel", RedisClient::getdel)
+        .register_fn("getex", RedisClient::getex)
+        .register_fn("append", RedisClient::append)
+        .register_fn("strlen", RedisClient::strlen)
+        .register_fn("getrange", RedisClient::getrange)
+        .register_fn("setrange", RedisClient::setrange)
+        .register_fn("incrbyfloat", RedisClient::incrbyfloat)
+        .register_fn("lcs", RedisClient::lcs)
+        .register_fn("lcs", RedisClient::lcs_with)
         .register_fn("del", RedisClient::del)
         .register_fn("exists", RedisClient::exists)
         .register_fn("incr", RedisClient::incr)
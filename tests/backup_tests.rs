@@ -0,0 +1,159 @@
+#[cfg(test)]
+mod backup_tests {
+    use redis::Commands;
+    use rhai_redis::{Error, RedisClient, RedisEngine};
+
+    const URL: &str = "redis://localhost:6379";
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_dump_and_restore_from_script() {
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(RedisClient::open(URL).expect("Failed to connect"));
+
+        let script = r#"
+            redis.del(["test:backup:src", "test:backup:dst"]);
+            redis.rpush("test:backup:src", "a");
+            redis.rpush("test:backup:src", "b");
+
+            let payload = redis.dump("test:backup:src");
+            if type_of(payload) != "blob" { throw "expected a blob"; }
+            if redis.dump("test:backup:missing") != () { throw "expected ()"; }
+
+            if !redis.restore("test:backup:dst", 0, payload) { throw "restore failed"; }
+            if redis.restore("test:backup:dst", 0, payload) { throw "restore should not overwrite"; }
+            if !redis.restore("test:backup:dst", 60000, payload, #{replace: true}) {
+                throw "restore with replace failed";
+            }
+            if redis.lrange("test:backup:dst", 0, -1) != ["a", "b"] { throw "wrong contents"; }
+            if redis.ttl("test:backup:dst") <= 0 { throw "expected a TTL"; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_export_and_import_keys() {
+        let client = redis::Client::open(URL).expect("Failed to connect");
+        let mut conn = client.get_connection().expect("Failed to get connection");
+        let _: () = redis::cmd("FLUSHDB").query(&mut conn).unwrap();
+        let _: () = conn.set("test:export:a", "1").unwrap();
+        let _: () = conn.set_ex("test:export:b", "2", 600).unwrap();
+        let _: () = conn.hset("test:export:c", "field", "value").unwrap();
+        let _: () = conn.set("test:other", "x").unwrap();
+
+        let mut redis_client = RedisClient::open(URL).expect("Failed to connect");
+        let mut backup = Vec::new();
+        assert_eq!(
+            redis_client
+                .export_keys("test:export:*", &mut backup)
+                .unwrap(),
+            3
+        );
+
+        let _: () = redis::cmd("FLUSHDB").query(&mut conn).unwrap();
+        assert_eq!(redis_client.import_keys(&backup[..], false).unwrap(), 3);
+        assert!(redis_client.import_keys(&backup[..], false).is_err());
+        assert_eq!(redis_client.import_keys(&backup[..], true).unwrap(), 3);
+
+        let value: String = conn.get("test:export:a").unwrap();
+        assert_eq!(value, "1");
+        let ttl: i64 = conn.ttl("test:export:b").unwrap();
+        assert!(ttl > 590 && ttl <= 600);
+        let field: String = conn.hget("test:export:c", "field").unwrap();
+        assert_eq!(field, "value");
+        assert!(!conn.exists::<_, bool>("test:other").unwrap());
+
+        assert!(matches!(
+            redis_client.import_keys(&b"NOTADUMP\x01"[..], true),
+            Err(Error::Backup(_))
+        ));
+        // A record claiming 4 GiB is rejected without allocating it
+        assert!(matches!(
+            redis_client.import_keys(&b"RHAIDUMP\x01\xff\xff\xff\xffkey"[..], true),
+            Err(Error::Backup(_))
+        ));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_json_round_trip() {
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(RedisClient::open(URL).expect("Failed to connect"));
+
+        let script = r#"
+            redis.cmd("FLUSHDB", []);
+            redis.set("test:json:string", "hello");
+            redis.expire("test:json:string", 600);
+            redis.rpush("test:json:list", "a");
+            redis.rpush("test:json:list", "b");
+            redis.hset("test:json:hash", "field", "value");
+            redis.sadd("test:json:set", "x");
+            redis.zadd("test:json:zset", 2.5, "m");
+            redis.xadd("test:json:stream", "1-0", #{n: "1"}, #{});
+
+            let json = redis.export_json("test:json:*");
+            let document = parse_json(json);
+            if document.keys.len() != 6 { throw "expected 6 keys, got " + document.keys.len(); }
+            if document.keys[4].key != "test:json:string" || document.keys[4].ttl <= 0 {
+                throw "expected the string with a TTL";
+            }
+
+            try {
+                redis.import_json(json);
+                throw "import should fail on existing keys";
+            } catch (err) {
+                if type_of(err) != "string" || !err.contains("already exist") { throw err; }
+            }
+
+            redis.cmd("FLUSHDB", []);
+            if redis.import_json(json) != 6 { throw "expected 6 imported keys"; }
+            if redis.get("test:json:string") != "hello" { throw "wrong string"; }
+            if redis.ttl("test:json:string") <= 0 { throw "expected a TTL"; }
+            if redis.lrange("test:json:list", 0, -1) != ["a", "b"] { throw "wrong list"; }
+            if redis.hget("test:json:hash", "field") != "value" { throw "wrong hash"; }
+            if !redis.sismember("test:json:set", "x") { throw "wrong set"; }
+            if redis.zscore("test:json:zset", "m") != 2.5 { throw "wrong zset"; }
+            if redis.xrange("test:json:stream", "-", "+")[0].id != "1-0" { throw "wrong stream"; }
+
+            if redis.import_json(json, true) != 6 { throw "expected replace to succeed"; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_json_round_trip_control_and_binary() {
+        let client = redis::Client::open(URL).expect("Failed to connect");
+        let mut conn = client.get_connection().expect("Failed to connect");
+        let _: () = redis::cmd("FLUSHDB").query(&mut conn).unwrap();
+
+        let control = "tab\tquote\"nul\0bell\u{7}back\\slash";
+        let binary: &[u8] = &[0xff, 0x00, 0xfe, b'a', 0x80];
+        let _: () = conn.set("test:json:control", control).unwrap();
+        let _: () = conn.set("test:json:binary", binary).unwrap();
+        let _: () = conn.hset("test:json:hash", binary, control).unwrap();
+
+        let mut source = RedisClient::open(URL).expect("Failed to connect");
+        let json = source.export_json("test:json:*").expect("export failed");
+        assert!(!json.contains("\\u{"), "Rust escapes leaked into {}", json);
+        assert!(json.contains("\"encoding\":\"base64\""));
+
+        let _: () = redis::cmd("FLUSHDB").query(&mut conn).unwrap();
+        assert_eq!(source.import_json(&json, false).unwrap(), 3);
+
+        let restored: String = conn.get("test:json:control").unwrap();
+        assert_eq!(restored, control);
+        let restored: Vec<u8> = conn.get("test:json:binary").unwrap();
+        assert_eq!(restored, binary);
+        let restored: String = conn.hget("test:json:hash", binary).unwrap();
+        assert_eq!(restored, control);
+
+        assert!(matches!(
+            source.import_json(&json, false),
+            Err(Error::Backup(_))
+        ));
+    }
+}
@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod cache_tests {
+    use rhai_redis::cache::CacheOptions;
+    use rhai_redis::{Dynamic, RedisClient, RedisEngine};
+
+    fn setup() -> RedisEngine {
+        let redis_client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_cached_computes_once() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:cache");
+            redis.del("test:cache:calls");
+            let compute = || {
+                redis.incr("test:cache:calls");
+                #{name: "ada", scores: [1, 2, 3], ratio: 0.5}
+            };
+
+            let first = redis.cached("test:cache", 60, compute);
+            let second = redis.cached("test:cache", 60, compute);
+            if redis.get("test:cache:calls") != "1" { throw "closure ran more than once"; }
+            if second.name != "ada" || second.scores[2] != 3 || second.ratio != 0.5 {
+                throw "maps and arrays should round-trip: " + second;
+            }
+
+            let ttl = redis.ttl("test:cache");
+            if ttl < 59 || ttl > 66 { throw "ttl should include jitter: " + ttl; }
+        "#;
+
+        engine.run(script).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_cached_errors_and_stale_values() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:cache:err");
+            try {
+                redis.cached("test:cache:err", 60, || { throw "boom"; });
+                throw "expected the error to propagate";
+            } catch (err) {
+                if err != "boom" { throw err; }
+            }
+            if redis.exists("test:cache:err") { throw "errors must not be cached"; }
+            if redis.exists("test:cache:err:lock") { throw "lock not released"; }
+
+            let failed = false;
+            try {
+                redis.cached("test:cache:err", 60, || #{at: timestamp()});
+            } catch {
+                failed = true;
+            }
+            if !failed { throw "values without a JSON form must be rejected"; }
+            if redis.exists("test:cache:err") { throw "unencodable values must not be cached"; }
+            if redis.exists("test:cache:err:lock") { throw "lock not released"; }
+
+            redis.del("test:cache:stale");
+            let options = #{stale_s: 30, jitter: 0.0, beta: 0.0};
+            redis.cached("test:cache:stale", 1, options, || "old");
+            sleep(1100);
+            // Another caller is recomputing, so the stale value is served
+            let lock = redis.lock("test:cache:stale:lock", 5000);
+            let value = redis.cached("test:cache:stale", 1, options, || "new");
+            if value != "old" { throw "expected the stale value, got " + value; }
+            lock.unlock();
+            let value = redis.cached("test:cache:stale", 1, options, || "new");
+            if value != "new" { throw "expected a recomputed value, got " + value; }
+        "#;
+
+        engine.run(script).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_cached_huge_ttl() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:cache:forever");
+            let value = redis.cached("test:cache:forever", 9223372036854775807, #{stale_s: 9223372036854775807}, || "kept");
+            if value != "kept" { throw "unexpected value: " + value; }
+            if redis.get("test:cache:forever") == () { throw "value should be cached"; }
+            if redis.ttl("test:cache:forever") <= 0 { throw "expected a TTL"; }
+        "#;
+
+        engine.run(script).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_get_or_compute_from_rust() {
+        let mut client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+        client.del("test:cache:rust");
+
+        let options = CacheOptions::default();
+        let value = client
+            .get_or_compute("test:cache:rust", 60, &options, || {
+                Ok::<_, String>(Dynamic::from(42_i64))
+            })
+            .unwrap();
+        assert_eq!(value.as_int().unwrap(), 42);
+
+        let value = client
+            .get_or_compute("test:cache:rust", 60, &options, || {
+                Err::<Dynamic, _>("should not run".to_string())
+            })
+            .unwrap();
+        assert_eq!(value.as_int().unwrap(), 42);
+    }
+}
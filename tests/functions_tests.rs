@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod functions_tests {
+    use redis::Client;
+    use rhai_redis::{Error, RedisClient, RedisEngine, RestorePolicy};
+
+    fn setup() -> RedisEngine {
+        let client = Client::open("redis://localhost:6379").expect("Failed to connect");
+        let conn = client.get_connection().expect("Failed to get connection");
+        let redis_client = RedisClient::new(conn);
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine.set_function_key("test:functions");
+        engine
+    }
+
+    const COUNTERS: &str = r#"
+        private fn amount(args) { if args.len() > 0 { parse_int(args[0]) } else { 1 } }
+        fn bump(redis, keys, args) { redis.incrby(keys[0], amount(args)) }
+    "#;
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_function_load_and_fcall() {
+        let mut engine = setup();
+        engine.run(r#"redis.cmd("FLUSHDB", []);"#).unwrap();
+
+        assert_eq!(
+            engine.function_load("counters", COUNTERS, false).unwrap(),
+            1
+        );
+        assert!(engine.function_load("counters", COUNTERS, false).is_err());
+        assert_eq!(engine.function_load("counters", COUNTERS, true).unwrap(), 2);
+
+        // Another library can't claim the same function name
+        assert!(engine
+            .function_load("other", "fn bump(redis, keys, args) { 0 }", false)
+            .is_err());
+
+        let value = engine.fcall("bump", &["test:count"], &["3"]).unwrap();
+        assert_eq!(value.as_int().unwrap(), 3);
+        assert!(matches!(
+            engine.fcall("amount", &[], &[]),
+            Err(Error::NoFunction(_))
+        ));
+
+        let libraries = engine.function_list().unwrap();
+        assert_eq!(libraries.len(), 1);
+        assert_eq!(libraries[0].functions, vec!["bump".to_string()]);
+        assert_eq!(libraries[0].version, 2);
+
+        assert!(engine.function_delete("counters").unwrap());
+        assert!(!engine.function_delete("counters").unwrap());
+        assert!(engine.fcall("bump", &["test:count"], &[]).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_concurrent_function_load() {
+        let mut engine = setup();
+        engine.run(r#"redis.cmd("FLUSHDB", []);"#).unwrap();
+
+        // Only one of several racing loads without `replace` may succeed
+        let loaders: Vec<_> = (0..4)
+            .map(|_| {
+                std::thread::spawn(|| setup().function_load("counters", COUNTERS, false).is_ok())
+            })
+            .collect();
+        let loaded = loaders
+            .into_iter()
+            .map(|loader| loader.join().unwrap())
+            .filter(|&ok| ok)
+            .count();
+        assert_eq!(loaded, 1);
+        assert_eq!(engine.function_list().unwrap()[0].version, 1);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_function_dump_and_restore() {
+        let mut engine = setup();
+        engine.run(r#"redis.cmd("FLUSHDB", []);"#).unwrap();
+
+        engine.function_load("counters", COUNTERS, false).unwrap();
+        let payload = engine.function_dump().unwrap();
+
+        assert!(engine
+            .function_restore(&payload, RestorePolicy::Append)
+            .is_err());
+        engine
+            .function_restore(&payload, RestorePolicy::Flush)
+            .unwrap();
+
+        let libraries = engine.function_list().unwrap();
+        assert_eq!(libraries.len(), 1);
+        assert_eq!(libraries[0].source, COUNTERS);
+        assert_eq!(
+            engine
+                .fcall("bump", &["test:count"], &[])
+                .unwrap()
+                .as_int()
+                .unwrap(),
+            1
+        );
+
+        // A restore that fails part-way leaves the existing libraries alone
+        let broken = r#"{"libraries":[
+            {"name":"extra","source":"fn extra(redis, keys, args) { 1 }"},
+            {"name":"broken","source":"fn broken(redis, keys, args) {"}
+        ]}"#;
+        assert!(engine
+            .function_restore(broken, RestorePolicy::Flush)
+            .is_err());
+        let libraries = engine.function_list().unwrap();
+        assert_eq!(libraries.len(), 1);
+        assert_eq!(libraries[0].name, "counters");
+    }
+}
@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod hashes_tests {
+    use rhai_redis::{RedisClient, RedisEngine};
+
+    fn setup() -> RedisEngine {
+        let redis_client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_hash_commands() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:hash");
+
+            if redis.hset("test:hash", #{name: "Ada", visits: "3", score: "1.5"}) != 3 {
+                throw "hset with a map failed";
+            }
+            if redis.hsetnx("test:hash", "name", "Bob") { throw "hsetnx should not overwrite"; }
+            if redis.hincrby("test:hash", "visits", 2) != 5 { throw "hincrby failed"; }
+            if redis.hincrbyfloat("test:hash", "score", 0.25) != 1.75 { throw "hincrbyfloat failed"; }
+            if redis.hstrlen("test:hash", "name") != 3 { throw "hstrlen failed"; }
+
+            let values = redis.hmget("test:hash", ["name", "missing"]);
+            if values.name != "Ada" || values.missing != () { throw "hmget failed: " + values; }
+
+            let typed = redis.hgetall("test:hash", true);
+            if typed.visits != 5 || typed.score != 1.75 || typed.name != "Ada" {
+                throw "numeric hgetall failed: " + typed;
+            }
+            if redis.hgetall("test:hash").visits != "5" { throw "hgetall should keep strings"; }
+
+            if !redis.hexists("test:hash", redis.hrandfield("test:hash")) { throw "hrandfield failed"; }
+            if redis.hrandfield("test:hash", 10).len() != 3 { throw "hrandfield count failed"; }
+            if redis.hrandfield("test:hash", -5).len() != 5 { throw "negative count should repeat"; }
+            let entry = redis.hrandfield("test:hash", 1, true)[0];
+            if redis.hget("test:hash", entry.field) != entry.value { throw "withvalues failed"; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore] // Requires Redis 8.0
+    fn test_field_expiration() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:hash:ttl");
+            redis.hset("test:hash:ttl", #{a: "1", b: "2"});
+
+            if redis.hexpire("test:hash:ttl", 100, ["a", "missing"]) != [1, -2] { throw "hexpire failed"; }
+            if redis.hexpire("test:hash:ttl", 50, ["a"], "GT") != [0] { throw "GT should fail"; }
+            if redis.hpexpire("test:hash:ttl", 200000, ["a"], "GT") != [1] { throw "hpexpire failed"; }
+
+            let ttls = redis.httl("test:hash:ttl", ["a", "b"]);
+            if ttls[0] <= 100 || ttls[1] != -1 { throw "unexpected ttls: " + ttls; }
+            if redis.hpersist("test:hash:ttl", ["a", "b"]) != [1, -1] { throw "hpersist failed"; }
+
+            if !redis.hsetex("test:hash:ttl", #{c: "3"}, #{ex: 60, fnx: true}) { throw "hsetex failed"; }
+            if redis.hsetex("test:hash:ttl", #{c: "4"}, #{fnx: true}) { throw "fnx should fail"; }
+            if redis.httl("test:hash:ttl", ["c"])[0] <= 0 { throw "hsetex did not set a TTL"; }
+
+            let values = redis.hgetex("test:hash:ttl", ["b", "c"], #{px: 5000});
+            if values.b != "2" || values.c != "3" { throw "hgetex failed"; }
+            if redis.hpttl("test:hash:ttl", ["b"])[0] > 5000 { throw "hgetex did not set a TTL"; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+}
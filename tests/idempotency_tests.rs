@@ -0,0 +1,136 @@
+#[cfg(test)]
+mod idempotency_tests {
+    use rhai_redis::idempotency::OnceOptions;
+    use rhai_redis::{Dynamic, RedisClient, RedisEngine};
+    use std::thread;
+    use std::time::Duration;
+
+    fn setup() -> RedisEngine {
+        let redis_client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_once_returns_stored_result() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:once");
+            redis.del("test:once:calls");
+            let handler = || {
+                redis.incr("test:once:calls");
+                #{status: "applied", items: [1, 2]}
+            };
+
+            let first = redis.once("test:once", 60, handler);
+            let repeat = redis.once("test:once", 60, handler);
+            if redis.get("test:once:calls") != "1" { throw "handler ran twice"; }
+            if repeat.status != "applied" || repeat.items[1] != 2 { throw "unexpected result: " + repeat; }
+            if redis.ttl("test:once") > 60 { throw "result ttl not applied"; }
+        "#;
+
+        engine.run(script).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_once_releases_key_on_error() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:once:err");
+            try {
+                redis.once("test:once:err", 60, || { throw "boom"; });
+                throw "expected the error to propagate";
+            } catch (err) {
+                if err != "boom" { throw err; }
+            }
+            if redis.exists("test:once:err") { throw "failed call should release the key"; }
+            if redis.once("test:once:err", 60, || 7) != 7 { throw "retry should run the handler"; }
+
+            redis.del("test:once:err");
+            let failed = false;
+            try {
+                redis.once("test:once:err", 60, || timestamp());
+            } catch {
+                failed = true;
+            }
+            if !failed { throw "results without a JSON form must be rejected"; }
+            if redis.exists("test:once:err") { throw "rejected result should release the key"; }
+        "#;
+
+        engine.run(script).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_once_waits_for_in_flight_call() {
+        let mut client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+        client.del("test:once:flight");
+
+        let mut other = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+        let first = thread::spawn(move || {
+            other
+                .run_once("test:once:flight", 60, &OnceOptions::default(), || {
+                    thread::sleep(Duration::from_millis(300));
+                    Ok::<_, String>(Dynamic::from(1_i64))
+                })
+                .map(|value| value.as_int().unwrap())
+        });
+        thread::sleep(Duration::from_millis(50));
+
+        let impatient = OnceOptions {
+            wait_ms: 50,
+            ..OnceOptions::default()
+        };
+        let result = client.run_once("test:once:flight", 60, &impatient, || {
+            Ok::<_, String>(Dynamic::from(2_i64))
+        });
+        assert!(result.unwrap_err().contains("still being processed"));
+
+        let result = client
+            .run_once("test:once:flight", 60, &OnceOptions::default(), || {
+                Ok::<_, String>(Dynamic::from(2_i64))
+            })
+            .unwrap();
+        assert_eq!(result.as_int().unwrap(), 1);
+        assert_eq!(first.join().unwrap().unwrap(), 1);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_once_reports_expired_claim() {
+        let mut client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+        client.del("test:once:slow");
+
+        let short = OnceOptions {
+            pending_ms: 100,
+            ..OnceOptions::default()
+        };
+        let result = client.run_once("test:once:slow", 60, &short, || {
+            thread::sleep(Duration::from_millis(200));
+            Ok::<_, String>(Dynamic::from(1_i64))
+        });
+        assert!(result.unwrap_err().contains("expired"));
+    }
+
+    #[test]
+    #[ignore] // Requires RedisBloom
+    fn test_seen_with_bloom_filter() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:seen");
+            if redis.seen("test:seen", "evt-1", false) { throw "check should not report unseen keys"; }
+            if redis.seen("test:seen", "evt-1") { throw "first sighting reported as seen"; }
+            if !redis.seen("test:seen", "evt-1") { throw "repeat not detected"; }
+            if !redis.seen("test:seen", "evt-1", false) { throw "check should find recorded keys"; }
+        "#;
+
+        engine.run(script).unwrap();
+    }
+}
@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod keys_tests {
+    use rhai_redis::{RedisClient, RedisEngine};
+
+    fn setup() -> RedisEngine {
+        let redis_client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_expiry_commands() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del(["test:keys:a", "test:keys:b"]);
+            redis.set("test:keys:a", "1");
+
+            if redis.expire("test:keys:a", 100, "XX") { throw "XX should fail without a TTL"; }
+            if !redis.expire("test:keys:a", 100, "NX") { throw "NX should set the TTL"; }
+            if redis.expire("test:keys:a", 50, "GT") { throw "GT should not lower the TTL"; }
+            if !redis.pexpire("test:keys:a", 200000, "GT") { throw "GT should raise the TTL"; }
+
+            let pttl = redis.pttl("test:keys:a");
+            if pttl <= 100000 || pttl > 200000 { throw "unexpected pttl " + pttl; }
+            if redis.expiretime("test:keys:a") <= 0 { throw "expected an expire time"; }
+
+            if !redis.persist("test:keys:a") { throw "persist failed"; }
+            if redis.expiretime("test:keys:a") != -1 { throw "expected no expire time"; }
+            if redis.expiretime("test:keys:b") != -2 { throw "expected a missing key"; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_key_management() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del(["test:keys:a", "test:keys:b", "test:keys:c"]);
+            redis.set("test:keys:a", "1");
+            redis.rpush("test:keys:list", "x");
+
+            if redis.type("test:keys:a") != "string" { throw "expected a string"; }
+            if redis.type("test:keys:list") != "list" { throw "expected a list"; }
+            if redis.type("test:keys:missing") != "none" { throw "expected none"; }
+
+            if !redis.rename("test:keys:a", "test:keys:b") { throw "rename failed"; }
+            redis.set("test:keys:c", "3");
+            if redis.renamenx("test:keys:b", "test:keys:c") { throw "renamenx should not overwrite"; }
+
+            if redis.copy("test:keys:b", "test:keys:c") { throw "copy should not overwrite"; }
+            if !redis.copy("test:keys:b", "test:keys:c", #{replace: true}) { throw "copy failed"; }
+            if redis.get("test:keys:c") != "1" { throw "copy did not replace"; }
+
+            if redis.exists(["test:keys:b", "test:keys:c", "test:keys:missing"]) != 2 {
+                throw "expected two existing keys";
+            }
+            if redis.touch(["test:keys:b", "test:keys:c"]) != 2 { throw "touch failed"; }
+            if redis.object_encoding("test:keys:b") == () { throw "expected an encoding"; }
+            if redis.object_idletime("test:keys:missing") != () { throw "expected ()"; }
+            if redis.memory_usage("test:keys:b") <= 0 { throw "expected memory usage"; }
+            if redis.randomkey() == () { throw "expected a random key"; }
+
+            if redis.unlink(["test:keys:b", "test:keys:list"]) != 2 { throw "unlink failed"; }
+            if redis.del(["test:keys:c", "test:keys:missing"]) != 1 { throw "del failed"; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+}
@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod leaderboard_tests {
+    use rhai_redis::{RedisClient, RedisEngine};
+
+    fn setup() -> RedisEngine {
+        let redis_client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_submit_and_query() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:board");
+            let board = redis.leaderboard("test:board");
+
+            board.submit("ada", 100.0);
+            if board.submit("ada", 50.0) != 100.0 { throw "best should keep the higher score"; }
+            if board.submit("ada", 150.0) != 150.0 { throw "best should take the higher score"; }
+            board.submit("bob", 40.0, "sum");
+            if board.submit("bob", 40.0, "sum") != 80.0 { throw "sum failed"; }
+            board.submit("eve", 120.0, "latest");
+            if board.submit("eve", 10.0, "latest") != 10.0 { throw "latest failed"; }
+            board.submit("dan", 90.0);
+
+            if board.rank("ada") != 1 || board.rank("eve") != 4 { throw "unexpected ranks"; }
+            if board.rank("missing") != () { throw "expected ()"; }
+            if board.count() != 4 { throw "count failed"; }
+
+            let top = board.top(2);
+            if top[0].member != "ada" || top[1].rank != 2 || top[1].member != "dan" { throw "top failed: " + top; }
+
+            let around = board.around("dan", 1);
+            if around.len() != 3 || around[0].member != "ada" || around[2].member != "bob" {
+                throw "around failed: " + around;
+            }
+            let page = board.page(2, 3);
+            if page.len() != 1 || page[0].rank != 4 { throw "page failed: " + page; }
+
+            try {
+                board.submit("ada", 1.0, "median");
+                throw "expected an unknown mode error";
+            } catch (err) {
+                if !err.contains("median") { throw err; }
+            }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_ascending_and_buckets() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:laps");
+            let laps = redis.leaderboard("test:laps", #{order: "asc"});
+            laps.submit("ada", 61.5);
+            laps.submit("ada", 65.0);
+            laps.submit("bob", 59.0);
+            if laps.score("ada") != 61.5 || laps.rank("bob") != 1 { throw "ascending board failed"; }
+
+            let daily = redis.leaderboard("test:daily", #{period: "daily", keep: 3});
+            let yesterday = daily.previous(1);
+            redis.del([daily.key(), yesterday.key(), "test:daily:total"]);
+
+            if daily.key() == yesterday.key() { throw "buckets should differ"; }
+            daily.submit("ada", 10.0);
+            yesterday.submit("ada", 30.0);
+            yesterday.submit("bob", 5.0);
+            if redis.ttl(daily.key()) <= 86400 { throw "expected a multi-day TTL"; }
+
+            let total = daily.merge(2, "test:daily:total");
+            if total.score("ada") != 30.0 || total.count() != 2 { throw "merge failed"; }
+            let summed = daily.merge(2, "test:daily:total", "sum");
+            if summed.score("ada") != 40.0 { throw "sum merge failed"; }
+            if redis.ttl("test:daily:total") <= 0 { throw "merged board should expire"; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+}
@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod lists_tests {
+    use rhai_redis::{RedisClient, RedisEngine};
+    use std::time::{Duration, Instant};
+
+    fn setup() -> RedisEngine {
+        let redis_client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_list_editing() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del(["test:list:a", "test:list:b"]);
+
+            if redis.rpushx("test:list:a", "x") != 0 { throw "rpushx should skip a missing list"; }
+            if redis.rpush("test:list:a", ["a", "b", "c", "b"]) != 4 { throw "rpush failed"; }
+            if redis.lpushx("test:list:a", ["z"]) != 5 { throw "lpushx failed"; }
+            if redis.linsert("test:list:a", "before", "c", "bb") != 6 { throw "linsert failed"; }
+            if redis.linsert("test:list:a", "after", "missing", "x") != -1 { throw "expected -1"; }
+
+            if redis.lpos("test:list:a", "b") != 2 { throw "lpos failed"; }
+            if redis.lpos("test:list:a", "b", #{rank: -1}) != 5 { throw "lpos rank failed"; }
+            if redis.lpos("test:list:a", "b", #{count: 0}) != [2, 5] { throw "lpos count failed"; }
+            if redis.lpos("test:list:a", "missing") != () { throw "expected ()"; }
+
+            if redis.lrem("test:list:a", 0, "b") != 2 { throw "lrem failed"; }
+            if !redis.ltrim("test:list:a", 1, -1) { throw "ltrim failed"; }
+            if redis.lrange("test:list:a", 0, -1) != ["a", "bb", "c"] { throw "unexpected contents"; }
+
+            if redis.lpop("test:list:a", 0) != [] { throw "lpop 0 should pop nothing"; }
+            if redis.rpop("test:list:a", -1) != [] { throw "rpop -1 should pop nothing"; }
+            if redis.llen("test:list:a") != 3 { throw "a zero count removed an element"; }
+            if redis.lpop("test:list:a", 2) != ["a", "bb"] { throw "lpop count failed"; }
+            if redis.rpop("test:list:a", 5) != ["c"] { throw "rpop count failed"; }
+            if redis.rpop("test:list:a", 1) != [] { throw "expected an empty array"; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_move_and_multi_pop() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del(["test:list:a", "test:list:b", "test:list:c"]);
+            redis.rpush("test:list:a", ["1", "2", "3"]);
+
+            if redis.lmove("test:list:a", "test:list:b", "LEFT", "RIGHT") != "1" { throw "lmove failed"; }
+            if redis.lmove("test:list:c", "test:list:b", "LEFT", "RIGHT") != () { throw "expected ()"; }
+
+            let popped = redis.lmpop(["test:list:c", "test:list:a"], "RIGHT", 5);
+            if popped.key != "test:list:a" || popped.values != ["3", "2"] { throw "lmpop failed"; }
+            if redis.lmpop(["test:list:a"], "LEFT") != () { throw "expected ()"; }
+
+            let item = redis.blpop(["test:list:a", "test:list:b"], 100);
+            if item.key != "test:list:b" || item.value != "1" { throw "blpop failed"; }
+            if redis.brpop("test:list:a", 100) != () { throw "expected a timeout"; }
+            if redis.blmpop(["test:list:a"], "LEFT", 1, 100) != () { throw "expected a timeout"; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_blocking_pop_waits_for_push() {
+        let mut engine = setup();
+        engine.run(r#"redis.del("test:list:wait");"#).unwrap();
+
+        let producer = std::thread::spawn(|| {
+            std::thread::sleep(Duration::from_millis(300));
+            let mut engine = setup();
+            engine
+                .run(r#"redis.rpush("test:list:wait", "job");"#)
+                .unwrap();
+        });
+
+        let start = Instant::now();
+        let script = r#"
+            let job = redis.blmove("test:list:wait", "test:list:working", "LEFT", "RIGHT", 5000);
+            if job != "job" { throw "expected the pushed job, got " + job; }
+            redis.lrem("test:list:working", 1, job);
+        "#;
+        engine.run(script).expect("Script failed");
+        assert!(start.elapsed() < Duration::from_secs(5));
+        producer.join().unwrap();
+    }
+}
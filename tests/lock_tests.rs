@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod lock_tests {
+    use rhai_redis::lock::Redlock;
+    use rhai_redis::{RedisClient, RedisEngine};
+
+    fn setup() -> RedisEngine {
+        let redis_client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_lock_unlock_extend() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:lock");
+            let lock = redis.lock("test:lock", 5000);
+            if lock == () { throw "expected to acquire the lock"; }
+            if lock.token().len() != 32 { throw "unexpected token: " + lock.token(); }
+            if redis.lock("test:lock", 5000) != () { throw "lock should be exclusive"; }
+            if !lock.is_held() { throw "expected the lock to be held"; }
+
+            if !lock.extend(20000) { throw "extend failed"; }
+            if redis.pttl("test:lock") <= 5000 { throw "extend did not reset the ttl"; }
+
+            // A stranger's token must not release the lock
+            redis.set("test:lock", "someone-else");
+            if lock.unlock() { throw "unlock released another holder's lock"; }
+            if lock.extend(1000) { throw "extend touched another holder's lock"; }
+            redis.del("test:lock");
+
+            let lock = redis.lock("test:lock", 5000);
+            if !lock.unlock() { throw "unlock failed"; }
+            if redis.exists("test:lock") { throw "key should be gone"; }
+        "#;
+
+        engine.run(script).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_try_lock_waits_for_expiry() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:lock:wait");
+            let first = redis.lock("test:lock:wait", 200);
+            if redis.try_lock("test:lock:wait", 1000, 20) != () { throw "should time out"; }
+            let second = redis.try_lock("test:lock:wait", 1000, 2000);
+            if second == () { throw "should acquire after expiry"; }
+            if first.unlock() { throw "expired holder must not unlock"; }
+            second.unlock();
+        "#;
+
+        engine.run(script).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_with_lock_releases_on_error() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:lock:with");
+            let value = redis.with_lock("test:lock:with", 5000, || {
+                if !redis.exists("test:lock:with") { throw "lock not held in body"; }
+                42
+            });
+            if value != 42 { throw "expected the closure result"; }
+            if redis.exists("test:lock:with") { throw "lock not released"; }
+
+            try {
+                redis.with_lock("test:lock:with", 5000, || { throw "boom"; });
+                throw "expected the error to propagate";
+            } catch (err) {
+                if err != "boom" { throw err; }
+            }
+            if redis.exists("test:lock:with") { throw "lock not released after error"; }
+
+            let held = redis.lock("test:lock:with", 5000);
+            try {
+                redis.with_lock("test:lock:with", 5000, 50, || { throw "should not run"; });
+                throw "expected an acquisition error";
+            } catch (err) {
+                if !err.contains("Could not acquire") { throw err; }
+            }
+            held.unlock();
+        "#;
+
+        engine.run(script).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_redlock_quorum() {
+        let clients: Vec<RedisClient> = (0..3)
+            .map(|_| RedisClient::open("redis://localhost:6379").expect("Failed to connect"))
+            .collect();
+        let mut probe = clients[0].clone();
+        probe.del("test:redlock");
+
+        // All three clients share one server, so only the first SET succeeds
+        let redlock = Redlock::new(clients);
+        assert!(redlock.lock("test:redlock", 5000).is_none());
+        assert!(!probe.exists("test:redlock"));
+
+        let redlock = Redlock::new(vec![probe.clone()]);
+        let mut guard = redlock.lock("test:redlock", 5000).expect("lock");
+        assert!(guard.validity().as_millis() > 0);
+        assert_eq!(guard.unlock(), 1);
+    }
+}
@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod modules_tests {
+    use redis::Client;
+    use rhai_redis::modules::{EmbeddedModuleResolver, RedisModuleResolver};
+    use rhai_redis::{create_redis_engine, RedisClient, RedisEngine};
+
+    fn setup() -> (RedisEngine, RedisClient) {
+        let client = Client::open("redis://localhost:6379").expect("Failed to connect");
+        let conn = client.get_connection().expect("Failed to get connection");
+        let redis_client = RedisClient::new(conn);
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client.clone());
+        (engine, redis_client)
+    }
+
+    #[test]
+    fn test_embedded_modules() {
+        let mut engine = create_redis_engine().unwrap();
+        let resolver: EmbeddedModuleResolver = [
+            (
+                "lib/math",
+                "fn double(x) { x * 2 } export const ANSWER = 42;",
+            ),
+            ("lib/broken", "fn oops( {"),
+        ]
+        .into_iter()
+        .collect();
+        engine.set_module_resolver(resolver);
+
+        let result: i64 = engine
+            .eval(r#"import "lib/math" as m; m::double(m::ANSWER)"#)
+            .unwrap();
+        assert_eq!(result, 84);
+
+        assert!(engine
+            .eval::<i64>(r#"import "lib/missing" as m; 1"#)
+            .is_err());
+        assert!(engine
+            .eval::<i64>(r#"import "lib/broken" as m; 1"#)
+            .is_err());
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_redis_modules() {
+        let (mut engine, client) = setup();
+        let resolver = RedisModuleResolver::new(client.clone()).with_prefix("test:lib:");
+        engine.set_module_resolver(resolver.clone());
+
+        let script = r#"
+            redis.cmd("FLUSHDB", []);
+            redis.set("test:lib:counter", "fn bump(r, key) { r.incr(key) }");
+            redis.hset("test:libs", "greet", "fn hello(name) { `hello ${name}` }");
+            redis.hset("test:libs", "v2:greet", "fn hello(name) { `hi ${name}` }");
+
+            import "counter" as c;
+            if c::bump(redis, "test:count") != 1 {
+                throw "module function failed";
+            }
+        "#;
+        engine.run(script).expect("Script failed");
+
+        // Replace the source and make sure invalidation picks it up
+        engine
+            .run(r#"redis.set("test:lib:counter", "fn bump(r, key) { r.incrby(key, 10) }");"#)
+            .unwrap();
+        assert!(resolver.invalidate("counter"));
+        engine
+            .run(
+                r#"
+            import "counter" as c;
+            if c::bump(redis, "test:count") != 11 {
+                throw "stale module after invalidation";
+            }
+        "#,
+            )
+            .expect("Script failed");
+
+        engine.set_module_resolver(RedisModuleResolver::from_hash(client.clone(), "test:libs"));
+        engine
+            .run(
+                r#"
+            import "greet" as g;
+            if g::hello("rhai") != "hello rhai" {
+                throw "hash module failed";
+            }
+        "#,
+            )
+            .expect("Script failed");
+
+        // A prefix selects hash fields without leaving hash mode
+        engine.set_module_resolver(
+            RedisModuleResolver::from_hash(client, "test:libs").with_prefix("v2:"),
+        );
+        engine
+            .run(
+                r#"
+            import "greet" as g;
+            if g::hello("rhai") != "hi rhai" {
+                throw "prefixed hash module failed";
+            }
+        "#,
+            )
+            .expect("Script failed");
+    }
+}
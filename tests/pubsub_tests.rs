@@ -0,0 +1,149 @@
+#[cfg(test)]
+mod pubsub_tests {
+    use redis::{Client, Commands};
+    use rhai_redis::pubsub::Subscriber;
+    use rhai_redis::{RedisClient, RedisEngine};
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    fn setup() -> RedisEngine {
+        let redis_client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_subscribe_timeout() {
+        let mut engine = setup();
+
+        let script = r#"
+            let received = redis.subscribe(["test:quiet"], |channel, message| {
+                throw "unexpected message";
+            }, 200);
+            if received != 0 {
+                throw "expected no messages";
+            }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_subscriber_callbacks_and_shutdown() {
+        let client = Client::open("redis://localhost:6379").expect("Failed to connect");
+        let mut conn = client.get_connection().expect("Failed to get connection");
+        let _: () = conn.del("test:events").unwrap();
+
+        let subscriber = Subscriber::spawn(
+            client,
+            r#"
+            while !redis.is_shutdown() {
+                redis.subscribe(["test:events"], |channel, message| {
+                    redis.rpush(channel, message);
+                    message != "last"
+                });
+            }
+        "#,
+        );
+
+        sleep(Duration::from_millis(300));
+        let _: i64 = conn.publish("test:events", "first").unwrap();
+        let _: i64 = conn.publish("test:events", "last").unwrap();
+        sleep(Duration::from_millis(300));
+
+        subscriber.stop().expect("Subscriber failed");
+        let messages: Vec<String> = conn.lrange("test:events", 0, -1).unwrap();
+        assert_eq!(messages, vec!["first", "last"]);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_psubscribe_stops_on_false() {
+        let client = Client::open("redis://localhost:6379").expect("Failed to connect");
+        let mut conn = client.get_connection().expect("Failed to get connection");
+
+        let subscriber = Subscriber::spawn(
+            client,
+            r#"
+            let handled = redis.psubscribe(["test:jobs:*"], |pattern, channel, message| {
+                if pattern != "test:jobs:*" { throw "wrong pattern " + pattern; }
+                false
+            }, 5000);
+            if handled != 1 { throw "expected one message"; }
+        "#,
+        );
+
+        sleep(Duration::from_millis(300));
+        let _: i64 = conn.publish("test:jobs:1", "go").unwrap();
+        subscriber.join().expect("Subscriber failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_ssubscribe_stops_on_false() {
+        let client = Client::open("redis://localhost:6379").expect("Failed to connect");
+        let mut conn = client.get_connection().expect("Failed to get connection");
+
+        let subscriber = Subscriber::spawn(
+            client,
+            r#"
+            let handled = redis.ssubscribe(["test:shard:orders"], |channel, message| {
+                if channel != "test:shard:orders" { throw "wrong channel " + channel; }
+                if message != "placed" { throw "wrong message " + message; }
+                false
+            }, 5000);
+            if handled != 1 { throw "expected one message"; }
+        "#,
+        );
+
+        sleep(Duration::from_millis(300));
+        let _: i64 = redis::cmd("SPUBLISH")
+            .arg("test:shard:orders")
+            .arg("placed")
+            .query(&mut conn)
+            .unwrap();
+        subscriber.join().expect("Subscriber failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_pubsub_introspection() {
+        let client = Client::open("redis://localhost:6379").expect("Failed to connect");
+        let subscriber = Subscriber::spawn(
+            client,
+            r#"redis.subscribe(["test:numsub"], |channel, message| false, 2000);"#,
+        );
+        sleep(Duration::from_millis(300));
+
+        let mut engine = setup();
+        let script = r#"
+            let channels = redis.pubsub_channels("test:*");
+            if !channels.contains("test:numsub") {
+                throw "channel not listed: " + channels.to_string();
+            }
+
+            let counts = redis.pubsub_numsub(["test:numsub", "test:nobody"]);
+            if counts["test:numsub"] != 1 || counts["test:nobody"] != 0 {
+                throw "wrong counts: " + counts.to_string();
+            }
+
+            if type_of(redis.pubsub_numpat()) != "i64" {
+                throw "numpat should be an integer";
+            }
+            if redis.spublish("test:shard", "hello") != 0 {
+                throw "nobody is subscribed to the shard channel";
+            }
+            let shard_counts = redis.pubsub_shardnumsub(["test:shard"]);
+            if shard_counts["test:shard"] != 0 {
+                throw "wrong shard counts: " + shard_counts.to_string();
+            }
+            redis.publish("test:numsub", "done");
+        "#;
+        engine.run(script).expect("Script failed");
+        subscriber.join().expect("Subscriber failed");
+    }
+}
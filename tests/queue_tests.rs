@@ -0,0 +1,132 @@
+#[cfg(test)]
+mod queue_tests {
+    use rhai_redis::{RedisClient, RedisEngine};
+
+    fn setup() -> RedisEngine {
+        let redis_client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    /// Script that deletes every key of the queue `name`
+    fn clean(name: &str) -> String {
+        format!(
+            r#"
+            for suffix in ["pending", "processing", "leases", "delayed", "dead", "jobs", "attempts", "seq"] {{
+                redis.del("{}:" + suffix);
+            }}
+            "#,
+            name
+        )
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_enqueue_reserve_ack() {
+        let mut engine = setup();
+
+        let script = r#"
+            let queue = redis.queue("test:queue");
+            let first = queue.enqueue(#{n: 1});
+            queue.enqueue("second");
+            queue.enqueue("urgent", #{priority: 1});
+
+            let job = queue.reserve(1000);
+            if job.payload != "urgent" { throw "priority job should be first: " + job; }
+            if job.attempts != 1 || job.max_attempts != 3 { throw "unexpected job: " + job; }
+            if !queue.ack(job) { throw "ack failed"; }
+            if queue.ack(job) { throw "second ack should fail"; }
+
+            let job = queue.reserve(1000);
+            if job.id != first || job.payload.n != 1 { throw "expected FIFO order: " + job; }
+            queue.ack(job.id);
+
+            let stats = queue.stats();
+            if stats.pending != 1 || stats.processing != 0 { throw "unexpected stats: " + stats; }
+            queue.ack(queue.reserve(1000));
+            if queue.reserve(100) != () { throw "queue should be empty"; }
+        "#;
+
+        engine
+            .run(&format!("{}{}", clean("test:queue"), script))
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_delayed_nack_and_dead_letter() {
+        let mut engine = setup();
+
+        let script = r#"
+            let queue = redis.queue("test:queue:retry");
+            queue.enqueue("later", #{delay_ms: 300, max_attempts: 2});
+            if queue.stats().delayed != 1 { throw "job should be delayed"; }
+            if queue.reserve(50) != () { throw "delayed job reserved early"; }
+
+            let job = queue.reserve(2000);
+            if job == () || job.payload != "later" { throw "delayed job not promoted"; }
+            if queue.nack(job, 200) != "retry" { throw "first nack should retry"; }
+            if queue.reserve(50) != () { throw "backoff not respected"; }
+
+            let job = queue.reserve(2000);
+            if job.attempts != 2 { throw "expected a second attempt: " + job; }
+            if queue.nack(job) != "dead" { throw "second nack should dead-letter"; }
+            if queue.nack(job) != () { throw "nack of an unreserved job"; }
+
+            let dead = queue.dead_letters(10);
+            if dead.len() != 1 || dead[0].payload != "later" || dead[0].attempts != 2 {
+                throw "unexpected dead letters: " + dead;
+            }
+        "#;
+
+        engine
+            .run(&format!("{}{}", clean("test:queue:retry"), script))
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_visibility_timeout_recovery() {
+        let mut engine = setup();
+
+        let script = r#"
+            let queue = redis.queue("test:queue:lease", #{visibility_ms: 200});
+            queue.enqueue("work");
+            let abandoned = queue.reserve(1000);
+            if queue.recover() != 0 { throw "lease should still be valid"; }
+
+            sleep(300);
+            if queue.recover() != 1 { throw "expired lease not recovered"; }
+            let job = queue.reserve(1000);
+            if job.id != abandoned.id || job.attempts != 2 { throw "unexpected job: " + job; }
+            if queue.ack(abandoned) != true { throw "ack by id should succeed"; }
+        "#;
+
+        engine
+            .run(&format!("{}{}", clean("test:queue:lease"), script))
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_enqueue_control_characters() {
+        let mut engine = setup();
+
+        let script = r#"
+            let queue = redis.queue("test:queue:escape");
+            let payload = "tab\t\"quote\" nul\x00 bell\x07";
+            let id = queue.enqueue(#{text: payload});
+            if type_of(id) != "string" { throw "expected an id, got " + id; }
+
+            let job = queue.reserve(1000);
+            if job.id != id || job.payload.text != payload { throw "payload changed: " + job; }
+            queue.ack(job);
+        "#;
+
+        engine
+            .run(&format!("{}{}", clean("test:queue:escape"), script))
+            .unwrap();
+    }
+}
@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod rate_limit_tests {
+    use rhai_redis::rate_limit::{Algorithm, RateLimit};
+    use rhai_redis::{RedisClient, RedisEngine};
+
+    fn setup() -> RedisEngine {
+        let redis_client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_each_algorithm_enforces_limit() {
+        let mut engine = setup();
+
+        let script = r#"
+            for algorithm in ["fixed_window", "sliding_window", "token_bucket", "gcra"] {
+                let key = "test:rl:" + algorithm;
+                redis.del(key);
+                let options = #{algorithm: algorithm, limit: 3, period_ms: 60000};
+
+                for i in 0..3 {
+                    let result = redis.rate_limit(key, options);
+                    if !result.allowed { throw algorithm + ": request " + i + " denied"; }
+                    if result.remaining != 2 - i { throw algorithm + ": remaining " + result; }
+                    if result.retry_after_ms != 0 { throw algorithm + ": retry " + result; }
+                }
+
+                let denied = redis.rate_limit(key, options);
+                if denied.allowed { throw algorithm + ": fourth request allowed"; }
+                if denied.remaining != 0 { throw algorithm + ": remaining " + denied; }
+                if denied.retry_after_ms <= 0 || denied.retry_after_ms > 120000 {
+                    throw algorithm + ": retry_after_ms " + denied;
+                }
+                if denied.reset_ms <= 0 || denied.reset_ms > 120000 {
+                    throw algorithm + ": reset_ms " + denied;
+                }
+            }
+        "#;
+
+        engine.run(script).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_cost_and_refill() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:rl:bucket");
+            let options = #{algorithm: "token_bucket", limit: 10, period_ms: 1000, cost: 10};
+            if !redis.rate_limit("test:rl:bucket", options).allowed { throw "full bucket denied"; }
+            let denied = redis.rate_limit("test:rl:bucket", options);
+            if denied.allowed { throw "empty bucket allowed"; }
+            sleep(denied.retry_after_ms + 20);
+            if !redis.rate_limit("test:rl:bucket", options).allowed { throw "bucket did not refill"; }
+
+            let peek = redis.rate_limit("test:rl:peek", #{limit: 1, period_ms: 1000, cost: 0});
+            if !peek.allowed || peek.remaining != 1 { throw "cost 0 should not consume: " + peek; }
+
+            let too_big = redis.rate_limit("test:rl:peek", #{limit: 1, period_ms: 1000, cost: 2});
+            if too_big.allowed || too_big.retry_after_ms != -1 { throw "cost above limit: " + too_big; }
+
+            try {
+                redis.rate_limit("test:rl:peek", #{algorithm: "leaky", limit: 1, period_ms: 1000});
+                throw "expected an unknown algorithm error";
+            } catch (err) {
+                if !err.contains("leaky") { throw err; }
+            }
+        "#;
+
+        engine.run(script).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rust_api() {
+        let mut client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+        client.del("test:rl:rust");
+
+        let limit = RateLimit::new(Algorithm::Gcra, 2, 1000);
+        assert!(
+            client
+                .check_rate_limit("test:rl:rust", &limit)
+                .unwrap()
+                .allowed
+        );
+        assert!(
+            client
+                .check_rate_limit("test:rl:rust", &limit)
+                .unwrap()
+                .allowed
+        );
+        let status = client.check_rate_limit("test:rl:rust", &limit).unwrap();
+        assert!(!status.allowed);
+        assert!(status.retry_after_ms > 0 && status.retry_after_ms <= 500);
+
+        let invalid = RateLimit::new(Algorithm::FixedWindow, 0, 1000);
+        assert!(client.check_rate_limit("test:rl:rust", &invalid).is_err());
+    }
+}
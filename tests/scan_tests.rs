@@ -0,0 +1,126 @@
+#[cfg(test)]
+mod scan_tests {
+    use rhai_redis::{Error, RedisClient, RedisEngine};
+    use std::time::{Duration, Instant};
+
+    fn setup() -> RedisEngine {
+        let redis_client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_scan_keys() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.cmd("FLUSHDB", []);
+            for i in 0..250 {
+                redis.set("test:scan:" + i, "x");
+            }
+            redis.hset("test:scan:hash", "field", "value");
+
+            let seen = #{};
+            for key in redis.scan("test:scan:*", #{count: 50}) {
+                seen[key] = true;
+            }
+            if seen.len() != 251 {
+                throw "expected 251 keys, got " + seen.len();
+            }
+
+            let hashes = [];
+            for key in redis.scan("test:scan:*", #{type: "hash"}) {
+                hashes.push(key);
+            }
+            if hashes != ["test:scan:hash"] {
+                throw "unexpected hashes: " + hashes;
+            }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_collection_scans() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:scan:h");
+            redis.del("test:scan:s");
+            redis.del("test:scan:z");
+            redis.hset("test:scan:h", "a", "1");
+            redis.hset("test:scan:h", "b", "2");
+            redis.sadd("test:scan:s", "x");
+            redis.sadd("test:scan:s", "y");
+            redis.zadd("test:scan:z", 1.5, "m");
+
+            let fields = #{};
+            for entry in redis.hscan("test:scan:h") {
+                fields[entry.field] = entry.value;
+            }
+            if fields.a != "1" || fields.b != "2" {
+                throw "unexpected fields: " + fields;
+            }
+
+            // `type` only applies to SCAN and is ignored by the keyed variants
+            let count = 0;
+            for entry in redis.hscan("test:scan:h", "*", #{type: "string", count: 10}) {
+                count += 1;
+            }
+            if count != 2 {
+                throw "expected 2 fields, got " + count;
+            }
+
+            let members = [];
+            for member in redis.sscan("test:scan:s", "x*") {
+                members.push(member);
+            }
+            if members != ["x"] {
+                throw "unexpected members: " + members;
+            }
+
+            let entries = [];
+            for entry in redis.zscan("test:scan:z") {
+                entries.push(entry);
+            }
+            if entries.len() != 1 || entries[0].member != "m" || entries[0].score != 1.5 {
+                throw "unexpected entries: " + entries;
+            }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_scan_respects_operation_limit() {
+        setup()
+            .run(r#"for i in 0..500 { redis.set("test:scan:limit:" + i, "x"); }"#)
+            .expect("Script failed");
+
+        let mut engine = setup();
+        engine.engine().set_max_operations(200);
+        let result = engine.run(r#"for key in redis.scan("test:scan:limit:*") { }"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_sparse_scan_times_out() {
+        setup()
+            .run(r#"for i in 0..5000 { redis.set("test:scan:sparse:" + i, "x"); }"#)
+            .expect("Script failed");
+
+        // Every batch comes back empty, so the cursor makes thousands of round trips
+        let mut engine = setup();
+        engine.set_timeout(Some(Duration::from_millis(50)));
+        let started = Instant::now();
+        let result = engine.run(r#"for key in redis.scan("test:nothing:*", #{count: 1}) { }"#);
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod scripts_tests {
+    use redis::Client;
+    use rhai_redis::{script_sha, Error, RedisClient, RedisEngine};
+
+    fn setup() -> RedisEngine {
+        let client = Client::open("redis://localhost:6379").expect("Failed to connect");
+        let conn = client.get_connection().expect("Failed to get connection");
+        let redis_client = RedisClient::new(conn);
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    fn test_script_sha() {
+        assert_eq!(script_sha(""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert!(matches!(
+            RedisEngine::new().evalsha("abc", &[], &[]),
+            Err(Error::Connection(_))
+        ));
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_script_load_and_evalsha() {
+        let mut engine = setup();
+        engine.set_script_key("test:scripts");
+        engine.script_flush().unwrap();
+
+        let sha = engine
+            .script_load(r#"redis.incrby(KEYS[0], parse_int(ARGV[0]))"#)
+            .unwrap();
+        assert_eq!(
+            engine.script_exists(&[&sha, "missing"]).unwrap(),
+            vec![true, false]
+        );
+
+        engine.run(r#"redis.del("test:counter");"#).unwrap();
+        let total = engine.evalsha(&sha, &["test:counter"], &["5"]).unwrap();
+        assert_eq!(total.as_int().unwrap(), 5);
+
+        // A fresh engine compiles the script from Redis
+        let mut other = setup();
+        other.set_script_key("test:scripts");
+        let total = other.evalsha(&sha, &["test:counter"], &["2"]).unwrap();
+        assert_eq!(total.as_int().unwrap(), 7);
+
+        engine.script_flush().unwrap();
+        assert!(matches!(
+            engine.evalsha(&sha, &[], &[]),
+            Err(Error::NoScript(_))
+        ));
+    }
+}
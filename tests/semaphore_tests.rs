@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod semaphore_tests {
+    use rhai_redis::{RedisClient, RedisEngine};
+    use std::thread;
+    use std::time::Duration;
+
+    fn setup() -> RedisEngine {
+        let redis_client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_semaphore_limits_holders() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:sem");
+            let a = redis.semaphore("test:sem", 2, 5000);
+            let b = redis.semaphore("test:sem", 2, 5000);
+            let c = redis.semaphore("test:sem", 2, 5000);
+
+            if !a.acquire(100) || !b.try_acquire() { throw "first two holders should get permits"; }
+            if c.acquire(100) { throw "third holder should wait"; }
+            if a.available() != 0 { throw "no permits should be available"; }
+
+            if !a.release() { throw "release failed"; }
+            if a.release() { throw "second release should report nothing held"; }
+            if !c.acquire(100) || !c.is_held() { throw "released permit should be reusable"; }
+            if !c.refresh() { throw "refresh failed"; }
+            b.release();
+            c.release();
+            if a.available() != 2 { throw "all permits should be free"; }
+        "#;
+
+        engine.run(script).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_semaphore_drops_stale_holders() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:sem:stale");
+            let crashed = redis.semaphore("test:sem:stale", 1, 200);
+            crashed.acquire(100);
+            let waiting = redis.semaphore("test:sem:stale", 1, 200);
+            if !waiting.acquire(1000) { throw "stale holder should expire"; }
+            if crashed.refresh() { throw "expired permit should not refresh"; }
+        "#;
+
+        engine.run(script).unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_latch_wakes_waiters() {
+        let mut client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+        client.del("test:latch:count");
+        client.del("test:latch:gate");
+        let mut latch = client.latch("test:latch", 2, 60_000);
+        assert!(!latch.wait(50));
+
+        let waiters: Vec<_> = (0..2)
+            .map(|_| {
+                let mut latch = RedisClient::open("redis://localhost:6379")
+                    .expect("Failed to connect")
+                    .latch("test:latch", 2, 60_000);
+                thread::spawn(move || latch.wait(5000))
+            })
+            .collect();
+
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(latch.count_down(), 1);
+        assert_eq!(latch.count_down(), 0);
+        for waiter in waiters {
+            assert!(waiter.join().unwrap());
+        }
+
+        let mut engine = setup();
+        engine
+            .run(
+                r#"
+                let latch = redis.latch("test:latch", 2);
+                if latch.count() != 0 || !latch.wait(10) { throw "latch should stay open"; }
+            "#,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    #[ignore]
+    fn test_latch_expires() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del(["test:latch:ttl:count", "test:latch:ttl:gate"]);
+            let latch = redis.latch("test:latch:ttl", 1, 200);
+            if redis.pttl("test:latch:ttl:count") <= 0 { throw "count key should expire"; }
+            latch.count_down();
+            if redis.pttl("test:latch:ttl:gate") <= 0 { throw "gate key should expire"; }
+
+            sleep(300);
+            // Counting down an expired latch leaves nothing behind
+            if latch.count_down() != 0 { throw "expected no remaining counts"; }
+            if redis.exists("test:latch:ttl:count") { throw "count key was recreated"; }
+
+            // The name can be reused once the old latch has expired
+            let reused = redis.latch("test:latch:ttl", 2);
+            if reused.count() != 2 { throw "expected a fresh latch"; }
+            redis.del(["test:latch:ttl:count", "test:latch:ttl:gate"]);
+        "#;
+
+        engine.run(script).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod sets_tests {
+    use rhai_redis::{RedisClient, RedisEngine};
+
+    fn setup() -> RedisEngine {
+        let redis_client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_set_algebra() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del(["test:set:a", "test:set:b", "test:set:out"]);
+
+            if redis.sadd("test:set:a", ["x", "y", "z"]) != 3 { throw "sadd failed"; }
+            if redis.sadd("test:set:b", ["y", "z", "w"]) != 3 { throw "sadd failed"; }
+            if redis.smismember("test:set:a", ["x", "w"]) != [true, false] { throw "smismember failed"; }
+
+            let inter = redis.sinter(["test:set:a", "test:set:b"]);
+            inter.sort();
+            if inter != ["y", "z"] { throw "sinter failed: " + inter; }
+            if redis.sunion(["test:set:a", "test:set:b"]).len() != 4 { throw "sunion failed"; }
+            if redis.sdiff(["test:set:a", "test:set:b"]) != ["x"] { throw "sdiff failed"; }
+
+            if redis.sinterstore("test:set:out", ["test:set:a", "test:set:b"]) != 2 { throw "sinterstore failed"; }
+            if redis.sunionstore("test:set:out", ["test:set:a", "test:set:b"]) != 4 { throw "sunionstore failed"; }
+            if redis.sdiffstore("test:set:out", ["test:set:b", "test:set:a"]) != 1 { throw "sdiffstore failed"; }
+
+            if redis.sintercard(["test:set:a", "test:set:b"]) != 2 { throw "sintercard failed"; }
+            if redis.sintercard(["test:set:a", "test:set:b"], 1) != 1 { throw "sintercard limit failed"; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_sampling_and_move() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del(["test:set:a", "test:set:b"]);
+            redis.sadd("test:set:a", ["1", "2", "3", "4"]);
+
+            if !redis.sismember("test:set:a", redis.srandmember("test:set:a")) { throw "srandmember failed"; }
+            if redis.srandmember("test:set:a", 10).len() != 4 { throw "srandmember count failed"; }
+            if redis.srandmember("test:set:a", -6).len() != 6 { throw "negative count should repeat"; }
+
+            let popped = redis.spop("test:set:a", 2);
+            if popped.len() != 2 || redis.scard("test:set:a") != 2 { throw "spop count failed"; }
+            if redis.spop("test:set:missing") != () { throw "expected ()"; }
+
+            let member = redis.spop("test:set:a");
+            redis.sadd("test:set:a", member);
+            if !redis.smove("test:set:a", "test:set:b", member) { throw "smove failed"; }
+            if redis.smove("test:set:a", "test:set:b", member) { throw "smove should fail"; }
+            if redis.srem("test:set:b", [member, "missing"]) != 1 { throw "srem failed"; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+}
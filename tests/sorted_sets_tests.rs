@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod sorted_sets_tests {
+    use rhai_redis::{RedisClient, RedisEngine};
+
+    fn setup() -> RedisEngine {
+        let redis_client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_zadd_and_ranks() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del("test:zset");
+
+            if redis.zadd("test:zset", #{a: 1, b: 2, c: 3}) != 3 { throw "zadd map failed"; }
+            if redis.zadd("test:zset", 0.5, "a", #{gt: true, ch: true}) != 0 { throw "GT should not lower"; }
+            if redis.zadd("test:zset", 5.0, "a", #{gt: true, ch: true}) != 1 { throw "GT should raise"; }
+            if redis.zadd("test:zset", 1.0, "a", #{incr: true}) != 6.0 { throw "incr failed"; }
+            if redis.zadd("test:zset", 1.0, "a", #{incr: true, nx: true}) != () { throw "expected ()"; }
+            if redis.zincrby("test:zset", -1.0, "a") != 5.0 { throw "zincrby failed"; }
+
+            if redis.zrank("test:zset", "a") != 2 { throw "zrank failed"; }
+            let rank = redis.zrevrank("test:zset", "a", true);
+            if rank.rank != 0 || rank.score != 5.0 { throw "zrevrank with score failed"; }
+            if redis.zrank("test:zset", "missing") != () { throw "expected ()"; }
+
+            if redis.zcount("test:zset", "(1", "+inf") != 2 { throw "zcount failed"; }
+            if redis.zmscore("test:zset", ["b", "missing"]) != [2.0, ()] { throw "zmscore failed"; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_ranges_and_removal() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del(["test:zset", "test:zset:lex", "test:zset:out"]);
+            redis.zadd("test:zset", #{a: 1, b: 2, c: 3, d: 4});
+            redis.zadd("test:zset:lex", #{a: 0, b: 0, c: 0});
+
+            let top = redis.zrange("test:zset", 0, 1, #{rev: true, withscores: true});
+            if top.len() != 2 || top[0].member != "d" || top[0].score != 4.0 { throw "rev range failed"; }
+            if redis.zrange("test:zset", 2, "+inf", #{byscore: true, limit: [1, 1]}) != ["c"] {
+                throw "byscore with limit failed";
+            }
+            if redis.zrange("test:zset:lex", "[b", "+", #{bylex: true}) != ["b", "c"] { throw "bylex failed"; }
+            if redis.zlexcount("test:zset:lex", "-", "(c") != 2 { throw "zlexcount failed"; }
+            if redis.zrangestore("test:zset:out", "test:zset", 0, 1, #{}) != 2 { throw "zrangestore failed"; }
+
+            if redis.zremrangebyscore("test:zset", "-inf", 1) != 1 { throw "zremrangebyscore failed"; }
+            if redis.zremrangebyrank("test:zset", -1, -1) != 1 { throw "zremrangebyrank failed"; }
+            if redis.zremrangebylex("test:zset:lex", "[a", "[b") != 2 { throw "zremrangebylex failed"; }
+            if redis.zrange("test:zset", 0, -1) != ["b", "c"] { throw "unexpected members"; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_pops_and_combinations() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del(["test:zset:a", "test:zset:b", "test:zset:out"]);
+            redis.zadd("test:zset:a", #{x: 1, y: 2, z: 3});
+            redis.zadd("test:zset:b", #{y: 10, z: 20});
+
+            let union = redis.zunion(["test:zset:a", "test:zset:b"], #{weights: [1, 2], withscores: true});
+            if union[2].member != "z" || union[2].score != 43.0 { throw "weighted zunion failed"; }
+            let inter = redis.zinter(["test:zset:a", "test:zset:b"], #{aggregate: "max", withscores: true});
+            if inter.len() != 2 || inter[1].score != 20.0 { throw "zinter aggregate failed"; }
+            if redis.zdiff(["test:zset:a", "test:zset:b"]) != ["x"] { throw "zdiff failed"; }
+            if redis.zunionstore("test:zset:out", ["test:zset:a", "test:zset:b"]) != 3 { throw "zunionstore failed"; }
+            if redis.zinterstore("test:zset:out", ["test:zset:a", "test:zset:b"], #{aggregate: "min"}) != 2 {
+                throw "zinterstore failed";
+            }
+            if redis.zdiffstore("test:zset:out", ["test:zset:a", "test:zset:b"]) != 1 { throw "zdiffstore failed"; }
+
+            if redis.zpopmin("test:zset:a")[0].member != "x" { throw "zpopmin failed"; }
+            if redis.zpopmax("test:zset:b", 5).len() != 2 { throw "zpopmax count failed"; }
+
+            let popped = redis.zmpop(["test:zset:b", "test:zset:a"], "MAX", 1);
+            if popped.key != "test:zset:a" || popped.entries[0].member != "z" { throw "zmpop failed"; }
+
+            let item = redis.bzpopmin(["test:zset:b", "test:zset:a"], 100);
+            if item.key != "test:zset:a" || item.member != "y" || item.score != 2.0 { throw "bzpopmin failed"; }
+            if redis.bzpopmax("test:zset:a", 100) != () { throw "expected a timeout"; }
+            if redis.bzmpop(["test:zset:a"], "MIN", 1, 100) != () { throw "expected a timeout"; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+}
@@ -0,0 +1,354 @@
+#[cfg(test)]
+mod streams_tests {
+    use redis::Client;
+    use rhai_redis::streams::{StreamWorker, WorkerStats};
+    use rhai_redis::{Error, RedisClient, RedisEngine};
+    use std::time::{Duration, Instant};
+
+    fn setup() -> RedisEngine {
+        let client = Client::open("redis://localhost:6379").expect("Failed to connect");
+        let conn = client.get_connection().expect("Failed to get connection");
+        let redis_client = RedisClient::new(conn);
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_pending_and_claim() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.cmd("FLUSHDB", []);
+            redis.xgroup_create("test:stream", "group", "0-0");
+            redis.xadd("test:stream", "1-1", ["field", "a"]);
+            redis.xadd("test:stream", "1-2", ["field", "b"]);
+            redis.xreadgroup("group", "alice", 10, ["test:stream", ">"]);
+
+            let summary = redis.xpending("test:stream", "group");
+            if summary.count != 2 || summary.consumers.alice != 2 {
+                throw "wrong summary: " + summary.to_string();
+            }
+
+            let pending = redis.xpending("test:stream", "group", "-", "+", 10, "alice");
+            if pending.len() != 2 || pending[0].deliveries != 1 {
+                throw "wrong pending: " + pending.to_string();
+            }
+
+            let claimed = redis.xclaim("test:stream", "group", "bob", 0, ["1-1"]);
+            if claimed[0].fields.field != "a" {
+                throw "wrong claim: " + claimed.to_string();
+            }
+
+            let auto = redis.xautoclaim("test:stream", "group", "bob", 0, "0-0", 10);
+            if auto.next != "0-0" || auto.entries.len() != 2 {
+                throw "wrong autoclaim: " + auto.to_string();
+            }
+
+            if redis.xack("test:stream", "group", ["1-1", "1-2"]) != 2 {
+                throw "ack failed";
+            }
+            if redis.xpending("test:stream", "group").count != 0 {
+                throw "entries still pending";
+            }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_structured_entries() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.cmd("FLUSHDB", []);
+            redis.xadd("test:a", "1-1", #{name: "first", n: 1});
+            redis.xadd("test:a", "1-2", ["name", "second"]);
+            redis.xadd("test:b", "2-1", #{name: "other"});
+
+            let range = redis.xrange("test:a", "-", "+");
+            if range.len() != 2 || range[0].id != "1-1" || range[0].fields.name != "first" {
+                throw "wrong xrange: " + range.to_string();
+            }
+            let rev = redis.xrevrange("test:a", "+", "-");
+            if rev[0].id != "1-2" {
+                throw "wrong xrevrange: " + rev.to_string();
+            }
+
+            let read = redis.xread(10, ["test:a", "test:b", "0", "0"]);
+            if read["test:a"].len() != 2 || read["test:b"][0].fields.name != "other" {
+                throw "wrong xread: " + read.to_string();
+            }
+            if redis.xread(10, ["test:a", "1-2"]).len() != 0 {
+                throw "expected an empty map";
+            }
+
+            redis.xgroup_create("test:a", "group", "0");
+            let group = redis.xreadgroup("group", "alice", 1, ["test:a", ">"]);
+            if group["test:a"][0].id != "1-1" {
+                throw "wrong xreadgroup: " + group.to_string();
+            }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_xadd_options() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.cmd("FLUSHDB", []);
+            if redis.xadd("test:opt", "*", #{a: 1}, #{nomkstream: true}) != () {
+                throw "nomkstream should not create the stream";
+            }
+            for i in 1..=5 {
+                redis.xadd("test:opt", i.to_string() + "-0", #{i: i}, #{maxlen: 3});
+            }
+            if redis.xlen("test:opt") != 3 {
+                throw "maxlen did not trim";
+            }
+            redis.xadd("test:opt", "6-0", #{i: 6}, #{minid: "5-0"});
+            let ids = redis.xrange("test:opt", "-", "+").map(|e| e.id);
+            if ids != ["5-0", "6-0"] {
+                throw "minid did not trim: " + ids.to_string();
+            }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_stream_introspection() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.cmd("FLUSHDB", []);
+            redis.xadd("test:info", "1-1", #{n: 1});
+            redis.xadd("test:info", "1-2", #{n: 2});
+            redis.xgroup_create("test:info", "group", "0");
+            redis.xreadgroup("group", "alice", 1, ["test:info", ">"]);
+
+            let info = redis.xinfo_stream("test:info", false);
+            if info.length != 2 || info.first_entry.id != "1-1" || info.last_entry.fields.n != "2" {
+                throw "wrong stream info: " + info.to_string();
+            }
+            let full = redis.xinfo_stream("test:info", true);
+            if full.entries.len() != 2 || full.groups[0].consumers[0].name != "alice" {
+                throw "wrong full info: " + full.to_string();
+            }
+
+            let groups = redis.xinfo_groups("test:info");
+            if groups[0].pending != 1 || groups[0].last_delivered_id != "1-1" || groups[0].lag != 1 {
+                throw "wrong groups: " + groups.to_string();
+            }
+
+            if !redis.xgroup_createconsumer("test:info", "group", "bob") {
+                throw "consumer not created";
+            }
+            let consumers = redis.xinfo_consumers("test:info", "group");
+            if consumers.len() != 2 || type_of(consumers[0].idle) != "i64" {
+                throw "wrong consumers: " + consumers.to_string();
+            }
+            if redis.xgroup_delconsumer("test:info", "group", "alice") != 1 {
+                throw "alice should have had one pending entry";
+            }
+
+            redis.xgroup_setid("test:info", "group", "0", 0);
+            if redis.xinfo_groups("test:info")[0].last_delivered_id != "0-0" {
+                throw "setid failed";
+            }
+            if !redis.xsetid("test:info", "5-0") || redis.xinfo_stream("test:info").last_generated_id != "5-0" {
+                throw "xsetid failed";
+            }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_blocking_reads() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.cmd("FLUSHDB", []);
+            if redis.xread(10, ["test:block", "$"], 200) != () {
+                throw "expected a timeout";
+            }
+
+            redis.xadd("test:block", "1-1", #{n: 1});
+            let read = redis.xread(10, ["test:block", "0"], 200);
+            if read["test:block"].len() != 1 {
+                throw "wrong read: " + read.to_string();
+            }
+
+            redis.xgroup_create("test:block", "group", "$");
+            if redis.xreadgroup("group", "alice", 10, ["test:block", ">"], 200) != () {
+                throw "expected a group timeout";
+            }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_blocking_read_timeout_and_cancel() {
+        let mut engine = setup();
+        engine.set_timeout(Some(Duration::from_millis(300)));
+
+        let started = Instant::now();
+        let result = engine.run(r#"redis.xread(1, ["test:never", "$"], 0);"#);
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        // The shared connection is still usable after the cancelled read
+        engine.set_timeout(None);
+        engine
+            .run(r#"redis.set("test:after", "ok"); if redis.get("test:after") != "ok" { throw "bad connection"; }"#)
+            .expect("Script failed");
+
+        let token = engine.cancel_token();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            token.cancel();
+        });
+        let result = engine
+            .run(r#"redis.xread(1, ["test:never", "$"], 0); redis.set("test:after", "late");"#);
+        canceller.join().unwrap();
+        assert!(matches!(result, Err(Error::Cancelled)));
+
+        // The cancellation only applies to the run it interrupted
+        engine
+            .run(r#"if redis.get("test:after") != "ok" { throw "script kept running"; }"#)
+            .expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_timeout_is_per_engine() {
+        let client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        // An engine without a timeout shares the client with one that times out
+        let shared = client.clone();
+        let patient = std::thread::spawn(move || {
+            let mut engine = RedisEngine::new();
+            engine.set_redis_client(shared);
+            let started = Instant::now();
+            engine
+                .run(r#"redis.xread(1, ["test:never", "$"], 600);"#)
+                .map(|_| started.elapsed())
+        });
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(client);
+        engine.set_timeout(Some(Duration::from_millis(100)));
+        let result = engine.run(r#"redis.xread(1, ["test:never", "$"], 0);"#);
+        assert!(matches!(result, Err(Error::Cancelled)));
+
+        let waited = patient.join().unwrap().expect("Script failed");
+        assert!(waited >= Duration::from_millis(500));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_stream_worker_retries_and_dead_letters() {
+        let mut engine = setup();
+        engine
+            .run(
+                r#"
+            redis.cmd("FLUSHDB", []);
+            redis.cmd("XGROUP", ["CREATE", "test:jobs", "workers", "0", "MKSTREAM"]);
+            redis.xadd("test:jobs", "*", ["amount", "5"]);
+            redis.xadd("test:jobs", "*", ["amount", "oops"]);
+        "#,
+            )
+            .unwrap();
+
+        let mut worker = StreamWorker::new(
+            engine,
+            "test:jobs",
+            "workers",
+            "worker-1",
+            r#"redis.incrby("test:total", parse_int(entry.fields.amount));"#,
+        )
+        .unwrap()
+        .with_block_ms(100)
+        .with_min_idle_ms(0)
+        .with_max_deliveries(2);
+
+        let first = worker.run_once().unwrap();
+        assert_eq!(
+            first,
+            WorkerStats {
+                processed: 1,
+                failed: 1,
+                dead_lettered: 0
+            }
+        );
+
+        let retried = worker.reclaim().unwrap();
+        assert_eq!(retried.failed, 1);
+        assert_eq!(retried.dead_lettered, 1);
+
+        let mut check = setup();
+        check
+            .run(
+                r#"
+            if redis.get("test:total") != "5" { throw "handler did not run"; }
+            if redis.xpending("test:jobs", "workers").count != 0 { throw "entries still pending"; }
+            let dead = redis.xrange("test:jobs:dead", "-", "+");
+            if dead.len() != 1 || dead[0].fields.amount != "oops" || dead[0].fields._deliveries != "2" {
+                throw "wrong dead letters: " + dead.to_string();
+            }
+        "#,
+            )
+            .expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_stream_worker_leaves_timed_out_entries_pending() {
+        let mut engine = setup();
+        engine
+            .run(
+                r#"
+            redis.cmd("FLUSHDB", []);
+            redis.cmd("XGROUP", ["CREATE", "test:slow", "workers", "0", "MKSTREAM"]);
+            redis.xadd("test:slow", "*", ["kind", "slow"]);
+        "#,
+            )
+            .unwrap();
+        engine.set_timeout(Some(Duration::from_millis(100)));
+
+        let mut worker = StreamWorker::new(
+            engine,
+            "test:slow",
+            "workers",
+            "worker-1",
+            "loop { sleep(10); }",
+        )
+        .unwrap()
+        .with_block_ms(100)
+        .with_max_deliveries(1);
+
+        // A timeout is neither a failure nor a reason to dead-letter the entry
+        assert_eq!(worker.run_once().unwrap(), WorkerStats::default());
+
+        let mut check = setup();
+        check
+            .run(
+                r#"
+            if redis.xpending("test:slow", "workers").count != 1 { throw "entry should stay pending"; }
+            if redis.exists("test:slow:dead") { throw "entry should not be dead-lettered"; }
+        "#,
+            )
+            .expect("Script failed");
+    }
+}
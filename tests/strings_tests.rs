@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod strings_tests {
+    use rhai_redis::{RedisClient, RedisEngine};
+
+    fn setup() -> RedisEngine {
+        let redis_client = RedisClient::open("redis://localhost:6379").expect("Failed to connect");
+
+        let mut engine = RedisEngine::new();
+        engine.set_redis_client(redis_client);
+        engine
+    }
+
+    #[test]
+    #[ignore] // Run with: cargo test -- --ignored
+    fn test_set_options() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del(["test:str:a", "test:str:b"]);
+
+            if !redis.set("test:str:a", "1", #{nx: true, px: 60000}) { throw "nx set failed"; }
+            if redis.set("test:str:a", "2", #{nx: true}) { throw "nx should fail"; }
+            if redis.set("test:str:b", "2", #{xx: true}) { throw "xx should fail"; }
+            if redis.pttl("test:str:a") <= 0 { throw "expected a TTL"; }
+
+            let previous = redis.set("test:str:a", "3", #{get: true, keepttl: true});
+            if previous != "1" { throw "expected the previous value, got " + previous; }
+            if redis.pttl("test:str:a") <= 0 { throw "keepttl lost the TTL"; }
+            if redis.set("test:str:b", "x", #{get: true}) != () { throw "expected ()"; }
+
+            if redis.getex("test:str:a", #{persist: true}) != "3" { throw "getex failed"; }
+            if redis.ttl("test:str:a") != -1 { throw "persist failed"; }
+            if redis.getdel("test:str:a") != "3" || redis.exists("test:str:a") { throw "getdel failed"; }
+            if redis.getset("test:str:b", "y") != "x" { throw "getset failed"; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_multi_key_and_string_commands() {
+        let mut engine = setup();
+
+        let script = r#"
+            redis.del(["test:str:a", "test:str:b", "test:str:c", "test:str:f"]);
+
+            if !redis.mset(#{"test:str:a": "1", "test:str:b": "2"}) { throw "mset failed"; }
+            if redis.mget(["test:str:a", "test:str:missing", "test:str:b"]) != ["1", (), "2"] {
+                throw "mget failed";
+            }
+            if redis.msetnx(["test:str:b", "x", "test:str:c", "y"]) { throw "msetnx should fail"; }
+            if redis.exists("test:str:c") { throw "msetnx set a key"; }
+
+            if !redis.setnx("test:str:c", "hello") { throw "setnx failed"; }
+            if redis.append("test:str:c", " world") != 11 { throw "append failed"; }
+            if redis.strlen("test:str:c") != 11 { throw "strlen failed"; }
+            if redis.getrange("test:str:c", 0, 4) != "hello" { throw "getrange failed"; }
+            if redis.setrange("test:str:c", 6, "there") != 11 { throw "setrange failed"; }
+            if redis.get("test:str:c") != "hello there" { throw "setrange wrote the wrong value"; }
+
+            if !redis.setex("test:str:f", 60, "1.5") { throw "setex failed"; }
+            if redis.incrbyfloat("test:str:f", 0.25) != 1.75 { throw "incrbyfloat failed"; }
+
+            redis.mset(#{"test:str:a": "ohmytext", "test:str:b": "mynewtext"});
+            if redis.lcs("test:str:a", "test:str:b") != "mytext" { throw "lcs failed"; }
+            if redis.lcs("test:str:a", "test:str:b", #{len: true}) != 6 { throw "lcs len failed"; }
+            let result = redis.lcs("test:str:a", "test:str:b", #{idx: true, minmatchlen: 4});
+            if result.len != 6 || result.matches.len() != 1 { throw "lcs idx failed: " + result; }
+        "#;
+
+        engine.run(script).expect("Script failed");
+    }
+}